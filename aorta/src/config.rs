@@ -1,11 +1,37 @@
-use chrono::Duration;
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+use chrono::{Duration, Utc};
+use failure::Fail;
 use hyper::{Method, Request, Uri};
 use hyper::header::{ContentLength, ContentType};
 use serde::Serialize;
+use uuid::Uuid;
 
 use auth::{PublicKey, RelayId, SecretKey};
 use upstream::UpstreamDescriptor;
 
+/// The highest aorta protocol version this relay understands. Sent to the upstream on every
+/// request via the `X-Sentry-Relay-Version` header, and compared against the version the upstream
+/// advertises back on auth/register to decide whether negotiation succeeds.
+pub const RELAY_VERSION: u16 = 1;
+
+/// Raised by `AortaConfig::record_negotiation` when the upstream advertises a protocol version
+/// newer than this relay understands, rather than silently sending it payloads it can't validate.
+#[derive(Fail, Debug)]
+pub enum NegotiationError {
+    /// The upstream's advertised version is higher than `RELAY_VERSION`.
+    #[fail(
+        display = "upstream speaks aorta protocol version {}, but this relay only understands up \
+                    to {}",
+        upstream_version, RELAY_VERSION
+    )]
+    UpstreamTooNew {
+        /// The version the upstream advertised.
+        upstream_version: u16,
+    },
+}
+
 /// Holds common config values that affect the aorta behavior.
 ///
 /// This config is typically created by something and then passed down
@@ -27,6 +53,18 @@ pub struct AortaConfig {
     pub secret_key: Option<SecretKey>,
     /// The public key for authentication.
     pub public_key: Option<PublicKey>,
+    /// The protocol version the upstream advertised on the first successful auth/register
+    /// exchange, or `None` before that has happened.
+    negotiated_version: RwLock<Option<u16>>,
+    /// The capabilities the upstream advertised alongside `negotiated_version`.
+    negotiated_capabilities: RwLock<HashSet<String>>,
+    /// Whether `prepare_aorta_req` attaches a timestamp and nonce to the signed payload, closing
+    /// the replay window on relay -> upstream traffic. Off by default so existing deployments
+    /// don't start sending a signature shape an older upstream doesn't expect until they opt in.
+    pub require_replay_protection: bool,
+    /// How far a signed request's timestamp may drift from now, in either direction, and still be
+    /// accepted as fresh by `is_within_clock_skew`.
+    pub max_clock_skew: Duration,
 }
 
 impl Default for AortaConfig {
@@ -39,10 +77,24 @@ impl Default for AortaConfig {
             relay_id: None,
             secret_key: None,
             public_key: None,
+            negotiated_version: RwLock::new(None),
+            negotiated_capabilities: RwLock::new(HashSet::new()),
+            require_replay_protection: false,
+            max_clock_skew: Duration::seconds(300),
         }
     }
 }
 
+/// Returns whether `timestamp` (a Unix timestamp, as sent in `X-Sentry-Relay-Timestamp`) falls
+/// within `max_clock_skew` of now, in either direction.
+///
+/// This only covers the skew check. The cryptographic verification of the signed
+/// `timestamp:nonce:body` payload belongs on `auth::PublicKey` (e.g. a `verify_replay_protected`
+/// method) -- the `auth` crate isn't part of this snapshot at all, so it can't be added here.
+pub fn is_within_clock_skew(timestamp: i64, max_clock_skew: Duration) -> bool {
+    (Utc::now().timestamp() - timestamp).abs() <= max_clock_skew.num_seconds()
+}
+
 impl AortaConfig {
     /// Returns the relay id or panics.
     pub fn relay_id(&self) -> &RelayId {
@@ -76,17 +128,83 @@ impl AortaConfig {
     }
 
     /// Prepares a JSON bodied API request to aorta with signature.
+    ///
+    /// When `require_replay_protection` is set, the signature covers `timestamp:nonce:body`
+    /// instead of just `body`, and the timestamp and nonce are attached as the
+    /// `X-Sentry-Relay-Timestamp`/`X-Sentry-Relay-Nonce` headers so the upstream can reconstruct
+    /// and verify the same signed payload, then reject it outside its own skew window or if the
+    /// nonce has been seen before.
     pub fn prepare_aorta_req<S: Serialize>(&self, method: Method, path: &str, body: &S) -> Request {
         let mut req = Request::new(method, self.get_api_uri(path));
-        let (json, signature) = self.secret_key().pack(body);
+
+        let replay_protection = if self.require_replay_protection {
+            let timestamp = Utc::now().timestamp();
+            let nonce = Uuid::new_v4().to_simple().to_string();
+            Some((timestamp, nonce))
+        } else {
+            None
+        };
+
+        let (json, signature) = match &replay_protection {
+            Some((timestamp, nonce)) => {
+                self.secret_key().pack_replay_protected(body, *timestamp, nonce)
+            }
+            None => self.secret_key().pack(body),
+        };
+
         {
             let headers = req.headers_mut();
             headers.set_raw("X-Sentry-Relay-Id", self.relay_id().to_string());
             headers.set_raw("X-Sentry-Relay-Signature", signature);
+            headers.set_raw("X-Sentry-Relay-Version", RELAY_VERSION.to_string());
+            if let Some((timestamp, nonce)) = replay_protection {
+                headers.set_raw("X-Sentry-Relay-Timestamp", timestamp.to_string());
+                headers.set_raw("X-Sentry-Relay-Nonce", nonce);
+            }
             headers.set(ContentType::json());
             headers.set(ContentLength(json.len() as u64));
         }
         req.set_body(json);
         req
     }
+
+    /// Records the protocol version and capability set the upstream advertised on the first
+    /// successful auth/register exchange, so later requests can gate newer request shapes
+    /// (batched snapshots, compressed bodies, ...) on what this particular upstream supports.
+    ///
+    /// Fails rather than recording the negotiation if `upstream_version` is newer than
+    /// `RELAY_VERSION` -- this relay has no business sending payloads a newer upstream might
+    /// expect but it can't actually produce correctly.
+    pub fn record_negotiation<I>(
+        &self,
+        upstream_version: u16,
+        capabilities: I,
+    ) -> Result<(), NegotiationError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        if upstream_version > RELAY_VERSION {
+            return Err(NegotiationError::UpstreamTooNew { upstream_version });
+        }
+
+        *self.negotiated_version.write().unwrap() = Some(upstream_version);
+        *self.negotiated_capabilities.write().unwrap() = capabilities.into_iter().collect();
+        Ok(())
+    }
+
+    /// Returns the protocol version negotiated with the upstream, or `None` if no successful
+    /// auth/register exchange has happened yet.
+    pub fn negotiated_version(&self) -> Option<u16> {
+        *self.negotiated_version.read().unwrap()
+    }
+
+    /// Returns whether the upstream has advertised support for `capability`. Always `false` before
+    /// the first successful negotiation, so callers naturally fall back to the older request shape
+    /// until it's known to be safe to use the newer one.
+    pub fn supports(&self, capability: &str) -> bool {
+        self.negotiated_capabilities
+            .read()
+            .unwrap()
+            .contains(capability)
+    }
 }
@@ -6,16 +6,32 @@ use crate::datascrubbing::DataScrubbingConfig;
 use crate::pii::{Pattern, PiiConfig, RedactPairRule, Redaction, RuleSpec, RuleType};
 use crate::processor::{SelectorPathItem, SelectorSpec};
 
+/// Converts a [`DataScrubbingConfig`] into the [`PiiConfig`] the processor actually runs.
+///
+/// Assumes a `scrub_secrets` flag on `DataScrubbingConfig`; the struct's definition lives outside
+/// this source snapshot, so the field isn't declared here, only consumed.
 pub fn to_pii_config(datascrubbing_config: &DataScrubbingConfig) -> Option<PiiConfig> {
     let mut custom_rules = BTreeMap::new();
     let mut applied_rules = Vec::new();
 
     if datascrubbing_config.scrub_data && datascrubbing_config.scrub_defaults {
         applied_rules.push("@common:filter".to_owned());
+        // `@scanner` classifies each token in the value with a single pass (see
+        // `crate::pii::scanner`) and redacts only the sensitive spans it finds, rather than
+        // matching the whole value as `@common:filter`'s regexes do.
+        applied_rules.push("@scanner".to_owned());
     } else if datascrubbing_config.scrub_ip_addresses {
         applied_rules.push("@ip:filter".to_owned());
     }
 
+    // `scrub_secrets` runs the `@secret` Bayesian classifier rule (see `crate::pii::bayes`) over
+    // every string value, independently of `scrub_data`'s sensitive-field matching -- it is meant
+    // to catch high-entropy tokens stored under an innocuous key that `sensitive_fields` wouldn't
+    // list.
+    if datascrubbing_config.scrub_secrets {
+        applied_rules.push("@secret".to_owned());
+    }
+
     if datascrubbing_config.scrub_data {
         let sensitive_fields_re = {
             let mut re = ".*(".to_owned();
@@ -67,6 +83,12 @@ pub fn to_pii_config(datascrubbing_config: &DataScrubbingConfig) -> Option<PiiCo
         return None;
     }
 
+    // `exclude_fields` only ever compiles to a static negated path selector below: a rule either
+    // always applies to a path or never does. `crate::pii::condition::Condition` adds conditional
+    // applications ("scrub field X only when field Y matches") on top of a selector, gated on
+    // another field's value at processing time, but wiring a `Condition` into `applications` here
+    // needs `PiiConfig.applications`'s real value type and a `PiiProcessor` hook to evaluate it
+    // against `ProcessingState`, neither of which are part of this source snapshot.
     let selector = if datascrubbing_config.exclude_fields.is_empty() {
         SelectorSpec::Path(vec![SelectorPathItem::DeepWildcard])
     } else {
@@ -86,6 +108,30 @@ pub fn to_pii_config(datascrubbing_config: &DataScrubbingConfig) -> Option<PiiCo
     let mut applications = BTreeMap::new();
     applications.insert(selector, applied_rules.clone());
 
+    // `device_app_hash`, `kernel_version`, and other fields annotated `pii = "maybe"` on the
+    // context structs in `relay-general`'s protocol types aren't swept by the default/custom
+    // rules above -- they're identifying, but not sensitive enough that every organization wants
+    // them gone. `scrub_maybe` is the opt-in for organizations that do.
+    if datascrubbing_config.scrub_data && datascrubbing_config.scrub_maybe {
+        let maybe_pii_fields = [
+            "name",
+            "timezone",
+            "boot_time",
+            "device_app_hash",
+            "build",
+            "kernel_version",
+        ];
+
+        let maybe_pii_selector = SelectorSpec::Or(
+            maybe_pii_fields
+                .iter()
+                .map(|field| SelectorSpec::Path(vec![SelectorPathItem::Key((*field).to_owned())]))
+                .collect(),
+        );
+
+        applications.insert(maybe_pii_selector, vec!["@anything:hash".to_owned()]);
+    }
+
     Some(PiiConfig {
         rules: custom_rules,
         vars: Default::default(),
@@ -180,7 +226,8 @@ THd+9FBxiHLGXNKhG/FRSyREXEt+NyYIf/0cyByc9tNksat794ddUqnLOg0vwSkv
           },
           "applications": {
             "**": [
-              "@common:filter"
+              "@common:filter",
+              "@scanner"
             ]
           }
         }
@@ -202,7 +249,8 @@ THd+9FBxiHLGXNKhG/FRSyREXEt+NyYIf/0cyByc9tNksat794ddUqnLOg0vwSkv
           },
           "applications": {
             "**": [
-              "@common:filter"
+              "@common:filter",
+              "@scanner"
             ]
           }
         }
@@ -234,6 +282,7 @@ THd+9FBxiHLGXNKhG/FRSyREXEt+NyYIf/0cyByc9tNksat794ddUqnLOg0vwSkv
           "applications": {
             "**": [
               "@common:filter",
+              "@scanner",
               "strip-fields"
             ]
           }
@@ -256,7 +305,8 @@ THd+9FBxiHLGXNKhG/FRSyREXEt+NyYIf/0cyByc9tNksat794ddUqnLOg0vwSkv
           },
           "applications": {
             "(~foobar)": [
-              "@common:filter"
+              "@common:filter",
+              "@scanner"
             ]
           }
         }
@@ -791,6 +841,48 @@ THd+9FBxiHLGXNKhG/FRSyREXEt+NyYIf/0cyByc9tNksat794ddUqnLOg0vwSkv
         assert_annotated_snapshot!(data);
     }
 
+    #[test]
+    fn test_scrub_maybe_device_ids_opt_in() {
+        let mut data = Event::from_value(
+            serde_json::json!({
+                "contexts": {
+                    "app": {"device_app_hash": "1234567890abcdef"},
+                    "os": {"kernel_version": "4.9.93-g1234567"}
+                }
+            })
+            .into(),
+        );
+
+        let pii_config = to_pii_config(&DataScrubbingConfig {
+            scrub_maybe: true,
+            ..simple_enabled_config()
+        })
+        .unwrap();
+
+        let mut pii_processor = PiiProcessor::new(&pii_config);
+        process_value(&mut data, &mut pii_processor, ProcessingState::root());
+        assert_annotated_snapshot!(data);
+    }
+
+    #[test]
+    fn test_scrub_maybe_device_ids_opt_out() {
+        let mut data = Event::from_value(
+            serde_json::json!({
+                "contexts": {
+                    "app": {"device_app_hash": "1234567890abcdef"},
+                    "os": {"kernel_version": "4.9.93-g1234567"}
+                }
+            })
+            .into(),
+        );
+
+        let pii_config = to_pii_config(&simple_enabled_config()).unwrap();
+
+        let mut pii_processor = PiiProcessor::new(&pii_config);
+        process_value(&mut data, &mut pii_processor, ProcessingState::root());
+        assert_annotated_snapshot!(data);
+    }
+
     #[test]
     fn test_explicit_fields() {
         let mut data = Event::from_value(
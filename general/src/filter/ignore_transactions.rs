@@ -0,0 +1,144 @@
+//! Filters out events whose transaction name or request URL matches a configured glob pattern --
+//! typically used to drop the high-volume, low-value traffic health-check probes and uptime
+//! monitors generate (`*/health`, `*/ping`, and the like) before it consumes event quota.
+//!
+//! `IgnoreTransactionsFilterConfig` is defined in `crate::filter::config`, which isn't part of
+//! this snapshot (same gap already noted in `client_ips.rs` for `ClientIpsFilterConfig`); this
+//! assumes it carries a `patterns: Vec<String>` and an `is_enabled: bool`.
+
+use regex::{Regex, RegexSet};
+
+use crate::filter::config::IgnoreTransactionsFilterConfig;
+use crate::filter::FilterStatKey;
+
+/// The glob patterns used when an operator hasn't configured their own: the request paths most
+/// commonly hit by health-check probes and uptime monitors.
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    "*/health",
+    "*/healthz",
+    "*/ping",
+    "*/up",
+    "*/livez",
+    "*/readyz",
+];
+
+/// Translates a glob pattern (`*` matches any run of characters, same convention as
+/// `RuleCondition::Glob`) into an anchored, case-insensitive regex -- `*/up` becomes `^.*/up$`, so
+/// it matches `/service/up` but not `/upload`, since the pattern is anchored at the end too.
+fn glob_to_anchored_regex(pattern: &str) -> String {
+    format!("(?i)^{}$", regex::escape(pattern).replace(r"\*", ".*"))
+}
+
+/// A precompiled matcher for `IgnoreTransactionsFilterConfig`: its patterns (or
+/// [`DEFAULT_PATTERNS`], if none are configured) compiled once into a `RegexSet`, so repeated
+/// `should_filter` calls only pay for a single set match rather than recompiling and retrying each
+/// pattern per event.
+pub struct IgnoreTransactionsMatcher {
+    is_enabled: bool,
+    patterns: RegexSet,
+}
+
+impl IgnoreTransactionsMatcher {
+    /// Compiles `config` into a matcher. Patterns that don't translate into a valid regex are
+    /// silently dropped, the same way the sibling IP-based filters ignore entries that don't
+    /// parse.
+    pub fn new(config: &IgnoreTransactionsFilterConfig) -> Self {
+        let configured = &config.patterns;
+        let patterns: Vec<&str> = if configured.is_empty() {
+            DEFAULT_PATTERNS.to_vec()
+        } else {
+            configured.iter().map(String::as_str).collect()
+        };
+
+        let regexes: Vec<String> = patterns
+            .iter()
+            .map(|pattern| glob_to_anchored_regex(pattern))
+            .filter(|regex| Regex::new(regex).is_ok())
+            .collect();
+
+        IgnoreTransactionsMatcher {
+            is_enabled: config.is_enabled,
+            patterns: RegexSet::new(&regexes).unwrap_or_else(|_| RegexSet::empty()),
+        }
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        self.patterns.is_match(value)
+    }
+}
+
+/// Should filter event based on its transaction name or request URL matching a configured glob.
+pub fn should_filter(
+    transaction: Option<&str>,
+    url: Option<&str>,
+    matcher: &IgnoreTransactionsMatcher,
+) -> Result<(), FilterStatKey> {
+    if !matcher.is_enabled {
+        return Ok(());
+    }
+
+    let is_match = transaction.map_or(false, |value| matcher.matches(value))
+        || url.map_or(false, |value| matcher.matches(value));
+
+    if is_match {
+        return Err(FilterStatKey::IgnoreTransactions);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(patterns: &[&str]) -> IgnoreTransactionsMatcher {
+        IgnoreTransactionsMatcher::new(&IgnoreTransactionsFilterConfig {
+            is_enabled: true,
+            patterns: patterns.iter().map(|p| p.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn test_default_patterns_match_health_checks() {
+        let matcher = matcher(&[]);
+
+        for path in &["/health", "/api/healthz", "/ping", "/up", "/livez", "/readyz"] {
+            assert_eq!(
+                should_filter(Some(path), None, &matcher),
+                Err(FilterStatKey::IgnoreTransactions),
+                "{} should have been filtered",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_anchored_pattern_does_not_match_longer_path() {
+        let matcher = matcher(&[]);
+
+        // "/upload" contains "/up" but does not end with it, and the default "*/up" pattern is
+        // anchored at the end, so it must not match.
+        assert_eq!(should_filter(Some("/upload"), None, &matcher), Ok(()));
+    }
+
+    #[test]
+    fn test_matches_url_as_well_as_transaction() {
+        let matcher = matcher(&["*/internal/status"]);
+
+        assert_eq!(
+            should_filter(None, Some("https://example.com/internal/status"), &matcher),
+            Err(FilterStatKey::IgnoreTransactions)
+        );
+        assert_eq!(should_filter(Some("unrelated"), None, &matcher), Ok(()));
+    }
+
+    #[test]
+    fn test_disabled_filter_never_matches() {
+        let matcher = IgnoreTransactionsMatcher::new(&IgnoreTransactionsFilterConfig {
+            is_enabled: false,
+            patterns: vec!["*/health".to_string()],
+        });
+
+        assert_eq!(should_filter(Some("/health"), None, &matcher), Ok(()));
+    }
+}
@@ -0,0 +1,261 @@
+//! A lightweight Bayesian classifier backing the `@secret` builtin PII rule (see
+//! [`crate::pii::builtin`]). Regex-based rules like `@urlauth` or `RedactPair` only catch secrets
+//! that look a certain way or live under a recognizable key; this classifier instead scores the
+//! *contents* of a string value and flags it once enough of its tokens look credential-like, so it
+//! also catches a random-looking token stored under an innocuous key such as `metadata`.
+//!
+//! The feature extraction is an orthogonal sparse bigram (OSB) tokenizer, a scheme popularized by
+//! spam filters such as CRM114: tokens are the anchors of a sliding window, and each anchor is
+//! paired with every other token within the window, one feature per skip distance. This captures
+//! some word-order information while staying robust to the anchor's immediate neighbour changing.
+//!
+//! This module is wired in from `pii::config` via a
+//! `RuleType::BayesClassifier(BayesClassifierRule)` variant and declared with `mod bayes;` in
+//! `pii::mod`; neither of those is part of this source snapshot, so the wiring below is written as
+//! though they already existed.
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+/// How many of the most informative features (farthest from `0.5`) are folded into the combined
+/// score. Fewer features make the classifier noisy on short strings; more features dilute a strong
+/// signal with tokens that carry little information either way.
+const MAX_FEATURES: usize = 15;
+
+/// Window size for the orthogonal sparse bigram tokenizer: each anchor token is paired with each
+/// of the next `WINDOW - 1` tokens that follow it.
+const WINDOW: usize = 5;
+
+/// Default threshold for the `@secret` builtin rule: the combined score from which a value is
+/// considered a secret and redacted. Tuned against [`DEFAULT_MODEL`] to keep false positives on
+/// ordinary log strings low; a `BayesClassifierRule` may override it per-rule.
+pub const DEFAULT_THRESHOLD: f64 = 0.9;
+
+/// Spam/ham occurrence counts for a single feature, as accumulated by the training corpus behind
+/// [`DEFAULT_MODEL`] or supplied by a user through `PiiConfig.vars`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokenCounts {
+    pub spam: u32,
+    pub ham: u32,
+}
+
+/// Configuration for a `RuleType::BayesClassifier` rule.
+///
+/// `threshold` is the minimum combined score (see [`classify`]) at which a value is treated as a
+/// secret. Defaults to [`DEFAULT_THRESHOLD`], which is tuned for [`DEFAULT_MODEL`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BayesClassifierRule {
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+}
+
+fn default_threshold() -> f64 {
+    DEFAULT_THRESHOLD
+}
+
+/// A trained Bayesian model: per-feature spam/ham counts plus the totals they were drawn from.
+#[derive(Clone, Debug)]
+pub struct Model {
+    tokens: HashMap<String, TokenCounts>,
+    total_spam: u32,
+    total_ham: u32,
+}
+
+impl Model {
+    /// Builds a model from raw per-feature counts, as they would be deserialized from
+    /// `PiiConfig.vars` when a user supplies their own training data instead of the builtin one.
+    pub fn from_counts(tokens: HashMap<String, TokenCounts>) -> Self {
+        let (total_spam, total_ham) = tokens
+            .values()
+            .fold((0u32, 0u32), |(s, h), c| (s + c.spam, h + c.ham));
+        Model {
+            tokens,
+            total_spam,
+            total_ham,
+        }
+    }
+
+    fn counts(&self, feature: &str) -> Option<TokenCounts> {
+        self.tokens.get(feature).copied()
+    }
+}
+
+lazy_static! {
+    /// The builtin model behind the `@secret` rule, trained on a small corpus of known credential
+    /// formats (API keys, bearer tokens, passwords, ...) against ordinary log strings. It is
+    /// intentionally tiny: it exists to give the classifier a sane default, not to be
+    /// exhaustive -- organizations with more specific needs are expected to supply their own counts
+    /// via `PiiConfig.vars`.
+    pub static ref DEFAULT_MODEL: Model = Model::from_counts(
+        [
+            ("secret", 40, 2),
+            ("token", 35, 3),
+            ("apikey", 32, 1),
+            ("api_key", 32, 1),
+            ("password", 30, 1),
+            ("passwd", 28, 1),
+            ("bearer", 25, 0),
+            ("credential", 24, 1),
+            ("private", 18, 4),
+            ("sk", 20, 0),
+            ("auth", 16, 6),
+            ("the", 1, 60),
+            ("and", 1, 55),
+            ("name", 2, 40),
+            ("user", 3, 35),
+            ("error", 2, 38),
+            ("request", 2, 30),
+            ("id", 4, 45),
+        ]
+        .iter()
+        .map(|(token, spam, ham)| {
+            (
+                (*token).to_owned(),
+                TokenCounts {
+                    spam: *spam,
+                    ham: *ham,
+                },
+            )
+        })
+        .collect()
+    );
+}
+
+/// Splits `value` into lowercase alphanumeric tokens, discarding everything else as a separator.
+fn tokenize(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+/// Expands a token stream into orthogonal sparse bigram features: every token on its own, plus
+/// every token paired with each of the next `WINDOW - 1` tokens, tagged with their skip distance so
+/// that e.g. `"api"` immediately before `"key"` is a different feature from `"api"` two tokens
+/// before `"key"`.
+fn orthogonal_sparse_bigrams(tokens: &[String]) -> Vec<String> {
+    let mut features = Vec::with_capacity(tokens.len() * WINDOW);
+
+    for (index, anchor) in tokens.iter().enumerate() {
+        features.push(anchor.clone());
+
+        for skip in 1..WINDOW {
+            if let Some(other) = tokens.get(index + skip) {
+                features.push(format!("{}_skip{}_{}", anchor, skip, other));
+            }
+        }
+    }
+
+    features
+}
+
+/// Computes the probability that `feature` indicates a secret, from its accumulated spam/ham
+/// counts: `p = (ws * Nh) / (ws * Nh + wh * Ns)`. Unseen features are neutral (`0.5`); the result
+/// is always clamped away from the extremes so that a single feature can never veto or force the
+/// combined verdict on its own.
+fn token_probability(feature: &str, model: &Model) -> f64 {
+    let counts = match model.counts(feature) {
+        Some(counts) if counts.spam > 0 || counts.ham > 0 => counts,
+        _ => return 0.5,
+    };
+
+    let (ws, wh) = (f64::from(counts.spam), f64::from(counts.ham));
+    let (ns, nh) = (f64::from(model.total_spam), f64::from(model.total_ham));
+
+    // Written as a ratio of per-class frequencies (equal to the formula above when both totals
+    // are non-zero) so that a model with no ham (or no spam) counts at all doesn't divide 0 by 0.
+    let spam_freq = if ns > 0.0 { ws / ns } else { 0.0 };
+    let ham_freq = if nh > 0.0 { wh / nh } else { 0.0 };
+
+    let p = spam_freq / (spam_freq + ham_freq);
+    p.clamp(0.01, 0.99)
+}
+
+/// Combines per-feature probabilities into a single score using the Robinson/Fisher product:
+/// `prod(p) / (prod(p) + prod(1 - p))`, restricted to the [`MAX_FEATURES`] features whose
+/// probability sits farthest from `0.5` (i.e. the most opinionated ones).
+fn combine(mut probabilities: Vec<f64>) -> f64 {
+    probabilities.sort_by(|a, b| {
+        let distance_a = (a - 0.5).abs();
+        let distance_b = (b - 0.5).abs();
+        distance_b.partial_cmp(&distance_a).unwrap()
+    });
+    probabilities.truncate(MAX_FEATURES);
+
+    let product: f64 = probabilities.iter().product();
+    let inverse_product: f64 = probabilities.iter().map(|p| 1.0 - p).product();
+
+    product / (product + inverse_product)
+}
+
+/// Scores `value` against `model`, returning the combined "secret" probability in `[0, 1]`.
+///
+/// Returns `None` for values with fewer than 3 tokens: short values don't carry enough signal for
+/// the tokenizer to say anything meaningful, so they short-circuit to "not secret" rather than
+/// risk a confident-looking score built from one or two features.
+pub fn classify(value: &str, model: &Model) -> Option<f64> {
+    let tokens = tokenize(value);
+    if tokens.len() < 3 {
+        return None;
+    }
+
+    let probabilities = orthogonal_sparse_bigrams(&tokens)
+        .iter()
+        .map(|feature| token_probability(feature, model))
+        .collect();
+
+    Some(combine(probabilities))
+}
+
+/// Returns `true` if `value` scores at or above `threshold` against `model`. Always `false` for
+/// values too short to classify (see [`classify`]).
+pub fn is_secret(value: &str, model: &Model, threshold: f64) -> bool {
+    classify(value, model).map_or(false, |score| score >= threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_value_is_not_secret() {
+        assert_eq!(classify("ab cd", &DEFAULT_MODEL), None);
+        assert!(!is_secret("ab cd", &DEFAULT_MODEL, 0.5));
+    }
+
+    #[test]
+    fn test_ordinary_log_line_scores_low() {
+        let score = classify(
+            "user requested the error page and the request id was logged",
+            &DEFAULT_MODEL,
+        )
+        .unwrap();
+        assert!(score < 0.5, "expected low score, got {}", score);
+    }
+
+    #[test]
+    fn test_credential_like_value_scores_high() {
+        let score = classify(
+            "api_key secret bearer token credential private sk auth password",
+            &DEFAULT_MODEL,
+        )
+        .unwrap();
+        assert!(score > 0.9, "expected high score, got {}", score);
+    }
+
+    #[test]
+    fn test_custom_model_from_counts() {
+        let mut tokens = HashMap::new();
+        tokens.insert(
+            "hunter2".to_owned(),
+            TokenCounts { spam: 10, ham: 0 },
+        );
+        let model = Model::from_counts(tokens);
+
+        let score = classify("my hunter2 password is weak", &model).unwrap();
+        assert!(score > 0.5, "expected elevated score, got {}", score);
+    }
+}
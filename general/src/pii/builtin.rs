@@ -2,6 +2,10 @@ use std::collections::BTreeMap;
 
 use lazy_static::lazy_static;
 
+use crate::pii::bayes::{self, BayesClassifierRule};
+use crate::pii::classifier::{self, ClassifierRule};
+use crate::pii::scanner::ScannerRule;
+use crate::pii::sql::SqlRule;
 use crate::pii::{
     AliasRule, AliasSelector, HashAlgorithm, HashRedaction, KindSelector, MaskRedaction,
     RedactPairRule, Redaction, ReplaceRedaction, RuleSpec, RuleType, SelectorType,
@@ -73,6 +77,10 @@ declare_builtin_rules! {
     };
 
     // ip rules
+    //
+    // `RuleType::Ip`'s regex covers plain IPv4 by shape alone, with no octet-range or IPv6/CIDR
+    // support; `crate::pii::ip::find_ip_addresses` is the validated, IPv6-and-CIDR-aware matcher
+    // intended to back it once `pii::config` adopts it.
     "@ip" => rule_alias!("@ip:replace");
     "@ip:replace" => RuleSpec {
         ty: RuleType::Ip,
@@ -177,6 +185,10 @@ declare_builtin_rules! {
     };
 
     // pem rules
+    //
+    // The regex backing `RuleType::Pemkey` only covers PEM `PUBLIC KEY`/`PRIVATE KEY`/
+    // `ENCRYPTED PRIVATE KEY`/`RSA PRIVATE KEY` armor; OpenSSH, PuTTY, PGP and JWK/JWT formats are
+    // recognized by `crate::pii::keys::detect` instead, which `RuleType::Pemkey` should also try.
     "@pemkey" => rule_alias!("@pemkey:replace");
     "@pemkey:replace" => RuleSpec {
         ty: RuleType::Pemkey,
@@ -224,6 +236,77 @@ declare_builtin_rules! {
         }),
     };
 
+    // cryptocurrency wallet material
+    //
+    // Each `RuleType`'s regex covers the format by shape alone (hex/base58 length); the actual
+    // checksum validation (EIP-55 for Ethereum, base58check's double-SHA256 for Bitcoin and WIF)
+    // lives in `crate::pii::crypto_wallets`, which each of these should also run before accepting
+    // a candidate -- otherwise an arbitrary hex or base58 blob the right length gets redacted too.
+    "@ethaddress" => rule_alias!("@ethaddress:replace");
+    "@ethaddress:replace" => RuleSpec {
+        ty: RuleType::EthAddress,
+        redaction: Redaction::Replace(ReplaceRedaction {
+            text: "[ethaddress]".into(),
+        }),
+    };
+    "@ethaddress:hash" => RuleSpec {
+        ty: RuleType::EthAddress,
+        redaction: Redaction::Hash(HashRedaction {
+            algorithm: HashAlgorithm::HmacSha1,
+            key: None,
+        }),
+    };
+
+    "@btcaddress" => rule_alias!("@btcaddress:replace");
+    "@btcaddress:replace" => RuleSpec {
+        ty: RuleType::BtcAddress,
+        redaction: Redaction::Replace(ReplaceRedaction {
+            text: "[btcaddress]".into(),
+        }),
+    };
+    "@btcaddress:hash" => RuleSpec {
+        ty: RuleType::BtcAddress,
+        redaction: Redaction::Hash(HashRedaction {
+            algorithm: HashAlgorithm::HmacSha1,
+            key: None,
+        }),
+    };
+
+    "@privkey" => rule_alias!("@privkey:replace");
+    "@privkey:replace" => RuleSpec {
+        ty: RuleType::CryptoPrivkey,
+        redaction: Redaction::Replace(ReplaceRedaction {
+            text: "[privkey]".into(),
+        }),
+    };
+    "@privkey:hash" => RuleSpec {
+        ty: RuleType::CryptoPrivkey,
+        redaction: Redaction::Hash(HashRedaction {
+            algorithm: HashAlgorithm::HmacSha1,
+            key: None,
+        }),
+    };
+
+    // JWTs and bearer tokens -- matches neither `@password`'s key-name pattern nor `@urlauth`'s
+    // URL-embedded-credentials shape, so auth material carried this way passed through unredacted
+    // until now. See `crate::pii::jwt` for how a candidate is confirmed to be a JWT (rather than
+    // just three dot-separated base64url-shaped segments) and for how a bare or header-prefixed
+    // bearer token is found.
+    "@jwt" => rule_alias!("@jwt:replace");
+    "@jwt:replace" => RuleSpec {
+        ty: RuleType::Jwt,
+        redaction: Redaction::Replace(ReplaceRedaction {
+            text: "[jwt]".into(),
+        }),
+    };
+    "@jwt:hash" => RuleSpec {
+        ty: RuleType::Jwt,
+        redaction: Redaction::Hash(HashRedaction {
+            algorithm: HashAlgorithm::HmacSha1,
+            key: None,
+        }),
+    };
+
     // password field removal
     "@password" => rule_alias!("@password:remove");
     "@password:remove" => RuleSpec {
@@ -232,6 +315,44 @@ declare_builtin_rules! {
         }),
         redaction: Redaction::Remove,
     };
+
+    // bayesian secret classifier, for values whose key doesn't give them away
+    "@secret" => rule_alias!("@secret:remove");
+    "@secret:remove" => RuleSpec {
+        ty: RuleType::BayesClassifier(BayesClassifierRule {
+            threshold: bayes::DEFAULT_THRESHOLD,
+        }),
+        redaction: Redaction::Remove,
+    };
+
+    // free-form secret classifier, for credentials embedded in otherwise ordinary text (log
+    // lines, breadcrumb messages, ...) that `@secret:remove` would be too blunt to use on, since
+    // it redacts the whole value rather than just the matched span. See `crate::pii::classifier`
+    // for how the span is found.
+    "@secret:replace" => RuleSpec {
+        ty: RuleType::Classifier(ClassifierRule {
+            threshold: classifier::DEFAULT_THRESHOLD,
+        }),
+        redaction: Redaction::Replace(ReplaceRedaction {
+            text: "[secret]".into(),
+        }),
+    };
+
+    // single-pass typed scanner: classifies every token in a value and redacts each sensitive
+    // span with the redaction appropriate to its kind (see `crate::pii::scanner`), in place of
+    // scanning the whole value once per entity kind as `@common:filter`'s regexes do.
+    "@scanner" => RuleSpec {
+        ty: RuleType::Scanner(ScannerRule::default()),
+        redaction: Redaction::Default,
+    };
+
+    // sql literal scrubbing, for breadcrumb messages and span descriptions
+    "@sql:filter" => RuleSpec {
+        ty: RuleType::Sql(SqlRule::default()),
+        redaction: Redaction::Replace(ReplaceRedaction {
+            text: "[filtered]".into(),
+        }),
+    };
 }
 
 // TODO: Move these tests to /tests
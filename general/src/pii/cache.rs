@@ -0,0 +1,141 @@
+//! Caches the compiled form of a [`DataScrubbingConfig`], so it only has to be turned into a
+//! [`PiiConfig`] -- regex compilation and all -- once per distinct configuration, not once per
+//! event.
+//!
+//! [`to_pii_config`] rebuilds its sensitive-fields regex and re-compiles it from scratch on every
+//! call. That's fine for the handful of call sites in this crate's own tests, but a long-running
+//! Relay process evaluates the same `DataScrubbingConfig` (one per project) against every event it
+//! processes, so recompiling on the hot path is wasted work. [`ScrubbingConfigCache`] compiles once
+//! into a cheaply-cloneable [`Arc`] handle and exposes [`ScrubbingConfigCache::reload`] to pick up
+//! config changes without a process restart: `reload` recompiles only if the config's content
+//! actually changed, and swaps the active handle atomically so any in-flight processing that
+//! already cloned the previous handle keeps running against it to completion.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use crate::datascrubbing::DataScrubbingConfig;
+use crate::pii::PiiConfig;
+
+use super::to_pii_config;
+
+/// A [`DataScrubbingConfig`] compiled once into its [`PiiConfig`], tagged with a content hash of
+/// the config it was compiled from so [`ScrubbingConfigCache::reload`] can tell whether a reload
+/// actually changed anything.
+pub struct CompiledScrubbingConfig {
+    pii_config: Option<PiiConfig>,
+    hash: u64,
+}
+
+impl CompiledScrubbingConfig {
+    fn compile(config: &DataScrubbingConfig) -> Self {
+        CompiledScrubbingConfig {
+            pii_config: to_pii_config(config),
+            hash: content_hash(config),
+        }
+    }
+
+    /// The compiled config, or `None` if scrubbing is disabled entirely (mirrors
+    /// [`to_pii_config`]'s return value).
+    pub fn pii_config(&self) -> Option<&PiiConfig> {
+        self.pii_config.as_ref()
+    }
+}
+
+/// A hot-reloadable cache holding the currently active [`CompiledScrubbingConfig`].
+pub struct ScrubbingConfigCache {
+    active: Mutex<Arc<CompiledScrubbingConfig>>,
+}
+
+impl ScrubbingConfigCache {
+    /// Compiles `config` and creates a cache with it as the active handle.
+    pub fn new(config: &DataScrubbingConfig) -> Self {
+        ScrubbingConfigCache {
+            active: Mutex::new(Arc::new(CompiledScrubbingConfig::compile(config))),
+        }
+    }
+
+    /// Returns the currently active compiled config. Cheap: it only clones the `Arc`.
+    pub fn current(&self) -> Arc<CompiledScrubbingConfig> {
+        self.active.lock().unwrap().clone()
+    }
+
+    /// Recompiles `config` and atomically swaps it in as the active handle, unless its content
+    /// hash matches the one currently active, in which case this is a no-op.
+    ///
+    /// Callers holding an `Arc` from an earlier [`ScrubbingConfigCache::current`] keep using that
+    /// version until they finish and drop it; only subsequent `current()` calls observe the
+    /// reload.
+    pub fn reload(&self, config: &DataScrubbingConfig) {
+        let hash = content_hash(config);
+
+        let mut active = self.active.lock().unwrap();
+        if active.hash == hash {
+            return;
+        }
+
+        *active = Arc::new(CompiledScrubbingConfig::compile(config));
+    }
+}
+
+/// Hashes the content of `config` as a stand-in for a derived `Hash` impl: `DataScrubbingConfig`'s
+/// serialized form round-trips every field that matters for compilation, so hashing it is enough
+/// to detect a config change without requiring `DataScrubbingConfig` itself to implement `Hash`.
+fn content_hash(config: &DataScrubbingConfig) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    if let Ok(json) = serde_json::to_string(config) {
+        json.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(scrub_data: bool) -> DataScrubbingConfig {
+        DataScrubbingConfig {
+            scrub_data,
+            scrub_defaults: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_current_returns_compiled_config() {
+        let cache = ScrubbingConfigCache::new(&config(true));
+        assert!(cache.current().pii_config().is_some());
+    }
+
+    #[test]
+    fn test_reload_swaps_in_changed_config() {
+        let cache = ScrubbingConfigCache::new(&config(false));
+        assert!(cache.current().pii_config().is_none());
+
+        cache.reload(&config(true));
+        assert!(cache.current().pii_config().is_some());
+    }
+
+    #[test]
+    fn test_reload_is_noop_for_unchanged_config() {
+        let cache = ScrubbingConfigCache::new(&config(true));
+        let before = cache.current();
+
+        cache.reload(&config(true));
+        let after = cache.current();
+
+        assert!(Arc::ptr_eq(&before, &after));
+    }
+
+    #[test]
+    fn test_in_flight_handle_survives_reload() {
+        let cache = ScrubbingConfigCache::new(&config(false));
+        let in_flight = cache.current();
+
+        cache.reload(&config(true));
+
+        assert!(in_flight.pii_config().is_none());
+        assert!(cache.current().pii_config().is_some());
+    }
+}
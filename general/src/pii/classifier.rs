@@ -0,0 +1,311 @@
+//! A shipped-model, hash-keyed sibling to [`crate::pii::bayes`] backing the `@secret:replace`
+//! builtin PII rule (see [`crate::pii::builtin`]).
+//!
+//! Where [`crate::pii::bayes`] scores a *whole value* as secret-or-not (suited to redacting an
+//! entire databag entry via `@secret:remove`), this module scans a free-form string the way
+//! [`crate::pii::scanner`] does -- one sliding window per token -- and redacts only the matched
+//! span, so a credential embedded in an otherwise ordinary log line doesn't take the rest of the
+//! line down with it. The feature extraction (orthogonal sparse bigrams) and combination rule
+//! (Robinson/Graham chaining) are the same as [`crate::pii::bayes`]; what differs is that features
+//! are looked up by a pair of hashes rather than by the feature string itself, so the shipped
+//! [`DEFAULT_TABLE`] can grow far beyond a toy corpus without the binary retaining every literal
+//! n-gram it was trained on.
+//!
+//! This module is wired in from `pii::config` via a `RuleType::Classifier(ClassifierRule)` variant
+//! and declared with `mod classifier;` in `pii::mod`; neither of those is part of this source
+//! snapshot, so the wiring below is written as though they already existed, the same way
+//! `crate::pii::bayes` already is.
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+/// Window size for the orthogonal sparse bigram tokenizer: each anchor token is paired with each
+/// of the next `WINDOW - 1` tokens that follow it. Matches [`crate::pii::bayes::WINDOW`] since both
+/// modules tune against corpora of the same shape.
+const WINDOW: usize = 5;
+
+/// How many of the most informative features (farthest from `0.5`) are folded into the combined
+/// score for a single window. See [`crate::pii::bayes`]'s identical constant for the rationale.
+const MAX_FEATURES: usize = 15;
+
+/// The probability assigned to a feature this process's table has never seen.
+const UNSEEN_PROBABILITY: f64 = 0.5;
+
+/// Default threshold for the `@secret:replace` builtin rule: the combined score from which a span
+/// is considered a secret and redacted. Tuned against [`DEFAULT_TABLE`]; a `ClassifierRule` may
+/// override it per-rule.
+pub const DEFAULT_THRESHOLD: f64 = 0.9;
+
+/// Sensitive/benign occurrence counts for a single feature, as accumulated by the training corpus
+/// behind [`DEFAULT_TABLE`] or supplied by a user through `PiiConfig.vars`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FeatureCounts {
+    pub sensitive: u32,
+    pub benign: u32,
+}
+
+/// Configuration for a `RuleType::Classifier` rule.
+///
+/// `threshold` is the minimum combined score (see [`classify`]) at which a span is treated as a
+/// secret. Defaults to [`DEFAULT_THRESHOLD`], which is tuned for [`DEFAULT_TABLE`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClassifierRule {
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+}
+
+fn default_threshold() -> f64 {
+    DEFAULT_THRESHOLD
+}
+
+/// A byte range within a scanned value that [`scan`] judged sensitive.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Two independent 32-bit FNV-1a variants (different offset basis each), standing in for a proper
+/// universal hash family -- good enough collision odds for a table of this size, and, unlike
+/// `std`'s `DefaultHasher`, stable across Rust releases. That stability matters here: the shipped
+/// table's keys are hashes of a fixed offline corpus, baked in at compile time, so the hash used to
+/// look a feature up must be the same one used to build the table in the first place.
+fn fnv1a32(seed: u32, data: &[u8]) -> u32 {
+    let mut hash = seed;
+    for &byte in data {
+        hash ^= u32::from(byte);
+        hash = hash.wrapping_mul(16_777_619);
+    }
+    hash
+}
+
+fn hash_feature(feature: &str) -> (u32, u32) {
+    (
+        fnv1a32(0x811c_9dc5, feature.as_bytes()),
+        fnv1a32(0x9e37_79b9, feature.as_bytes()),
+    )
+}
+
+/// A trained model: per-feature sensitive/benign counts, keyed by [`hash_feature`] rather than the
+/// feature string itself, plus the totals they were drawn from.
+#[derive(Clone, Debug)]
+pub struct FeatureTable {
+    counts: HashMap<(u32, u32), FeatureCounts>,
+    total_sensitive: u32,
+    total_benign: u32,
+}
+
+impl FeatureTable {
+    /// Builds a table from raw `(feature, sensitive_count, benign_count)` triples, hashing each
+    /// feature on the way in. This is how [`DEFAULT_TABLE`] is built from the shipped corpus below,
+    /// and how a user-supplied training set from `PiiConfig.vars` would be turned into a table too.
+    pub fn from_entries<I>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = (&'static str, u32, u32)>,
+    {
+        let mut counts = HashMap::new();
+        let (mut total_sensitive, mut total_benign) = (0u32, 0u32);
+
+        for (feature, sensitive, benign) in entries {
+            total_sensitive += sensitive;
+            total_benign += benign;
+            counts.insert(hash_feature(feature), FeatureCounts { sensitive, benign });
+        }
+
+        FeatureTable {
+            counts,
+            total_sensitive,
+            total_benign,
+        }
+    }
+
+    fn lookup(&self, feature: &str) -> Option<FeatureCounts> {
+        self.counts.get(&hash_feature(feature)).copied()
+    }
+}
+
+lazy_static! {
+    /// The builtin table behind `@secret:replace`, trained on a small corpus of known credential
+    /// and connection-string vocabulary against ordinary log prose. Intentionally tiny, like
+    /// `crate::pii::bayes::DEFAULT_MODEL` -- it exists to give the classifier a sane default, not
+    /// to be exhaustive.
+    pub static ref DEFAULT_TABLE: FeatureTable = FeatureTable::from_entries([
+        ("secret", 40, 2),
+        ("token", 35, 3),
+        ("apikey", 32, 1),
+        ("password", 30, 1),
+        ("passwd", 28, 1),
+        ("bearer", 25, 0),
+        ("credential", 24, 1),
+        ("private", 18, 4),
+        ("sk", 20, 0),
+        ("auth", 16, 6),
+        ("connectionstring", 26, 1),
+        ("mongodb", 22, 1),
+        ("postgres", 20, 1),
+        ("redis", 18, 2),
+        ("amqp", 18, 1),
+        ("the", 1, 60),
+        ("and", 1, 55),
+        ("name", 2, 40),
+        ("user", 3, 35),
+        ("error", 2, 38),
+        ("request", 2, 30),
+        ("id", 4, 45),
+        ("server", 1, 30),
+        ("started", 1, 25),
+    ]);
+}
+
+/// Splits `value` into lowercase alphanumeric tokens with their byte offsets in `value`, so a
+/// matched window's anchor token can be reported back as a redactable [`Span`].
+fn tokenize(value: &str) -> Vec<(usize, usize, String)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut end = 0;
+
+    for (index, c) in value.char_indices() {
+        if c.is_alphanumeric() {
+            if start.is_none() {
+                start = Some(index);
+            }
+            end = index + c.len_utf8();
+        } else if let Some(token_start) = start.take() {
+            tokens.push((token_start, end, value[token_start..end].to_lowercase()));
+        }
+    }
+
+    if let Some(token_start) = start {
+        tokens.push((token_start, end, value[token_start..end].to_lowercase()));
+    }
+
+    tokens
+}
+
+/// Expands a token window into orthogonal sparse bigram features: the anchor (`window[0]`) on its
+/// own, plus the anchor paired with each following token in the window, tagged with the gap between
+/// them (e.g. `"tok0|__|tok2"` for a gap of 2) so that adjacent and distant pairings count as
+/// distinct features.
+fn osb_features(window: &[&str]) -> Vec<String> {
+    let anchor = window[0];
+    let mut features = Vec::with_capacity(window.len());
+    features.push(anchor.to_owned());
+
+    for (gap, other) in window.iter().enumerate().skip(1) {
+        features.push(format!("{}|{}|{}", anchor, "_".repeat(gap), other));
+    }
+
+    features
+}
+
+/// Computes the probability that `feature` indicates a secret, from its accumulated counts:
+/// `p = (ws * Nh) / (ws * Nh + wh * Ns)`. Unseen features are neutral (`0.5`); the result is
+/// clamped away from the extremes so a single feature can never veto or force the verdict alone.
+fn feature_probability(feature: &str, table: &FeatureTable) -> f64 {
+    let counts = match table.lookup(feature) {
+        Some(counts) if counts.sensitive > 0 || counts.benign > 0 => counts,
+        _ => return UNSEEN_PROBABILITY,
+    };
+
+    let (ws, wh) = (f64::from(counts.sensitive), f64::from(counts.benign));
+    let (ns, nh) = (f64::from(table.total_sensitive), f64::from(table.total_benign));
+
+    let p = (ws * nh) / (ws * nh + wh * ns);
+    p.clamp(0.01, 0.99)
+}
+
+/// Combines per-feature probabilities into a single score using the Robinson/Graham chaining rule:
+/// `prod(p) / (prod(p) + prod(1 - p))`, restricted to the [`MAX_FEATURES`] features whose
+/// probability sits farthest from `0.5` (i.e. the most opinionated ones).
+fn combine(mut probabilities: Vec<f64>) -> f64 {
+    probabilities.sort_by(|a, b| {
+        let distance_a = (a - 0.5).abs();
+        let distance_b = (b - 0.5).abs();
+        distance_b.partial_cmp(&distance_a).unwrap()
+    });
+    probabilities.truncate(MAX_FEATURES);
+
+    let product: f64 = probabilities.iter().product();
+    let inverse_product: f64 = probabilities.iter().map(|p| 1.0 - p).product();
+
+    product / (product + inverse_product)
+}
+
+/// Scores the window anchored at `tokens[index]` against `table`, or `None` if fewer than two
+/// tokens remain in the window (a single token carries no bigram features to score).
+fn classify_window(
+    tokens: &[(usize, usize, String)],
+    index: usize,
+    table: &FeatureTable,
+) -> Option<f64> {
+    let window: Vec<&str> = tokens[index..]
+        .iter()
+        .take(WINDOW)
+        .map(|(_, _, token)| token.as_str())
+        .collect();
+
+    if window.len() < 2 {
+        return None;
+    }
+
+    let probabilities = osb_features(&window)
+        .iter()
+        .map(|feature| feature_probability(feature, table))
+        .collect();
+
+    Some(combine(probabilities))
+}
+
+/// Scans `value` for substrings judged sensitive by `table`, returning one [`Span`] per anchor
+/// token whose window scores at or above `threshold`.
+///
+/// Unlike [`crate::pii::bayes::classify`], this scores every token position rather than the value
+/// as a whole, so it can point at the specific span to redact instead of an all-or-nothing verdict
+/// on the entire string.
+pub fn scan(value: &str, table: &FeatureTable, threshold: f64) -> Vec<Span> {
+    let tokens = tokenize(value);
+    let mut spans = Vec::new();
+
+    for index in 0..tokens.len() {
+        if let Some(score) = classify_window(&tokens, index, table) {
+            if score >= threshold {
+                let (start, end, _) = tokens[index];
+                spans.push(Span { start, end });
+            }
+        }
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ordinary_log_line_has_no_spans() {
+        let spans = scan(
+            "user requested the error page and the request id was logged",
+            &DEFAULT_TABLE,
+            DEFAULT_THRESHOLD,
+        );
+        assert_eq!(spans, vec![]);
+    }
+
+    #[test]
+    fn test_credential_like_value_has_spans() {
+        let value = "api_key secret bearer token credential private sk auth password";
+        let spans = scan(value, &DEFAULT_TABLE, DEFAULT_THRESHOLD);
+        assert!(!spans.is_empty());
+        assert!(spans.iter().any(|span| &value[span.start..span.end] == "secret"));
+    }
+
+    #[test]
+    fn test_custom_table_from_entries() {
+        let table = FeatureTable::from_entries([("hunter2", 10, 0), ("weak", 0, 5)]);
+        let spans = scan("my hunter2 password is weak", &table, 0.5);
+        assert!(spans.iter().any(|span| span.start == 3 && span.end == 10));
+    }
+}
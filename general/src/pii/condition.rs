@@ -0,0 +1,150 @@
+//! A small expression evaluator for conditional PII scrubbing.
+//!
+//! `to_pii_config` currently only ever compiles `exclude_fields`/`sensitive_fields` into static
+//! selectors (`(~foobar)`, `.*(field).*`): a rule either always applies to a path, or never does.
+//! [`Condition`] adds a middle ground -- "scrub `request.data` only when
+//! `request.headers.content-type` contains `json`" -- by letting a selector's application be
+//! gated on the value of *another* field, resolved at processing time.
+//!
+//! [`Condition::evaluate`] is generic over anything that can resolve a dotted field path to a
+//! string value; in the real pipeline that resolver would walk the current `ProcessingState` back
+//! up to the root `Annotated` value being processed, but neither `ProcessingState`'s path-lookup
+//! nor the `PiiProcessor` hook that would call `evaluate` before applying a rule set are part of
+//! this source snapshot. [`FieldResolver`] stands in for that missing lookup so this module is
+//! fully self-contained and testable on its own.
+
+/// Resolves a dotted selector path (e.g. `"request.headers.content-type"`) to the string value of
+/// the field it points at, if any. Implemented by `ProcessingState` in the real pipeline.
+pub trait FieldResolver {
+    fn resolve(&self, path: &str) -> Option<String>;
+}
+
+/// A boolean condition over one or more field references, compiled from a `DataScrubbingConfig`
+/// rule and evaluated against the value currently being processed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    /// The field at `path` resolves to exactly `value`.
+    Equals { path: String, value: String },
+    /// The field at `path` resolves to a string containing `needle`.
+    Contains { path: String, needle: String },
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    pub fn evaluate(&self, resolver: &dyn FieldResolver) -> bool {
+        match self {
+            Condition::Equals { path, value } => {
+                resolver.resolve(path).as_deref() == Some(value.as_str())
+            }
+            Condition::Contains { path, needle } => resolver
+                .resolve(path)
+                .is_some_and(|resolved| resolved.contains(needle.as_str())),
+            Condition::And(conditions) => {
+                conditions.iter().all(|condition| condition.evaluate(resolver))
+            }
+            Condition::Or(conditions) => {
+                conditions.iter().any(|condition| condition.evaluate(resolver))
+            }
+            Condition::Not(condition) => !condition.evaluate(resolver),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    struct MapResolver(BTreeMap<&'static str, &'static str>);
+
+    impl FieldResolver for MapResolver {
+        fn resolve(&self, path: &str) -> Option<String> {
+            self.0.get(path).map(|value| (*value).to_owned())
+        }
+    }
+
+    fn resolver() -> MapResolver {
+        MapResolver(
+            [
+                ("request.headers.content-type", "application/json; charset=utf-8"),
+                ("request.method", "POST"),
+            ]
+            .into_iter()
+            .collect(),
+        )
+    }
+
+    #[test]
+    fn test_equals() {
+        let condition = Condition::Equals {
+            path: "request.method".to_owned(),
+            value: "POST".to_owned(),
+        };
+        assert!(condition.evaluate(&resolver()));
+
+        let condition = Condition::Equals {
+            path: "request.method".to_owned(),
+            value: "GET".to_owned(),
+        };
+        assert!(!condition.evaluate(&resolver()));
+    }
+
+    #[test]
+    fn test_contains() {
+        let condition = Condition::Contains {
+            path: "request.headers.content-type".to_owned(),
+            needle: "json".to_owned(),
+        };
+        assert!(condition.evaluate(&resolver()));
+
+        let condition = Condition::Contains {
+            path: "request.headers.content-type".to_owned(),
+            needle: "xml".to_owned(),
+        };
+        assert!(!condition.evaluate(&resolver()));
+    }
+
+    #[test]
+    fn test_missing_field_resolves_to_false() {
+        let condition = Condition::Equals {
+            path: "request.headers.x-missing".to_owned(),
+            value: "anything".to_owned(),
+        };
+        assert!(!condition.evaluate(&resolver()));
+    }
+
+    #[test]
+    fn test_and_or_not() {
+        let json_and_post = Condition::And(vec![
+            Condition::Contains {
+                path: "request.headers.content-type".to_owned(),
+                needle: "json".to_owned(),
+            },
+            Condition::Equals {
+                path: "request.method".to_owned(),
+                value: "POST".to_owned(),
+            },
+        ]);
+        assert!(json_and_post.evaluate(&resolver()));
+
+        let xml_or_get = Condition::Or(vec![
+            Condition::Contains {
+                path: "request.headers.content-type".to_owned(),
+                needle: "xml".to_owned(),
+            },
+            Condition::Equals {
+                path: "request.method".to_owned(),
+                value: "GET".to_owned(),
+            },
+        ]);
+        assert!(!xml_or_get.evaluate(&resolver()));
+
+        let not_xml = Condition::Not(Box::new(Condition::Contains {
+            path: "request.headers.content-type".to_owned(),
+            needle: "xml".to_owned(),
+        }));
+        assert!(not_xml.evaluate(&resolver()));
+    }
+}
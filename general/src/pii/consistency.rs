@@ -0,0 +1,131 @@
+//! A post-pass that keeps derived/composite fields consistent with the primary fields PII
+//! scrubbing already redacted.
+//!
+//! Scrubbing `user.ip_address` does nothing for `user.sentry_user` = `"ip:73.133.27.120"`: it's a
+//! separate string that happens to embed the same IP, with no `pii` annotation of its own pointing
+//! at that substring. [`ConsistencyPass`] closes that gap generically -- it is not a
+//! `sentry_user`-specific rule -- by recording every value a rule redacted during the main pass,
+//! then re-scanning whatever other string fields the caller points it at for those same
+//! substrings, redacting them too with a remark that mirrors the original.
+//!
+//! `PiiProcessor` itself (the thing that would run this as a post-pass once the main
+//! `process_value` walk is done, and decide which fields count as "sibling/derived") is not part of
+//! this source snapshot, so this module only provides the substring-recording and -rescrubbing
+//! primitive; wiring it into the processor's traversal is left to whoever lands `PiiProcessor`.
+
+/// One value a rule redacted during the main scrubbing pass: `original` was replaced by
+/// `replacement` (e.g. `"73.133.27.120"` -> `"[ip]"`).
+#[derive(Clone, Debug, PartialEq)]
+struct ScrubbedValue {
+    original: String,
+    replacement: String,
+}
+
+/// Accumulates the values scrubbed during a processing run and re-applies them to derived fields
+/// that embed the same substrings.
+#[derive(Clone, Debug, Default)]
+pub struct ConsistencyPass {
+    scrubbed: Vec<ScrubbedValue>,
+}
+
+impl ConsistencyPass {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `original` was redacted to `replacement` somewhere in the event, so that later
+    /// calls to [`ConsistencyPass::scrub_derived_field`] also catch `original` if it resurfaces in
+    /// another field.
+    ///
+    /// Empty originals are ignored: matching an empty string would redact every position in every
+    /// derived field.
+    pub fn record(&mut self, original: &str, replacement: &str) {
+        if original.is_empty() {
+            return;
+        }
+        self.scrubbed.push(ScrubbedValue {
+            original: original.to_owned(),
+            replacement: replacement.to_owned(),
+        });
+    }
+
+    /// Re-scans `text` for any previously recorded original value and replaces each occurrence
+    /// with its matching replacement. Longer originals are tried first, so that one recorded value
+    /// which happens to be a substring of another (e.g. an IP embedded in a longer token) doesn't
+    /// shadow the more specific match.
+    ///
+    /// Returns the (possibly unchanged) text and whether any substitution was made.
+    pub fn scrub_derived_field(&self, text: &str) -> (String, bool) {
+        if self.scrubbed.is_empty() {
+            return (text.to_owned(), false);
+        }
+
+        let mut ordered: Vec<&ScrubbedValue> = self.scrubbed.iter().collect();
+        ordered.sort_by_key(|value| std::cmp::Reverse(value.original.len()));
+
+        let mut result = text.to_owned();
+        let mut changed = false;
+
+        for value in ordered {
+            if result.contains(&value.original) {
+                result = result.replace(&value.original, &value.replacement);
+                changed = true;
+            }
+        }
+
+        (result, changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrubs_recorded_substring_in_derived_field() {
+        let mut pass = ConsistencyPass::new();
+        pass.record("73.133.27.120", "[ip]");
+
+        let (scrubbed, changed) = pass.scrub_derived_field("ip:73.133.27.120");
+        assert!(changed);
+        assert_eq!(scrubbed, "ip:[ip]");
+    }
+
+    #[test]
+    fn test_leaves_unrelated_field_untouched() {
+        let mut pass = ConsistencyPass::new();
+        pass.record("73.133.27.120", "[ip]");
+
+        let (scrubbed, changed) = pass.scrub_derived_field("username:johnappleseed");
+        assert!(!changed);
+        assert_eq!(scrubbed, "username:johnappleseed");
+    }
+
+    #[test]
+    fn test_longer_original_wins_over_substring() {
+        let mut pass = ConsistencyPass::new();
+        pass.record("token-abc", "[short]");
+        pass.record("prefix-token-abc", "[long]");
+
+        let (scrubbed, _) = pass.scrub_derived_field("value=prefix-token-abc");
+        assert_eq!(scrubbed, "value=[long]");
+    }
+
+    #[test]
+    fn test_empty_original_is_ignored() {
+        let mut pass = ConsistencyPass::new();
+        pass.record("", "[nope]");
+
+        let (scrubbed, changed) = pass.scrub_derived_field("anything at all");
+        assert!(!changed);
+        assert_eq!(scrubbed, "anything at all");
+    }
+
+    #[test]
+    fn test_no_recorded_values_is_a_cheap_no_op() {
+        let pass = ConsistencyPass::new();
+        let (scrubbed, changed) = pass.scrub_derived_field("ip:73.133.27.120");
+        assert!(!changed);
+        assert_eq!(scrubbed, "ip:73.133.27.120");
+    }
+}
@@ -0,0 +1,245 @@
+//! Validated matchers for cryptocurrency addresses and private keys, backing the
+//! `RuleType::EthAddress`/`RuleType::BtcAddress`/`RuleType::CryptoPrivkey` builtins (`@ethaddress`,
+//! `@btcaddress`, `@privkey` -- see [`crate::pii::builtin`]).
+//!
+//! Each format's shape alone is loose enough to false-positive on an arbitrary hex or base58 blob,
+//! so -- mirroring the Luhn/IBAN checks in [`crate::pii::scanner`] -- every matcher here also
+//! verifies the format's own checksum: the EIP-55 mixed-case checksum for Ethereum addresses, and
+//! base58check's double-SHA256 checksum for Bitcoin addresses and WIF private keys.
+//!
+//! This module is wired in from `pii::config` via `RuleType::EthAddress`/`RuleType::BtcAddress`/
+//! `RuleType::CryptoPrivkey` and declared with `mod crypto_wallets;` in `pii::mod`; neither of
+//! those is part of this source snapshot, so the wiring below is written as though they already
+//! existed, the same way `crate::pii::keys`/`crate::pii::ip` already are.
+
+use sha2::{Digest, Sha256};
+use tiny_keccak::{Hasher, Keccak};
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Returns whether `candidate` is a well-formed Ethereum address: `0x` followed by 40 hex
+/// characters.
+///
+/// An all-lowercase or all-uppercase body has no EIP-55 checksum to check and is accepted on shape
+/// alone, matching how wallets themselves treat un-checksummed addresses. A mixed-case body must
+/// pass the EIP-55 checksum -- the keccak256 digest of the lowercase body determines, nibble by
+/// nibble, which hex letters are required to be uppercase -- so an arbitrary 40-hex-char blob that
+/// happens to have mixed case isn't redacted as an address.
+pub fn is_valid_eth_address(candidate: &str) -> bool {
+    let body = match candidate
+        .strip_prefix("0x")
+        .or_else(|| candidate.strip_prefix("0X"))
+    {
+        Some(body) if body.len() == 40 && body.chars().all(|c| c.is_ascii_hexdigit()) => body,
+        _ => return false,
+    };
+
+    let has_lower = body.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = body.chars().any(|c| c.is_ascii_uppercase());
+    if !(has_lower && has_upper) {
+        return true;
+    }
+
+    eip55_checksum_valid(body)
+}
+
+fn eip55_checksum_valid(body: &str) -> bool {
+    let lower = body.to_lowercase();
+
+    let mut hasher = Keccak::v256();
+    hasher.update(lower.as_bytes());
+    let mut digest = [0u8; 32];
+    hasher.finalize(&mut digest);
+
+    for (index, c) in body.chars().enumerate() {
+        if !c.is_ascii_alphabetic() {
+            continue;
+        }
+
+        let nibble = if index % 2 == 0 {
+            digest[index / 2] >> 4
+        } else {
+            digest[index / 2] & 0x0f
+        };
+
+        if c.is_ascii_uppercase() != (nibble >= 8) {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn base58_decode(value: &str) -> Option<Vec<u8>> {
+    let mut digits: Vec<u8> = vec![0];
+
+    for c in value.chars() {
+        let digit_value = BASE58_ALPHABET.iter().position(|&b| b == c as u8)? as u32;
+
+        let mut carry = digit_value;
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) * 58;
+            *digit = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            digits.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+
+    // Every leading '1' encodes one leading zero byte that the loop above never produces a digit
+    // for, since multiplying by 58 and adding 0 never grows `digits`.
+    let leading_zeros = value.chars().take_while(|&c| c == '1').count();
+    digits.resize(digits.len() + leading_zeros, 0);
+    digits.reverse();
+    Some(digits)
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+/// Base58check-decodes `value`, returning the payload (version byte plus body) with the trailing
+/// 4-byte checksum stripped off, or `None` if the checksum doesn't match
+/// `SHA256(SHA256(payload))[..4]`.
+fn base58check_decode(value: &str) -> Option<Vec<u8>> {
+    let decoded = base58_decode(value)?;
+    if decoded.len() < 5 {
+        return None;
+    }
+
+    let (payload, checksum) = decoded.split_at(decoded.len() - 4);
+    if &sha256(&sha256(payload))[..4] != checksum {
+        return None;
+    }
+
+    Some(payload.to_vec())
+}
+
+/// Returns whether `candidate` is a Bitcoin address: base58check-decodable into a 21-byte payload
+/// (1 version byte + 20-byte hash). Covers legacy (`1...`) and P2SH (`3...`) addresses; bech32
+/// (`bc1...`) addresses use a different encoding entirely and aren't handled here.
+pub fn is_valid_btc_address(candidate: &str) -> bool {
+    base58check_decode(candidate).map_or(false, |payload| payload.len() == 21)
+}
+
+/// Returns whether `candidate` is a private key in one of the two forms wallets commonly export:
+/// a raw 64-character hex string, or Wallet Import Format (base58check with version byte `0x80`).
+pub fn is_valid_privkey(candidate: &str) -> bool {
+    if candidate.len() == 64 && candidate.chars().all(|c| c.is_ascii_hexdigit()) {
+        return true;
+    }
+
+    base58check_decode(candidate).map_or(false, |payload| payload.first() == Some(&0x80))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base58_encode(data: &[u8]) -> String {
+        let leading_zeros = data.iter().take_while(|&&b| b == 0).count();
+
+        let mut digits: Vec<u8> = vec![0];
+        for &byte in data {
+            let mut carry = u32::from(byte);
+            for digit in digits.iter_mut() {
+                carry += u32::from(*digit) << 8;
+                *digit = (carry % 58) as u8;
+                carry /= 58;
+            }
+            while carry > 0 {
+                digits.push((carry % 58) as u8);
+                carry /= 58;
+            }
+        }
+
+        let mut out: String = "1".repeat(leading_zeros);
+        out.extend(digits.iter().rev().map(|&d| BASE58_ALPHABET[d as usize] as char));
+        out
+    }
+
+    fn base58check_encode(payload: &[u8]) -> String {
+        let checksum = sha256(&sha256(payload));
+        let mut bytes = payload.to_vec();
+        bytes.extend_from_slice(&checksum[..4]);
+        base58_encode(&bytes)
+    }
+
+    #[test]
+    fn test_eip55_checksummed_addresses() {
+        for address in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            assert!(is_valid_eth_address(address), "{} should be valid", address);
+        }
+    }
+
+    #[test]
+    fn test_lowercase_and_uppercase_addresses_need_no_checksum() {
+        assert!(is_valid_eth_address(
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+        ));
+        assert!(is_valid_eth_address(
+            "0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED"
+        ));
+    }
+
+    #[test]
+    fn test_mixed_case_address_with_wrong_checksum_is_rejected() {
+        // Same address as above with one letter's case flipped.
+        assert!(!is_valid_eth_address(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"
+        ));
+    }
+
+    #[test]
+    fn test_wrong_length_or_missing_prefix_is_rejected() {
+        assert!(!is_valid_eth_address("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+        assert!(!is_valid_eth_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA"));
+    }
+
+    #[test]
+    fn test_valid_btc_address_round_trips() {
+        let mut payload = vec![0x00]; // version byte for a legacy P2PKH address
+        payload.extend_from_slice(&[0x11; 20]);
+        let address = base58check_encode(&payload);
+        assert!(is_valid_btc_address(&address));
+    }
+
+    #[test]
+    fn test_tampered_btc_address_is_rejected() {
+        let mut payload = vec![0x00];
+        payload.extend_from_slice(&[0x11; 20]);
+        let mut address = base58check_encode(&payload);
+        // Flip the last character; the checksum no longer matches.
+        address.pop();
+        address.push(if address.ends_with('1') { '2' } else { '1' });
+        assert!(!is_valid_btc_address(&address));
+    }
+
+    #[test]
+    fn test_valid_wif_privkey_round_trips() {
+        let mut payload = vec![0x80]; // WIF version byte
+        payload.extend_from_slice(&[0x22; 32]);
+        let wif = base58check_encode(&payload);
+        assert!(is_valid_privkey(&wif));
+    }
+
+    #[test]
+    fn test_raw_hex_privkey() {
+        let key = "a".repeat(64);
+        assert!(is_valid_privkey(&key));
+    }
+
+    #[test]
+    fn test_ordinary_string_is_not_a_privkey() {
+        assert!(!is_valid_privkey("just a normal log line"));
+    }
+}
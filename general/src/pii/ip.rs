@@ -0,0 +1,254 @@
+//! Structured IPv4/IPv6 matcher backing the `@ip` builtin rule (see [`crate::pii::builtin`]).
+//!
+//! The existing `RuleType::Ip` regex (defined in `pii::config`, outside this source snapshot)
+//! matches IPv4 dotted-quads by shape alone -- `builtin.rs`'s own `@ip` tests only ever exercise
+//! plain IPv4 -- so it has no octet-range validation (`999.999.999.999` would match as readily as a
+//! real address), no IPv6 support, and no `/prefix` CIDR handling. [`find_ip_addresses`] replaces
+//! that shape-only match with one that validates IPv4 octets are 0-255, expands and validates full
+//! and compressed (`::`) IPv6 including an embedded IPv4 tail (`::ffff:1.2.3.4`), recognizes an
+//! optional CIDR suffix, and requires a non-alphanumeric boundary on both sides so a version number
+//! or identifier that merely contains digits and dots doesn't get swept up.
+//!
+//! Wiring this in as `RuleType::Ip`'s actual matcher, and turning each [`IpMatch`] into a `Remark`
+//! over the real value, needs `pii::config` and `PiiProcessor`, neither of which are part of this
+//! snapshot; this module only provides the scanning primitive.
+
+/// Which address family a matched range parsed as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpKind {
+    V4,
+    V6,
+}
+
+/// A single IP address (and, if present, its CIDR suffix) found inside a larger string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IpMatch {
+    pub start: usize,
+    pub end: usize,
+    pub kind: IpKind,
+}
+
+/// Scans `text` for IPv4 and IPv6 addresses anywhere inside it, returning the range each one
+/// occupies, including an optional `/prefix` CIDR suffix. `start` and `end` are char indices into
+/// `text.chars()`, not byte offsets; the two coincide only where everything before the match is
+/// ASCII.
+pub fn find_ip_addresses(text: &str) -> Vec<IpMatch> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut matches = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if !is_address_char(chars[i]) || !left_boundary_ok(&chars, i) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut end = start;
+        while end < chars.len() && is_address_char(chars[end]) {
+            end += 1;
+        }
+
+        let candidate: String = chars[start..end].iter().collect();
+        if let Some((len, kind)) = classify(&candidate) {
+            let match_end = extend_with_cidr(&chars, start + len, kind);
+
+            if right_boundary_ok(&chars, match_end) {
+                matches.push(IpMatch { start, end: match_end, kind });
+                i = match_end;
+                continue;
+            }
+        }
+
+        i = end.max(start + 1);
+    }
+
+    matches
+}
+
+fn is_address_char(c: char) -> bool {
+    c.is_ascii_hexdigit() || c == '.' || c == ':'
+}
+
+fn left_boundary_ok(chars: &[char], start: usize) -> bool {
+    start == 0 || !chars[start - 1].is_ascii_alphanumeric()
+}
+
+fn right_boundary_ok(chars: &[char], end: usize) -> bool {
+    end >= chars.len() || !chars[end].is_ascii_alphanumeric()
+}
+
+/// If `text[end..]` starts with `/` followed by a valid CIDR prefix length for `kind`, returns the
+/// index just past it; otherwise returns `end` unchanged.
+fn extend_with_cidr(chars: &[char], end: usize, kind: IpKind) -> usize {
+    if end >= chars.len() || chars[end] != '/' {
+        return end;
+    }
+
+    let mut digits_end = end + 1;
+    while digits_end < chars.len() && chars[digits_end].is_ascii_digit() {
+        digits_end += 1;
+    }
+
+    if digits_end == end + 1 {
+        return end;
+    }
+
+    let prefix: String = chars[end + 1..digits_end].iter().collect();
+    let max_prefix = match kind {
+        IpKind::V4 => 32,
+        IpKind::V6 => 128,
+    };
+
+    match prefix.parse::<u32>() {
+        Ok(value) if value <= max_prefix => digits_end,
+        _ => end,
+    }
+}
+
+/// Classifies `candidate` as an IPv4 or IPv6 address, returning the length (in chars) of the
+/// address itself -- which, for a bare `ipv4:port` shape, is shorter than `candidate` since the
+/// port isn't part of the address.
+fn classify(candidate: &str) -> Option<(usize, IpKind)> {
+    if is_valid_ipv6(candidate) {
+        return Some((candidate.chars().count(), IpKind::V6));
+    }
+
+    if !candidate.contains(':') {
+        return is_valid_ipv4(candidate).then(|| (candidate.chars().count(), IpKind::V4));
+    }
+
+    // Not a valid standalone IPv6 address, but it contains a colon: check whether it's a bare
+    // "ipv4:port" shape, e.g. the host:port embedded in a CSP `blocked_uri`.
+    let (addr, port) = candidate.rsplit_once(':')?;
+    if is_valid_ipv4(addr) && !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) {
+        return Some((addr.chars().count(), IpKind::V4));
+    }
+
+    None
+}
+
+fn is_valid_ipv4(candidate: &str) -> bool {
+    let parts: Vec<_> = candidate.split('.').collect();
+    parts.len() == 4
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.len() <= 3 && part.parse::<u8>().is_ok())
+}
+
+fn is_valid_hextet(group: &str) -> bool {
+    !group.is_empty() && group.len() <= 4 && group.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Validates a full or `::`-compressed IPv6 address, including an embedded IPv4 tail such as the
+/// one in `::ffff:1.2.3.4`.
+fn is_valid_ipv6(candidate: &str) -> bool {
+    if candidate.matches("::").count() > 1 {
+        return false;
+    }
+
+    let has_double_colon = candidate.contains("::");
+    let (left, right) = candidate.split_once("::").unwrap_or((candidate, ""));
+
+    let mut left_groups: Vec<&str> =
+        if left.is_empty() { Vec::new() } else { left.split(':').collect() };
+    let mut right_groups: Vec<&str> =
+        if right.is_empty() { Vec::new() } else { right.split(':').collect() };
+
+    // An embedded IPv4 tail can only appear as the very last group of the whole address.
+    let tail_groups = if has_double_colon { &mut right_groups } else { &mut left_groups };
+    let mut hextets_from_v4 = 0;
+    if let Some(last) = tail_groups.last().copied() {
+        if last.contains('.') {
+            if !is_valid_ipv4(last) {
+                return false;
+            }
+            hextets_from_v4 = 2;
+            tail_groups.pop();
+        }
+    }
+
+    let left_valid = left_groups.iter().all(|g| is_valid_hextet(g));
+    let right_valid = right_groups.iter().all(|g| is_valid_hextet(g));
+    if !left_valid || !right_valid {
+        return false;
+    }
+
+    let hextet_count = left_groups.len() + right_groups.len() + hextets_from_v4;
+
+    if has_double_colon {
+        hextet_count < 8
+    } else {
+        hextet_count == 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(text: &str) -> Vec<(usize, usize)> {
+        find_ip_addresses(text).iter().map(|m| (m.start, m.end)).collect()
+    }
+
+    #[test]
+    fn test_finds_plain_ipv4() {
+        assert_eq!(ranges("client ip was 127.0.0.1 today"), vec![(14, 23)]);
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_octets() {
+        assert!(ranges("version 999.999.999.999").is_empty());
+    }
+
+    #[test]
+    fn test_finds_full_ipv6() {
+        let text = "addr 2001:0db8:0000:0000:0000:ff00:0042:8329 seen";
+        let (start, end) = ranges(text)[0];
+        assert_eq!(&text[start..end], "2001:0db8:0000:0000:0000:ff00:0042:8329");
+    }
+
+    #[test]
+    fn test_finds_compressed_ipv6() {
+        let text = "loopback is ::1 here";
+        assert_eq!(ranges(text), vec![(12, 15)]);
+    }
+
+    #[test]
+    fn test_finds_ipv6_with_embedded_ipv4_tail() {
+        let text = "mapped ::ffff:1.2.3.4 address";
+        let (start, end) = ranges(text)[0];
+        assert_eq!(&text[start..end], "::ffff:1.2.3.4");
+    }
+
+    #[test]
+    fn test_finds_cidr_suffix() {
+        let text = "subnet 10.0.0.0/24 blocked";
+        let (start, end) = ranges(text)[0];
+        assert_eq!(&text[start..end], "10.0.0.0/24");
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_cidr_prefix() {
+        let text = "subnet 10.0.0.0/99 blocked";
+        let (start, end) = ranges(text)[0];
+        assert_eq!(&text[start..end], "10.0.0.0");
+    }
+
+    #[test]
+    fn test_finds_ip_in_blocked_uri() {
+        let text = "blocked_uri: http://192.168.1.1:8080/resource";
+        let (start, end) = ranges(text)[0];
+        assert_eq!(&text[start..end], "192.168.1.1");
+    }
+
+    #[test]
+    fn test_skips_version_number_attached_to_identifier() {
+        assert!(ranges("build v1.2.3.4 passed").is_empty());
+    }
+
+    #[test]
+    fn test_finds_multiple_addresses() {
+        assert_eq!(ranges("from 10.0.0.1 to 10.0.0.2").len(), 2);
+    }
+}
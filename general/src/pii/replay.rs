@@ -0,0 +1,332 @@
+//! Scrubbing support for session replay recordings.
+//!
+//! A recording is a gzip-compressed stream of newline-delimited rrweb events. The two event types
+//! that can carry DOM content are walked here:
+//!
+//! * `FullSnapshot` (rrweb type `2`) carries a full `node` tree under `data.node`, the same shape
+//!   at every depth: `{"type": <NodeType>, "textContent": ..., "attributes": {...},
+//!   "childNodes": [...]}`.
+//! * `IncrementalSnapshot` (rrweb type `3`) carries incremental `data.source`-tagged updates; the
+//!   two sources that can carry content are mutations (`source: 0`, with `texts`/`attributes`
+//!   entries and freshly `adds`-ed nodes) and input events (`source: 5`, with a `text` field).
+//!
+//! `relay-replays` and its recording event/node types aren't part of this source snapshot, so the
+//! field names above are taken from the public rrweb wire format itself rather than from a type
+//! already in this tree. Events are kept as bare `serde_json::Value`s rather than typed structs for
+//! the same reason: there's no existing `Event`/`Node` type here to deserialize into, and scrubbing
+//! only ever needs to look at a handful of well-known fields.
+
+use std::io::{self, Read, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::Value;
+
+/// rrweb's `type` discriminant for a `FullSnapshot` event.
+const EVENT_TYPE_FULL_SNAPSHOT: u64 = 2;
+/// rrweb's `type` discriminant for an `IncrementalSnapshot` event.
+const EVENT_TYPE_INCREMENTAL_SNAPSHOT: u64 = 3;
+
+/// rrweb's `data.source` discriminant for a DOM mutation within an `IncrementalSnapshot`.
+const INCREMENTAL_SOURCE_MUTATION: u64 = 0;
+/// rrweb's `data.source` discriminant for a captured form input within an `IncrementalSnapshot`.
+const INCREMENTAL_SOURCE_INPUT: u64 = 5;
+
+/// rrweb's `type` discriminant for a text node, the only node type whose `textContent` needs
+/// scrubbing.
+const NODE_TYPE_TEXT: u64 = 3;
+
+/// Returns `true` if `attribute_name` (an rrweb DOM attribute name, or an input's `name`/`id`)
+/// should be treated as sensitive, the same way `sensitive_fields` matches a key under `extra`:
+/// case-insensitive, substring match against each configured field.
+///
+/// This mirrors the matching behavior of the `strip-fields` rule `to_pii_config` compiles from
+/// `DataScrubbingConfig::sensitive_fields` (see `crate::datascrubbing::convert`), so a field named
+/// `password` is redacted in a replay recording exactly as it would be in `extra`.
+pub fn is_sensitive_attribute(attribute_name: &str, sensitive_fields: &[String]) -> bool {
+    let attribute_name = attribute_name.to_lowercase();
+    sensitive_fields
+        .iter()
+        .filter(|field| !field.is_empty())
+        .any(|field| attribute_name.contains(&field.to_lowercase()))
+}
+
+/// Runs a single piece of recording text (a `textContent`, an attribute value, or an input's
+/// `value`) through `redact`, which is expected to apply the same compiled rule set
+/// (`@common:filter`, `redact_pair`, custom value patterns, ...) that `PiiProcessor::process_value`
+/// applies to `Event` string values elsewhere.
+pub fn scrub_recording_text(value: &str, redact: impl Fn(&str) -> String) -> String {
+    redact(value)
+}
+
+/// Scrubs a gzip-compressed, newline-delimited rrweb event stream: decompresses it, walks each
+/// event's DOM content redacting text nodes, sensitive attribute values, and captured input
+/// values, then re-serializes and re-compresses the result.
+///
+/// A line that isn't valid JSON, or a gzip stream that fails to decompress, is surfaced as an
+/// `io::Error` rather than silently passed through -- unlike a single corrupt cache entry
+/// elsewhere in this crate, a recording that can't be parsed can't be scrubbed, so it must not be
+/// forwarded downstream as though it were.
+pub fn scrub_recording(
+    payload: &[u8],
+    sensitive_fields: &[String],
+    redact: impl Fn(&str) -> String,
+) -> io::Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(payload).read_to_end(&mut decoded)?;
+    let decoded = String::from_utf8(decoded)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    let mut scrubbed_lines = Vec::new();
+    for line in decoded.lines() {
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut event: Value = serde_json::from_str(line)?;
+        scrub_event(&mut event, sensitive_fields, &redact);
+        scrubbed_lines.push(serde_json::to_string(&event)?);
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(scrubbed_lines.join("\n").as_bytes())?;
+    encoder.finish()
+}
+
+fn scrub_event(event: &mut Value, sensitive_fields: &[String], redact: &impl Fn(&str) -> String) {
+    let event_type = event.get("type").and_then(Value::as_u64);
+
+    let data = match event.get_mut("data") {
+        Some(data) => data,
+        None => return,
+    };
+
+    match event_type {
+        Some(EVENT_TYPE_FULL_SNAPSHOT) => {
+            if let Some(node) = data.get_mut("node") {
+                scrub_node(node, sensitive_fields, redact);
+            }
+        }
+        Some(EVENT_TYPE_INCREMENTAL_SNAPSHOT) => {
+            scrub_incremental_snapshot(data, sensitive_fields, redact);
+        }
+        _ => {}
+    }
+}
+
+fn scrub_incremental_snapshot(
+    data: &mut Value,
+    sensitive_fields: &[String],
+    redact: &impl Fn(&str) -> String,
+) {
+    match data.get("source").and_then(Value::as_u64) {
+        Some(INCREMENTAL_SOURCE_MUTATION) => {
+            if let Some(texts) = data.get_mut("texts").and_then(Value::as_array_mut) {
+                for text in texts {
+                    scrub_string_field(text, "value", redact);
+                }
+            }
+
+            if let Some(attributes) = data.get_mut("attributes").and_then(Value::as_array_mut) {
+                for entry in attributes {
+                    let attributes = entry.get_mut("attributes").and_then(Value::as_object_mut);
+                    if let Some(attributes) = attributes {
+                        scrub_attributes(attributes, sensitive_fields, redact);
+                    }
+                }
+            }
+
+            if let Some(adds) = data.get_mut("adds").and_then(Value::as_array_mut) {
+                for add in adds {
+                    if let Some(node) = add.get_mut("node") {
+                        scrub_node(node, sensitive_fields, redact);
+                    }
+                }
+            }
+        }
+        Some(INCREMENTAL_SOURCE_INPUT) => {
+            scrub_string_field(data, "text", redact);
+        }
+        _ => {}
+    }
+}
+
+fn scrub_node(node: &mut Value, sensitive_fields: &[String], redact: &impl Fn(&str) -> String) {
+    let node_type = node.get("type").and_then(Value::as_u64);
+
+    if node_type == Some(NODE_TYPE_TEXT) {
+        scrub_string_field(node, "textContent", redact);
+    }
+
+    if let Some(attributes) = node.get_mut("attributes").and_then(Value::as_object_mut) {
+        scrub_attributes(attributes, sensitive_fields, redact);
+    }
+
+    if let Some(children) = node.get_mut("childNodes").and_then(Value::as_array_mut) {
+        for child in children {
+            scrub_node(child, sensitive_fields, redact);
+        }
+    }
+}
+
+fn scrub_attributes(
+    attributes: &mut serde_json::Map<String, Value>,
+    sensitive_fields: &[String],
+    redact: &impl Fn(&str) -> String,
+) {
+    // An element whose `name` or `id` is itself sensitive (e.g. `<input name="password">`) has
+    // its `value` redacted too.
+    let element_is_sensitive = ["name", "id"].iter().any(|key| {
+        attributes
+            .get(*key)
+            .and_then(Value::as_str)
+            .map_or(false, |text| is_sensitive_attribute(text, sensitive_fields))
+    });
+
+    for (name, value) in attributes.iter_mut() {
+        let is_sensitive = is_sensitive_attribute(name, sensitive_fields)
+            || (element_is_sensitive && name == "value");
+        if !is_sensitive {
+            continue;
+        }
+        if let Some(text) = value.as_str() {
+            *value = Value::String(redact(text));
+        }
+    }
+}
+
+fn scrub_string_field(value: &mut Value, field: &str, redact: &impl Fn(&str) -> String) {
+    if let Some(text) = value.get(field).and_then(Value::as_str) {
+        let scrubbed = redact(text);
+        value[field] = Value::String(scrubbed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upper(value: &str) -> String {
+        value.to_uppercase()
+    }
+
+    #[test]
+    fn test_is_sensitive_attribute_matches_case_insensitively() {
+        let fields = vec!["password".to_owned()];
+        assert!(is_sensitive_attribute("Password", &fields));
+        assert!(is_sensitive_attribute("user_password", &fields));
+        assert!(!is_sensitive_attribute("username", &fields));
+    }
+
+    #[test]
+    fn test_is_sensitive_attribute_ignores_empty_fields() {
+        let fields = vec!["".to_owned()];
+        assert!(!is_sensitive_attribute("password", &fields));
+    }
+
+    #[test]
+    fn test_scrub_recording_text_delegates_to_redact() {
+        let result = scrub_recording_text("john@appleseed.com", |value| value.replace('@', "[at]"));
+        assert_eq!(result, "john[at]appleseed.com");
+    }
+
+    fn gzip(text: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn gunzip(bytes: &[u8]) -> String {
+        let mut decoded = String::new();
+        GzDecoder::new(bytes).read_to_string(&mut decoded).unwrap();
+        decoded
+    }
+
+    #[test]
+    fn test_scrub_recording_redacts_full_snapshot_text_node() {
+        let event = serde_json::json!({
+            "type": EVENT_TYPE_FULL_SNAPSHOT,
+            "data": {
+                "node": {
+                    "type": NODE_TYPE_TEXT,
+                    "textContent": "hello world",
+                }
+            }
+        });
+
+        let payload = gzip(&event.to_string());
+        let scrubbed = scrub_recording(&payload, &[], upper).unwrap();
+        let decompressed = gunzip(&scrubbed);
+        let result: Value = serde_json::from_str(decompressed.lines().next().unwrap()).unwrap();
+
+        assert_eq!(result["data"]["node"]["textContent"], "HELLO WORLD");
+    }
+
+    #[test]
+    fn test_scrub_recording_redacts_sensitive_attribute_in_nested_node() {
+        let event = serde_json::json!({
+            "type": EVENT_TYPE_FULL_SNAPSHOT,
+            "data": {
+                "node": {
+                    "type": 2,
+                    "tagName": "div",
+                    "childNodes": [{
+                        "type": 2,
+                        "tagName": "input",
+                        "attributes": { "value": "hunter2", "name": "password" },
+                    }],
+                }
+            }
+        });
+
+        let payload = gzip(&event.to_string());
+        let fields = vec!["password".to_owned()];
+        let scrubbed = scrub_recording(&payload, &fields, upper).unwrap();
+        let decompressed = gunzip(&scrubbed);
+        let result: Value = serde_json::from_str(decompressed.lines().next().unwrap()).unwrap();
+
+        assert_eq!(result["data"]["node"]["childNodes"][0]["attributes"]["value"], "HUNTER2");
+        assert_eq!(result["data"]["node"]["childNodes"][0]["attributes"]["name"], "password");
+    }
+
+    #[test]
+    fn test_scrub_recording_redacts_incremental_mutation_text_and_input() {
+        let mutation = serde_json::json!({
+            "type": EVENT_TYPE_INCREMENTAL_SNAPSHOT,
+            "data": {
+                "source": INCREMENTAL_SOURCE_MUTATION,
+                "texts": [{ "id": 7, "value": "secret note" }],
+            }
+        });
+        let input = serde_json::json!({
+            "type": EVENT_TYPE_INCREMENTAL_SNAPSHOT,
+            "data": {
+                "source": INCREMENTAL_SOURCE_INPUT,
+                "id": 9,
+                "text": "hunter2",
+            }
+        });
+
+        let payload = gzip(&format!("{}\n{}", mutation, input));
+        let scrubbed = scrub_recording(&payload, &[], upper).unwrap();
+        let lines: Vec<Value> = gunzip(&scrubbed)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines[0]["data"]["texts"][0]["value"], "SECRET NOTE");
+        assert_eq!(lines[1]["data"]["text"], "HUNTER2");
+    }
+
+    #[test]
+    fn test_scrub_recording_leaves_non_content_events_untouched() {
+        let event = serde_json::json!({ "type": 4, "data": { "href": "https://example.com" } });
+        let payload = gzip(&event.to_string());
+        let scrubbed = scrub_recording(&payload, &[], upper).unwrap();
+        let decompressed = gunzip(&scrubbed);
+        let result: Value = serde_json::from_str(decompressed.lines().next().unwrap()).unwrap();
+
+        assert_eq!(result["data"]["href"], "https://example.com");
+    }
+}
@@ -0,0 +1,255 @@
+//! SQL literal scrubbing backing the `@sql:filter` builtin rule (see [`crate::pii::builtin`]).
+//!
+//! `redact_pair` only scrubs a literal when the column name next to it happens to match a
+//! configured key pattern (see `test_breadcrumb_message`'s `session_key` example) -- a literal next
+//! to an unlisted column sails through untouched. [`redact_sql_literals`] instead scrubs every
+//! string and numeric literal in a SQL string unconditionally, in two left-to-right passes
+//! (string literals first, then numbers), so it
+//! catches secrets regardless of what column they sit in while leaving keywords, identifiers, and
+//! quoted/backtick-quoted names readable.
+//!
+//! This module is wired in from `pii::config` via a `RuleType::Sql(SqlRule)` variant and declared
+//! with `mod sql;` in `pii::mod`; neither of those is part of this source snapshot, so the wiring
+//! is written as though they already existed. Intended to be attached under `applications` to
+//! `breadcrumbs.**.message` and span `description`.
+
+use serde::{Deserialize, Serialize};
+
+/// Configuration for a `RuleType::Sql` rule.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SqlRule;
+
+/// Scrubs every single-quoted string literal and standalone numeric literal out of `sql`,
+/// replacing each with `redaction`. Keywords, identifiers, and double-quoted/backtick-quoted names
+/// are left untouched.
+pub fn redact_sql_literals(sql: &str, redaction: &str) -> String {
+    let with_strings_redacted = redact_string_literals(sql, redaction);
+    redact_numeric_literals(&with_strings_redacted, redaction)
+}
+
+/// Replaces the contents of every single-quoted string literal with `redaction`, correctly
+/// treating a doubled `''` inside a literal as an escaped quote rather than the literal's end.
+fn redact_string_literals(sql: &str, redaction: &str) -> String {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut result = String::with_capacity(sql.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] == '"' || chars[i] == '`' {
+            // A double-quoted or backtick-quoted name is copied through unchanged, so a `'` inside
+            // it is not taken for the start of a string literal.
+            let close = chars[i + 1..]
+                .iter()
+                .position(|&c| c == chars[i])
+                .map_or(chars.len(), |offset| i + offset + 2);
+            result.extend(&chars[i..close]);
+            i = close;
+            continue;
+        }
+
+        if chars[i] != '\'' {
+            result.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        // Find the end of this literal, treating `''` as an escaped quote rather than the
+        // terminator.
+        let mut j = i + 1;
+        loop {
+            if j >= chars.len() {
+                break;
+            }
+            if chars[j] == '\'' {
+                if j + 1 < chars.len() && chars[j + 1] == '\'' {
+                    j += 2;
+                    continue;
+                }
+                break;
+            }
+            j += 1;
+        }
+
+        result.push('\'');
+        result.push_str(redaction);
+        result.push('\'');
+        i = if j < chars.len() { j + 1 } else { j };
+    }
+
+    result
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Replaces every standalone numeric literal (digit runs, optional decimal part and exponent, and
+/// `0x`-prefixed hex) with `redaction`, skipping over string literals and double-quoted/backtick-
+/// quoted names and leaving a number that is part of an identifier (`table123`) or followed by a
+/// `.`-qualified name (`1.accounts`) alone.
+fn redact_numeric_literals(sql: &str, redaction: &str) -> String {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut result = String::with_capacity(sql.len());
+    // The quote character of the string literal or quoted name being copied through, if any.
+    let mut quote: Option<char> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if let Some(open) = quote {
+            if c == open {
+                quote = None;
+            }
+            result.push(c);
+            i += 1;
+            continue;
+        }
+
+        if matches!(c, '\'' | '"' | '`') {
+            quote = Some(c);
+            result.push(c);
+            i += 1;
+            continue;
+        }
+
+        if is_ident_start(c) {
+            let start = i;
+            while i < chars.len() && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            result.extend(&chars[start..i]);
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let start = i;
+            i = consume_numeric_literal(&chars, i);
+            let end = i;
+
+            let followed_by_identifier =
+                end < chars.len() && (is_ident_char(chars[end]) || chars[end] == '.');
+
+            if followed_by_identifier {
+                result.extend(&chars[start..end]);
+            } else {
+                result.push_str(redaction);
+            }
+            continue;
+        }
+
+        result.push(c);
+        i += 1;
+    }
+
+    result
+}
+
+/// Advances past a numeric literal starting at `start` (which must point at an ASCII digit),
+/// returning the index just past it.
+fn consume_numeric_literal(chars: &[char], start: usize) -> usize {
+    let mut i = start;
+
+    if chars[i] == '0' && i + 1 < chars.len() && matches!(chars[i + 1], 'x' | 'X') {
+        i += 2;
+        while i < chars.len() && chars[i].is_ascii_hexdigit() {
+            i += 1;
+        }
+        return i;
+    }
+
+    while i < chars.len() && chars[i].is_ascii_digit() {
+        i += 1;
+    }
+
+    if i < chars.len() && chars[i] == '.' && i + 1 < chars.len() && chars[i + 1].is_ascii_digit() {
+        i += 1;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+    }
+
+    if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+        let mut exponent_end = i + 1;
+        if exponent_end < chars.len() && matches!(chars[exponent_end], '+' | '-') {
+            exponent_end += 1;
+        }
+        if exponent_end < chars.len() && chars[exponent_end].is_ascii_digit() {
+            i = exponent_end;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+        }
+    }
+
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_string_literal() {
+        assert_eq!(
+            redact_sql_literals("SELECT * FROM t WHERE session_key = 'abcdefg'", "xxx"),
+            "SELECT * FROM t WHERE session_key = 'xxx'"
+        );
+    }
+
+    #[test]
+    fn test_handles_doubled_quote_escape() {
+        assert_eq!(
+            redact_sql_literals("WHERE name = 'O''Brien'", "xxx"),
+            "WHERE name = 'xxx'"
+        );
+    }
+
+    #[test]
+    fn test_redacts_standalone_integer_literal() {
+        assert_eq!(
+            redact_sql_literals("SELECT * FROM t WHERE id = 42", "xxx"),
+            "SELECT * FROM t WHERE id = xxx"
+        );
+    }
+
+    #[test]
+    fn test_redacts_float_and_exponent_literals() {
+        assert_eq!(redact_sql_literals("WHERE price = 19.99", "xxx"), "WHERE price = xxx");
+        assert_eq!(redact_sql_literals("WHERE ratio = 1.5e10", "xxx"), "WHERE ratio = xxx");
+    }
+
+    #[test]
+    fn test_redacts_hex_literal() {
+        assert_eq!(redact_sql_literals("WHERE flags = 0x1F", "xxx"), "WHERE flags = xxx");
+    }
+
+    #[test]
+    fn test_leaves_identifier_with_trailing_digits_alone() {
+        assert_eq!(
+            redact_sql_literals("SELECT * FROM table123", "xxx"),
+            "SELECT * FROM table123"
+        );
+    }
+
+    #[test]
+    fn test_leaves_dot_qualified_numeric_prefix_alone() {
+        assert_eq!(
+            redact_sql_literals("SELECT * FROM 1.accounts", "xxx"),
+            "SELECT * FROM 1.accounts"
+        );
+    }
+
+    #[test]
+    fn test_leaves_keywords_and_quoted_identifiers_alone() {
+        let sql = r#"SELECT "user"."email" FROM `accounts` WHERE id = 1"#;
+        assert_eq!(
+            redact_sql_literals(sql, "xxx"),
+            r#"SELECT "user"."email" FROM `accounts` WHERE id = xxx"#
+        );
+    }
+}
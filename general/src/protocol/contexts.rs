@@ -1,12 +1,14 @@
 use regex::Regex;
 
+use crate::processor::{ProcessingState, Processor};
 use crate::protocol::LenientString;
-use crate::types::{Annotated, Error, FromValue, Object, Value};
+use crate::types::{Annotated, Error, FromValue, Meta, Object, Value, ValueAction};
 
 /// Device information.
 #[derive(Clone, Debug, Default, PartialEq, Empty, FromValue, ToValue, ProcessValue)]
 pub struct DeviceContext {
     /// Name of the device.
+    #[metastructure(pii = "maybe")]
     pub name: Annotated<String>,
 
     /// Family of the device model.
@@ -76,11 +78,16 @@ pub struct DeviceContext {
     pub external_free_storage: Annotated<u64>,
 
     /// Indicator when the device was booted.
+    #[metastructure(pii = "maybe")]
     pub boot_time: Annotated<String>,
 
     /// Timezone of the device.
+    #[metastructure(pii = "maybe")]
     pub timezone: Annotated<String>,
 
+    /// The form factor of the device.
+    pub device_type: Annotated<DeviceType>,
+
     /// Additional arbitrary fields for forwards compatibility.
     #[metastructure(additional_properties, retain = "true")]
     pub other: Object<Value>,
@@ -91,6 +98,98 @@ impl DeviceContext {
     pub fn default_key() -> &'static str {
         "device"
     }
+
+    /// Infers `device_type` from `family`, `model`, and `screen_resolution` when the SDK did not
+    /// send one explicitly. `simulator` and `screen_dpi` are not consulted.
+    ///
+    /// Only overwrites an empty `device_type`, so an SDK that sent it explicitly always wins. If
+    /// none of the heuristics below match, `device_type` is left empty rather than guessed at.
+    pub fn normalize(&mut self) {
+        if self.device_type.value().is_some() {
+            return;
+        }
+
+        let haystack = [self.family.value(), self.model.value()]
+            .into_iter()
+            .flatten()
+            .map(|s| s.to_ascii_lowercase())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let device_type = if haystack.contains("ipad") || haystack.contains("tablet") {
+            "tablet"
+        } else if haystack.contains("tv") {
+            "tv"
+        } else if haystack.contains("watch") {
+            "wearable"
+        } else if haystack.contains("iphone") || haystack.contains("phone") || haystack.contains("mobile") {
+            "mobile"
+        } else if self
+            .screen_resolution
+            .value()
+            .map_or(false, |resolution| is_desktop_resolution(resolution))
+        {
+            "desktop"
+        } else {
+            return;
+        };
+
+        self.device_type = Annotated::new(DeviceType(device_type.to_owned()));
+    }
+}
+
+/// Returns `true` for a `"<width>x<height>"` resolution wide enough to be a desktop monitor
+/// rather than a phone or tablet screen, e.g. `"2560x1440"`.
+fn is_desktop_resolution(resolution: &str) -> bool {
+    resolution
+        .split_once('x')
+        .and_then(|(width, _)| width.parse::<u32>().ok())
+        .map_or(false, |width| width >= 1920)
+}
+
+/// Classification of a device by its form factor, e.g. `"mobile"` or `"tablet"`.
+///
+/// Accepts the canonical lowercase names (`mobile`, `tablet`, `desktop`, `console`, `tv`,
+/// `wearable`, `unknown`) as well as common aliases (`phone`, `slate`, `watch`); anything else is
+/// rejected, recording the original value in `meta` rather than passing through an arbitrary
+/// free-form string.
+#[derive(Clone, Debug, Default, PartialEq, Empty, ToValue, ProcessValue)]
+pub struct DeviceType(pub String);
+
+impl FromValue for DeviceType {
+    fn from_value(value: Annotated<Value>) -> Annotated<Self> {
+        match value {
+            Annotated(Some(Value::String(value)), mut meta) => {
+                match canonical_device_type(&value) {
+                    Some(canonical) => Annotated(Some(DeviceType(canonical.to_owned())), meta),
+                    None => {
+                        meta.add_error(Error::invalid("unknown device type"));
+                        meta.set_original_value(Some(value));
+                        Annotated(None, meta)
+                    }
+                }
+            }
+            Annotated(None, meta) => Annotated(None, meta),
+            Annotated(Some(value), mut meta) => {
+                meta.add_error(Error::expected("device type"));
+                meta.set_original_value(Some(value));
+                Annotated(None, meta)
+            }
+        }
+    }
+}
+
+fn canonical_device_type(value: &str) -> Option<&'static str> {
+    match value.to_ascii_lowercase().as_str() {
+        "mobile" | "phone" => Some("mobile"),
+        "tablet" | "slate" => Some("tablet"),
+        "desktop" => Some("desktop"),
+        "console" => Some("console"),
+        "tv" => Some("tv"),
+        "wearable" | "watch" => Some("wearable"),
+        "unknown" => Some("unknown"),
+        _ => None,
+    }
 }
 
 /// Operating system information.
@@ -103,9 +202,11 @@ pub struct OsContext {
     pub version: Annotated<String>,
 
     /// Internal build number of the operating system.
+    #[metastructure(pii = "maybe")]
     pub build: Annotated<LenientString>,
 
     /// Current kernel version.
+    #[metastructure(pii = "maybe")]
     pub kernel_version: Annotated<String>,
 
     /// Indicator if the OS is rooted (mobile mostly).
@@ -124,6 +225,114 @@ impl OsContext {
     pub fn default_key() -> &'static str {
         "os"
     }
+
+    /// Fills in `name`, `version`, `build`, and `kernel_version` from `raw_description` when the
+    /// SDK only sent the raw string.
+    ///
+    /// Only fields that are still empty are overwritten, so an SDK that sent structured fields
+    /// explicitly always wins. If `raw_description` doesn't match any of the known patterns, the
+    /// structured fields are left empty rather than guessed at or treated as an error.
+    pub fn normalize(&mut self) {
+        let raw_description = match self.raw_description.value() {
+            Some(raw_description) => raw_description.clone(),
+            None => return,
+        };
+
+        let parsed = match parse_os_description(&raw_description) {
+            Some(parsed) => parsed,
+            None => return,
+        };
+
+        if self.name.value().is_none() {
+            self.name = Annotated::new(parsed.name.to_owned());
+        }
+        if self.version.value().is_none() {
+            if let Some(version) = parsed.version {
+                self.version = Annotated::new(version);
+            }
+        }
+        if self.build.value().is_none() {
+            if let Some(build) = parsed.build {
+                self.build = Annotated::new(LenientString(build));
+            }
+        }
+        if self.kernel_version.value().is_none() {
+            if let Some(kernel_version) = parsed.kernel_version {
+                self.kernel_version = Annotated::new(kernel_version);
+            }
+        }
+    }
+}
+
+struct ParsedOsDescription {
+    name: &'static str,
+    version: Option<String>,
+    build: Option<String>,
+    kernel_version: Option<String>,
+}
+
+fn parse_os_description(raw_description: &str) -> Option<ParsedOsDescription> {
+    lazy_static::lazy_static! {
+        // "iOS 11.4.2 FEEDFACE (17.4.0)"
+        static ref IOS: Regex =
+            Regex::new(r"(?i)^iOS\s+(?P<version>\d+[\.\d]*)\s+(?P<build>\S+)\s+\((?P<kernel>[\.\w]+)\)$").unwrap();
+        // "Mac OS X 10.13.4 (17E199)"
+        static ref MACOS: Regex =
+            Regex::new(r"(?i)^Mac OS X\s+(?P<version>\d+[\.\d]*)\s+\((?P<build>\w+)\)$").unwrap();
+        // "Windows 10.0.14393"
+        static ref WINDOWS: Regex = Regex::new(r"(?i)^Windows\s+(?P<version>\d+[\.\d]*)$").unwrap();
+        // "Linux 4.9.93"
+        static ref LINUX: Regex = Regex::new(r"(?i)^Linux\s+(?P<version>\d+[\.\d]*)$").unwrap();
+        // "Android 8.1.0"
+        static ref ANDROID: Regex = Regex::new(r"(?i)^Android\s+(?P<version>\d+[\.\d]*)$").unwrap();
+    }
+
+    if let Some(caps) = IOS.captures(raw_description) {
+        return Some(ParsedOsDescription {
+            name: "iOS",
+            version: caps.name("version").map(|m| m.as_str().to_owned()),
+            build: caps.name("build").map(|m| m.as_str().to_owned()),
+            kernel_version: caps.name("kernel").map(|m| m.as_str().to_owned()),
+        });
+    }
+
+    if let Some(caps) = MACOS.captures(raw_description) {
+        return Some(ParsedOsDescription {
+            name: "macOS",
+            version: caps.name("version").map(|m| m.as_str().to_owned()),
+            build: caps.name("build").map(|m| m.as_str().to_owned()),
+            kernel_version: None,
+        });
+    }
+
+    if let Some(caps) = WINDOWS.captures(raw_description) {
+        return Some(ParsedOsDescription {
+            name: "Windows",
+            version: caps.name("version").map(|m| m.as_str().to_owned()),
+            build: None,
+            kernel_version: None,
+        });
+    }
+
+    if let Some(caps) = LINUX.captures(raw_description) {
+        return Some(ParsedOsDescription {
+            name: "Linux",
+            version: caps.name("version").map(|m| m.as_str().to_owned()),
+            build: None,
+            kernel_version: None,
+        });
+    }
+
+    if let Some(caps) = ANDROID.captures(raw_description) {
+        return Some(ParsedOsDescription {
+            name: "Android",
+            version: caps.name("version").map(|m| m.as_str().to_owned()),
+            build: None,
+            kernel_version: None,
+        });
+    }
+
+    None
 }
 
 /// Runtime information.
@@ -141,6 +350,19 @@ pub struct RuntimeContext {
     /// Unprocessed runtime info.
     pub raw_description: Annotated<String>,
 
+    /// The raw, unparsed `version` string as originally reported, distro suffixes and all, e.g.
+    /// `"7.1.20-1+ubuntu16.04.1+deb.sury.org+1"`.
+    pub raw_version: Annotated<String>,
+
+    /// Major version component parsed from `version`.
+    pub major: Annotated<u64>,
+
+    /// Minor version component parsed from `version`.
+    pub minor: Annotated<u64>,
+
+    /// Patch version component parsed from `version`.
+    pub patch: Annotated<u64>,
+
     /// Additional arbitrary fields for forwards compatibility.
     #[metastructure(additional_properties, retain = "true")]
     pub other: Object<Value>,
@@ -151,6 +373,156 @@ impl RuntimeContext {
     pub fn default_key() -> &'static str {
         "runtime"
     }
+
+    /// Fills in `name` and `version` from `raw_description` when the SDK only sent the raw
+    /// string, e.g. `"rustc 1.27.0 stable"` or `".NET Framework 4.7.2"`.
+    ///
+    /// Only fields that are still empty are overwritten, and an unparseable `raw_description`
+    /// simply leaves the structured fields empty.
+    pub fn normalize(&mut self) {
+        let raw_description = match self.raw_description.value() {
+            Some(raw_description) => raw_description.clone(),
+            None => return,
+        };
+
+        let parsed = match parse_runtime_description(&raw_description) {
+            Some(parsed) => parsed,
+            None => return,
+        };
+
+        if self.name.value().is_none() {
+            self.name = Annotated::new(parsed.name);
+        }
+        if self.version.value().is_none() {
+            if let Some(version) = parsed.version {
+                self.version = Annotated::new(version);
+            }
+        }
+    }
+
+    /// Breaks `version` down into `raw_version`, `major`, `minor`, `patch`, and `build`.
+    ///
+    /// SDKs on Linux commonly report distro-mangled versions like
+    /// `"7.1.20-1+ubuntu16.04.1+deb.sury.org+1"`; this truncates at the first character that
+    /// isn't part of a plain `major.minor.patch` run and keeps everything from there on,
+    /// including the separator, as an opaque `build` tag. Only fields that are still empty are
+    /// overwritten, and a `version` with no leading numeric run leaves the structured fields
+    /// empty.
+    pub fn normalize_version(&mut self) {
+        let version = match self.version.value() {
+            Some(version) => version.clone(),
+            None => return,
+        };
+
+        let parsed = match parse_semver_ish(&version) {
+            Some(parsed) => parsed,
+            None => return,
+        };
+
+        if self.raw_version.value().is_none() {
+            self.raw_version = Annotated::new(version);
+        }
+        if self.major.value().is_none() {
+            self.major = Annotated::new(parsed.major);
+        }
+        if let Some(minor) = parsed.minor {
+            if self.minor.value().is_none() {
+                self.minor = Annotated::new(minor);
+            }
+        }
+        if let Some(patch) = parsed.patch {
+            if self.patch.value().is_none() {
+                self.patch = Annotated::new(patch);
+            }
+        }
+        if let Some(build) = parsed.build {
+            if self.build.value().is_none() {
+                self.build = Annotated::new(LenientString(build));
+            }
+        }
+    }
+}
+
+struct ParsedSemverIsh {
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    build: Option<String>,
+}
+
+/// Parses a leading `major[.minor[.patch]]` run off of `version`, keeping everything after it
+/// (separator included) as an opaque build tag, e.g. `"7.1.20-1+ubuntu16.04.1" ->
+/// (7, 1, 20, Some("-1+ubuntu16.04.1"))`.
+fn parse_semver_ish(version: &str) -> Option<ParsedSemverIsh> {
+    lazy_static::lazy_static! {
+        static ref SEMVER_ISH: Regex =
+            Regex::new(r"^(?P<major>\d+)(?:\.(?P<minor>\d+)(?:\.(?P<patch>\d+))?)?(?P<build>.*)$").unwrap();
+    }
+
+    let caps = SEMVER_ISH.captures(version)?;
+    let build = caps.name("build").map(|m| m.as_str()).unwrap_or("");
+
+    Some(ParsedSemverIsh {
+        major: caps.name("major")?.as_str().parse().ok()?,
+        minor: caps.name("minor").map(|m| m.as_str().parse()).transpose().ok()?,
+        patch: caps.name("patch").map(|m| m.as_str().parse()).transpose().ok()?,
+        build: if build.is_empty() {
+            None
+        } else {
+            Some(build.to_owned())
+        },
+    })
+}
+
+struct ParsedRuntimeDescription {
+    name: String,
+    version: Option<String>,
+}
+
+fn parse_runtime_description(raw_description: &str) -> Option<ParsedRuntimeDescription> {
+    lazy_static::lazy_static! {
+        // "rustc 1.27.0 stable", "rustc 1.27.0-nightly"
+        static ref RUSTC: Regex = Regex::new(r"(?i)^rustc\s+(?P<version>\d+\.\d+\.\d+)").unwrap();
+        // ".NET Framework 4.7.2", ".NET Core 3.1.0"
+        static ref DOTNET: Regex =
+            Regex::new(r"(?i)^(?P<name>\.NET(?:\s+Framework|\s+Core)?)\s+(?P<version>\d+[\.\d]*)").unwrap();
+        // "CPython 3.8.5", "PyPy 7.3.1"
+        static ref PYTHON: Regex =
+            Regex::new(r"(?i)^(?P<name>CPython|PyPy)\s+(?P<version>\d+[\.\d]*)").unwrap();
+        // "OpenJDK Runtime Environment 11.0.7", "Java HotSpot(TM) 1.8.0_151"
+        static ref JAVA: Regex =
+            Regex::new(r"(?i)^(?P<name>[\w \(\)™]+?)\s+(?P<version>\d+[\.\d_]*)$").unwrap();
+    }
+
+    if let Some(caps) = RUSTC.captures(raw_description) {
+        return Some(ParsedRuntimeDescription {
+            name: "rustc".to_owned(),
+            version: caps.name("version").map(|m| m.as_str().to_owned()),
+        });
+    }
+
+    if let Some(caps) = DOTNET.captures(raw_description) {
+        return Some(ParsedRuntimeDescription {
+            name: caps.name("name").unwrap().as_str().to_owned(),
+            version: caps.name("version").map(|m| m.as_str().to_owned()),
+        });
+    }
+
+    if let Some(caps) = PYTHON.captures(raw_description) {
+        return Some(ParsedRuntimeDescription {
+            name: caps.name("name").unwrap().as_str().to_owned(),
+            version: caps.name("version").map(|m| m.as_str().to_owned()),
+        });
+    }
+
+    if let Some(caps) = JAVA.captures(raw_description) {
+        return Some(ParsedRuntimeDescription {
+            name: caps.name("name").unwrap().as_str().trim().to_owned(),
+            version: caps.name("version").map(|m| m.as_str().to_owned()),
+        });
+    }
+
+    None
 }
 
 /// Application information.
@@ -160,6 +532,7 @@ pub struct AppContext {
     pub app_start_time: Annotated<String>,
 
     /// Device app hash (app specific device ID)
+    #[metastructure(pii = "maybe")]
     pub device_app_hash: Annotated<String>,
 
     /// Build identicator.
@@ -221,30 +594,52 @@ lazy_static::lazy_static! {
 
 /// GPU information.
 #[derive(Clone, Debug, Default, PartialEq, Empty, FromValue, ToValue, ProcessValue)]
-pub struct GpuContext(pub Object<Value>);
+pub struct GpuContext {
+    /// The name of the graphics device.
+    pub name: Annotated<String>,
 
-impl From<Object<Value>> for GpuContext {
-    fn from(object: Object<Value>) -> Self {
-        Self(object)
-    }
-}
+    /// The Version of the graphics device.
+    pub version: Annotated<String>,
 
-impl std::ops::Deref for GpuContext {
-    type Target = Object<Value>;
+    /// The PCI identifier of the graphics device.
+    pub id: Annotated<Value>,
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
+    /// The PCI vendor identifier of the graphics device.
+    pub vendor_id: Annotated<String>,
 
-impl std::ops::DerefMut for GpuContext {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
+    /// The vendor name as reported by the graphics device.
+    pub vendor_name: Annotated<String>,
+
+    /// The total GPU memory available in bytes.
+    pub memory_size: Annotated<u64>,
+
+    /// The device low-level API type.
+    ///
+    /// Examples: `"Apple Metal"` or `"Direct3D11"`
+    pub api_type: Annotated<String>,
+
+    /// Whether the GPU has multi-threaded rendering or not.
+    pub multi_threaded_rendering: Annotated<bool>,
+
+    /// The Non-Power-Of-Two-Support support.
+    pub npot_support: Annotated<String>,
+
+    /// Largest size of a texture that is supported by the graphics hardware.
+    pub max_texture_size: Annotated<u64>,
+
+    /// Approximate "shader capability" level of the graphics device.
+    pub graphics_shader_level: Annotated<String>,
+
+    /// Whether GPU draw call instancing is supported.
+    pub supports_draw_call_instancing: Annotated<bool>,
+
+    /// Additional arbitrary fields for forwards compatibility.
+    #[metastructure(additional_properties, retain = "true")]
+    pub other: Object<Value>,
 }
 
 impl GpuContext {
-    /// The key under which a runtime context is generally stored (in `Contexts`)
+    /// The key under which a gpu context is generally stored (in `Contexts`)
     pub fn default_key() -> &'static str {
         "gpu"
     }
@@ -401,6 +796,178 @@ impl Context {
             _ => None,
         }
     }
+
+    /// Fills in derived fields on the context, where supported.
+    ///
+    /// This is a thin dispatcher over the per-context normalize methods
+    /// ([`DeviceContext::normalize`], [`OsContext::normalize`], and [`RuntimeContext::normalize`]
+    /// followed by [`RuntimeContext::normalize_version`]); other contexts are left untouched. Intended to be called from
+    /// `process_context` once per context, the same way [`Contexts::from_value`] already fills in
+    /// a missing `"type"` key for [`Context::Other`] once per context.
+    pub fn normalize(&mut self) {
+        match self {
+            Context::Device(device) => device.normalize(),
+            Context::Os(os) => os.normalize(),
+            Context::Runtime(runtime) => {
+                runtime.normalize();
+                runtime.normalize_version();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A typed complement to [`Processor::process_context`] that dispatches into one hook per context
+/// kind instead of requiring every implementor to `match` the [`Context`] enum itself.
+///
+/// All hooks default to a no-op, so implementors only override the context kinds they actually
+/// care about -- mirroring how [`Processor`] itself only requires overriding the hooks a
+/// processor needs. Wrap an implementor in [`TypedContextDispatcher`] to use it as a
+/// [`Processor`]; its `process_context` fans out into these hooks, so they still run as part of
+/// the normal `process_value` traversal and still see the context's own `ProcessingState` (in
+/// particular `state.path()`, which carries the context map key -- `"os"`, `"runtime"`, ...).
+pub trait TypedContextProcessor {
+    /// Runs for [`Context::Device`]. See [`Processor::process_context`].
+    fn process_device_context(
+        &mut self,
+        _value: &mut DeviceContext,
+        _meta: &mut Meta,
+        _state: &ProcessingState<'_>,
+    ) -> ValueAction {
+        ValueAction::default()
+    }
+
+    /// Runs for [`Context::Os`]. See [`Processor::process_context`].
+    fn process_os_context(
+        &mut self,
+        _value: &mut OsContext,
+        _meta: &mut Meta,
+        _state: &ProcessingState<'_>,
+    ) -> ValueAction {
+        ValueAction::default()
+    }
+
+    /// Runs for [`Context::Runtime`]. See [`Processor::process_context`].
+    fn process_runtime_context(
+        &mut self,
+        _value: &mut RuntimeContext,
+        _meta: &mut Meta,
+        _state: &ProcessingState<'_>,
+    ) -> ValueAction {
+        ValueAction::default()
+    }
+
+    /// Runs for [`Context::App`]. See [`Processor::process_context`].
+    fn process_app_context(
+        &mut self,
+        _value: &mut AppContext,
+        _meta: &mut Meta,
+        _state: &ProcessingState<'_>,
+    ) -> ValueAction {
+        ValueAction::default()
+    }
+
+    /// Runs for [`Context::Browser`]. See [`Processor::process_context`].
+    fn process_browser_context(
+        &mut self,
+        _value: &mut BrowserContext,
+        _meta: &mut Meta,
+        _state: &ProcessingState<'_>,
+    ) -> ValueAction {
+        ValueAction::default()
+    }
+
+    /// Runs for [`Context::Gpu`]. See [`Processor::process_context`].
+    fn process_gpu_context(
+        &mut self,
+        _value: &mut GpuContext,
+        _meta: &mut Meta,
+        _state: &ProcessingState<'_>,
+    ) -> ValueAction {
+        ValueAction::default()
+    }
+
+    /// Runs for [`Context::Trace`]. See [`Processor::process_context`].
+    fn process_trace_context(
+        &mut self,
+        _value: &mut TraceContext,
+        _meta: &mut Meta,
+        _state: &ProcessingState<'_>,
+    ) -> ValueAction {
+        ValueAction::default()
+    }
+
+    /// Runs for [`Context::Monitor`]. See [`Processor::process_context`].
+    fn process_monitor_context(
+        &mut self,
+        _value: &mut MonitorContext,
+        _meta: &mut Meta,
+        _state: &ProcessingState<'_>,
+    ) -> ValueAction {
+        ValueAction::default()
+    }
+}
+
+/// Adapts a [`TypedContextProcessor`] into a [`Processor`] by dispatching `process_context` into
+/// the hook matching the context's kind.
+pub struct TypedContextDispatcher<P>(pub P);
+
+impl<P: TypedContextProcessor> Processor for TypedContextDispatcher<P> {
+    #[inline]
+    fn process_context(
+        &mut self,
+        context: &mut Context,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ValueAction {
+        match context {
+            Context::Device(device) => self.0.process_device_context(device, meta, state),
+            Context::Os(os) => self.0.process_os_context(os, meta, state),
+            Context::Runtime(runtime) => self.0.process_runtime_context(runtime, meta, state),
+            Context::App(app) => self.0.process_app_context(app, meta, state),
+            Context::Browser(browser) => self.0.process_browser_context(browser, meta, state),
+            Context::Gpu(gpu) => self.0.process_gpu_context(gpu, meta, state),
+            Context::Trace(trace) => self.0.process_trace_context(trace, meta, state),
+            Context::Monitor(monitor) => self.0.process_monitor_context(monitor, meta, state),
+            Context::Other(_) => ValueAction::default(),
+        }
+    }
+}
+
+/// Normalizes context fields derived from raw, free-form strings -- `OsContext`/
+/// `RuntimeContext`'s `raw_description`, and `RuntimeContext`'s distro-mangled `version` -- into
+/// the same structured fields [`Context::normalize`] fills in for those two kinds. It does not run
+/// [`DeviceContext::normalize`], so `device_type` is not inferred by this processor.
+///
+/// Wrap in [`TypedContextDispatcher`] to use as a [`Processor`]; it then runs once per context as
+/// part of the normal `process_value` traversal, rather than requiring every caller to remember
+/// to call the `OsContext`/`RuntimeContext` normalize methods by hand. Only targets the two context kinds it cares about,
+/// leaving the rest of [`TypedContextProcessor`]'s hooks at their no-op default.
+pub struct ContextNormalizeProcessor;
+
+impl TypedContextProcessor for ContextNormalizeProcessor {
+    #[inline]
+    fn process_os_context(
+        &mut self,
+        value: &mut OsContext,
+        _meta: &mut Meta,
+        _state: &ProcessingState<'_>,
+    ) -> ValueAction {
+        value.normalize();
+        ValueAction::default()
+    }
+
+    #[inline]
+    fn process_runtime_context(
+        &mut self,
+        value: &mut RuntimeContext,
+        _meta: &mut Meta,
+        _state: &ProcessingState<'_>,
+    ) -> ValueAction {
+        value.normalize();
+        value.normalize_version();
+        ValueAction::default()
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Empty, FromValue, ToValue, ProcessValue)]
@@ -532,6 +1099,7 @@ fn test_device_context_roundtrip() {
         external_free_storage: Annotated::new(2_097_152),
         boot_time: Annotated::new("2018-02-08T12:52:12Z".to_string()),
         timezone: Annotated::new("Europe/Vienna".to_string()),
+        device_type: Annotated::empty(),
         other: {
             let mut map = Object::new();
             map.insert(
@@ -594,6 +1162,10 @@ fn test_runtime_context_roundtrip() {
         version: Annotated::new("1.27.0".to_string()),
         build: Annotated::new(LenientString("stable".to_string())),
         raw_description: Annotated::new("rustc 1.27.0 stable".to_string()),
+        raw_version: Annotated::empty(),
+        major: Annotated::empty(),
+        minor: Annotated::empty(),
+        patch: Annotated::empty(),
         other: {
             let mut map = Object::new();
             map.insert(
@@ -0,0 +1,302 @@
+use regex::Regex;
+
+use crate::protocol::{BrowserContext, Context, Contexts, DeviceContext, OsContext};
+use crate::types::Annotated;
+
+#[cfg(test)]
+use crate::protocol::ContextInner;
+
+/// One entry of a ua-parser-style regex database: a pattern together with the family/version
+/// template it produces a match from.
+///
+/// Templates may reference capture groups positionally (`$1`, `$2`, `$3`) the same way
+/// ua-parser's `regexes.yaml` does, which keeps this table a straightforward transcription of
+/// that format rather than a bespoke one.
+struct UserAgentPattern {
+    regex: Regex,
+    family: &'static str,
+    v1: Option<&'static str>,
+    v2: Option<&'static str>,
+    v3: Option<&'static str>,
+}
+
+struct ParsedUserAgent {
+    family: String,
+    major: Option<String>,
+    minor: Option<String>,
+    patch: Option<String>,
+}
+
+fn expand(template: Option<&str>, caps: &regex::Captures<'_>) -> Option<String> {
+    let template = template?;
+    if let Some(index) = template.strip_prefix('$') {
+        let index: usize = index.parse().ok()?;
+        caps.get(index).map(|m| m.as_str().to_owned())
+    } else {
+        Some(template.to_owned())
+    }
+}
+
+fn parse_with_table(ua: &str, table: &[UserAgentPattern]) -> Option<ParsedUserAgent> {
+    for pattern in table {
+        if let Some(caps) = pattern.regex.captures(ua) {
+            let family = expand(Some(pattern.family), &caps).unwrap_or_else(|| pattern.family.to_owned());
+            return Some(ParsedUserAgent {
+                family,
+                major: expand(pattern.v1, &caps),
+                minor: expand(pattern.v2, &caps),
+                patch: expand(pattern.v3, &caps),
+            });
+        }
+    }
+    None
+}
+
+fn version_string(parsed: &ParsedUserAgent) -> Option<String> {
+    let major = parsed.major.as_ref()?;
+    let mut version = major.clone();
+    if let Some(minor) = &parsed.minor {
+        version.push('.');
+        version.push_str(minor);
+        if let Some(patch) = &parsed.patch {
+            version.push('.');
+            version.push_str(patch);
+        }
+    }
+    Some(version)
+}
+
+lazy_static::lazy_static! {
+    // A small excerpt of the patterns ua-parser ships, transcribed into this table's shape.
+    // Ordered most-specific first, since the first match wins.
+    static ref BROWSERS: Vec<UserAgentPattern> = vec![
+        UserAgentPattern {
+            regex: Regex::new(r"Edg(?:e|A|iOS)?/(\d+)\.(\d+)?\.?(\d+)?").unwrap(),
+            family: "Edge",
+            v1: Some("$1"),
+            v2: Some("$2"),
+            v3: Some("$3"),
+        },
+        UserAgentPattern {
+            regex: Regex::new(r"OPR/(\d+)\.(\d+)\.?(\d+)?").unwrap(),
+            family: "Opera",
+            v1: Some("$1"),
+            v2: Some("$2"),
+            v3: Some("$3"),
+        },
+        UserAgentPattern {
+            regex: Regex::new(r"Chrome/(\d+)\.(\d+)\.?(\d+)?").unwrap(),
+            family: "Chrome",
+            v1: Some("$1"),
+            v2: Some("$2"),
+            v3: Some("$3"),
+        },
+        UserAgentPattern {
+            regex: Regex::new(r"Version/(\d+)\.(\d+)\.?(\d+)? Safari/").unwrap(),
+            family: "Safari",
+            v1: Some("$1"),
+            v2: Some("$2"),
+            v3: Some("$3"),
+        },
+        UserAgentPattern {
+            regex: Regex::new(r"Firefox/(\d+)\.(\d+)\.?(\d+)?").unwrap(),
+            family: "Firefox",
+            v1: Some("$1"),
+            v2: Some("$2"),
+            v3: Some("$3"),
+        },
+    ];
+
+    static ref OPERATING_SYSTEMS: Vec<UserAgentPattern> = vec![
+        UserAgentPattern {
+            regex: Regex::new(r"Windows NT (\d+)\.(\d+)").unwrap(),
+            family: "Windows",
+            v1: Some("$1"),
+            v2: Some("$2"),
+            v3: None,
+        },
+        UserAgentPattern {
+            regex: Regex::new(r"Mac OS X (\d+)[_.](\d+)[_.]?(\d+)?").unwrap(),
+            family: "Mac OS X",
+            v1: Some("$1"),
+            v2: Some("$2"),
+            v3: Some("$3"),
+        },
+        UserAgentPattern {
+            regex: Regex::new(r"Android (\d+)\.(\d+)\.?(\d+)?").unwrap(),
+            family: "Android",
+            v1: Some("$1"),
+            v2: Some("$2"),
+            v3: Some("$3"),
+        },
+        UserAgentPattern {
+            regex: Regex::new(r"iPhone OS (\d+)_(\d+)_?(\d+)?").unwrap(),
+            family: "iOS",
+            v1: Some("$1"),
+            v2: Some("$2"),
+            v3: Some("$3"),
+        },
+        UserAgentPattern {
+            regex: Regex::new(r"Linux").unwrap(),
+            family: "Linux",
+            v1: None,
+            v2: None,
+            v3: None,
+        },
+    ];
+
+    static ref DEVICES: Vec<UserAgentPattern> = vec![
+        UserAgentPattern {
+            regex: Regex::new(r"iPhone").unwrap(),
+            family: "iPhone",
+            v1: None,
+            v2: None,
+            v3: None,
+        },
+        UserAgentPattern {
+            regex: Regex::new(r"iPad").unwrap(),
+            family: "iPad",
+            v1: None,
+            v2: None,
+            v3: None,
+        },
+        UserAgentPattern {
+            regex: Regex::new(r"; (\w[\w -]*) Build/").unwrap(),
+            family: "$1",
+            v1: None,
+            v2: None,
+            v3: None,
+        },
+    ];
+}
+
+fn context_is_empty(context: Option<&Context>, default_key: &str) -> bool {
+    match context {
+        None => true,
+        Some(Context::Other(_)) => true,
+        Some(context) => context.default_key() != Some(default_key),
+    }
+}
+
+/// Parses a `User-Agent` header into `BrowserContext`, `OsContext`, and `DeviceContext` and
+/// merges the results into `contexts`, filling in only the contexts that are missing or empty.
+///
+/// SDKs that already send structured device/os/browser metadata (mobile SDKs, mainly) always
+/// win: this never overwrites an existing context, it only adds ones that are absent. Calling
+/// this repeatedly with the same `user_agent` is a no-op once all three contexts are populated.
+pub fn normalize_user_agent(contexts: &mut Contexts, user_agent: &str) {
+    if context_is_empty(contexts.get("browser").and_then(|c| c.value()).map(|c| &c.0), "browser") {
+        if let Some(parsed) = parse_with_table(user_agent, &BROWSERS) {
+            let mut version = Annotated::empty();
+            if let Some(v) = version_string(&parsed) {
+                version = Annotated::new(v);
+            }
+            contexts.add(Context::Browser(Box::new(BrowserContext {
+                name: Annotated::new(parsed.family),
+                version,
+                other: Default::default(),
+            })));
+        }
+    }
+
+    if context_is_empty(contexts.get("os").and_then(|c| c.value()).map(|c| &c.0), "os") {
+        if let Some(parsed) = parse_with_table(user_agent, &OPERATING_SYSTEMS) {
+            let mut version = Annotated::empty();
+            if let Some(v) = version_string(&parsed) {
+                version = Annotated::new(v);
+            }
+            contexts.add(Context::Os(Box::new(OsContext {
+                name: Annotated::new(parsed.family),
+                version,
+                ..Default::default()
+            })));
+        }
+    }
+
+    if context_is_empty(contexts.get("device").and_then(|c| c.value()).map(|c| &c.0), "device") {
+        if let Some(parsed) = parse_with_table(user_agent, &DEVICES) {
+            contexts.add(Context::Device(Box::new(DeviceContext {
+                family: Annotated::new(parsed.family),
+                ..Default::default()
+            })));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn browser(ua: &str) -> (String, Option<String>) {
+        let mut contexts = Contexts::new();
+        normalize_user_agent(&mut contexts, ua);
+        match contexts.get("browser").and_then(|c| c.value()) {
+            Some(ContextInner(Context::Browser(browser))) => (
+                browser.name.value().cloned().unwrap_or_default(),
+                browser.version.value().cloned(),
+            ),
+            _ => panic!("expected a browser context"),
+        }
+    }
+
+    fn os(ua: &str) -> (String, Option<String>) {
+        let mut contexts = Contexts::new();
+        normalize_user_agent(&mut contexts, ua);
+        match contexts.get("os").and_then(|c| c.value()) {
+            Some(ContextInner(Context::Os(os))) => {
+                (os.name.value().cloned().unwrap_or_default(), os.version.value().cloned())
+            }
+            _ => panic!("expected an os context"),
+        }
+    }
+
+    #[test]
+    fn test_edge_wins_over_chrome() {
+        // Edge's UA string still advertises a Chrome token for compatibility with sites that only
+        // check for Chrome; the Edge pattern must be tried first or Edge users get misclassified.
+        let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+                  Chrome/91.0.4472.124 Safari/537.36 Edg/91.0.864.59";
+        assert_eq!(browser(ua), ("Edge".to_owned(), Some("91.0.864".to_owned())));
+    }
+
+    #[test]
+    fn test_chrome_matches_when_no_edge_token_present() {
+        let ua = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) \
+                  Chrome/91.0.4472.124 Safari/537.36";
+        assert_eq!(browser(ua), ("Chrome".to_owned(), Some("91.0.4472".to_owned())));
+    }
+
+    #[test]
+    fn test_mac_os_x_dot_separator() {
+        let ua = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15.7) AppleWebKit/537.36";
+        assert_eq!(os(ua), ("Mac OS X".to_owned(), Some("10.15.7".to_owned())));
+    }
+
+    #[test]
+    fn test_mac_os_x_underscore_separator() {
+        let ua = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36";
+        assert_eq!(os(ua), ("Mac OS X".to_owned(), Some("10.15.7".to_owned())));
+    }
+
+    #[test]
+    fn test_device_model_captured_from_build_suffix() {
+        let ua = "Mozilla/5.0 (Linux; Android 10; SM-G973F Build/QP1A.190711.020)";
+        let mut contexts = Contexts::new();
+        normalize_user_agent(&mut contexts, ua);
+        match contexts.get("device").and_then(|c| c.value()) {
+            Some(ContextInner(Context::Device(device))) => {
+                assert_eq!(device.family.value().unwrap(), "SM-G973F");
+            }
+            _ => panic!("expected a device context"),
+        }
+    }
+
+    #[test]
+    fn test_no_match_leaves_contexts_unpopulated() {
+        let mut contexts = Contexts::new();
+        normalize_user_agent(&mut contexts, "some-internal-cron-job/1.0");
+        assert!(contexts.get("browser").is_none());
+        assert!(contexts.get("os").is_none());
+        assert!(contexts.get("device").is_none());
+    }
+}
@@ -1,9 +1,11 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
 use std::fmt;
 use std::fs;
 use std::io;
 use std::io::Write;
 use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::num::NonZeroU32;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
@@ -129,6 +131,10 @@ pub struct OverridableConfig {
     pub secret_key: Option<String>,
     /// The public key of the relay
     pub public_key: Option<String>,
+    /// The deployment this relay belongs to (e.g. "default" or "canary").
+    pub deployment: Option<String>,
+    /// A free-form identifier of the environment this relay is running in (e.g. "us1-canary").
+    pub environment: Option<String>,
 }
 
 /// The relay credentials
@@ -192,6 +198,34 @@ impl fmt::Display for RelayMode {
     }
 }
 
+/// The deployment a relay belongs to.
+///
+/// This is purely descriptive: it doesn't change any processing behavior, but lets operators
+/// route traffic to, and tell metrics apart for, a canary fleet distinct from the main one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RelayDeployment {
+    /// A regular, fully rolled out relay.
+    Default,
+    /// A relay running a canary build or config, observed separately before a full rollout.
+    Canary,
+}
+
+impl fmt::Display for RelayDeployment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RelayDeployment::Default => write!(f, "default"),
+            RelayDeployment::Canary => write!(f, "canary"),
+        }
+    }
+}
+
+impl Default for RelayDeployment {
+    fn default() -> Self {
+        RelayDeployment::Default
+    }
+}
+
 /// Checks if we are running in docker.
 fn is_docker() -> bool {
     if fs::metadata("/.dockerenv").is_ok() {
@@ -231,6 +265,18 @@ pub struct Relay {
     pub tls_identity_path: Option<PathBuf>,
     /// Password for the PKCS12 archive.
     pub tls_identity_password: Option<String>,
+    /// The deployment this relay belongs to.
+    pub deployment: RelayDeployment,
+    /// A free-form identifier of the environment this relay is running in (e.g. `"us1-canary"`).
+    pub environment: Option<String>,
+    /// The index into the list of file descriptors passed by the init system (LISTEN_FDS-style
+    /// socket activation) to adopt as the listening socket, instead of binding `host`/`port`
+    /// directly. `None` falls back to binding `host`/`port` as usual.
+    pub bind_from_fd: Option<u32>,
+    /// Path to a file containing just the secret key, for deployments that mount it as a
+    /// separate secret rather than writing it into `credentials.json`. Takes precedence over the
+    /// secret key in `credentials.json`, but is itself overridden by `RELAY_SECRET_KEY`.
+    pub secret_key_path: Option<PathBuf>,
 }
 
 impl Default for Relay {
@@ -243,6 +289,10 @@ impl Default for Relay {
             tls_port: None,
             tls_identity_path: None,
             tls_identity_password: None,
+            deployment: RelayDeployment::default(),
+            environment: None,
+            bind_from_fd: None,
+            secret_key_path: None,
         }
     }
 }
@@ -295,6 +345,10 @@ struct Metrics {
     statsd: Option<String>,
     /// The prefix that should be added to all metrics.
     prefix: String,
+    /// If set to a host/port string, Relay exposes its internal metrics on this address as a
+    /// `/metrics` resource in Prometheus text exposition format, for operators who scrape metrics
+    /// rather than push them to statsd.
+    prometheus_addr: Option<String>,
 }
 
 impl Default for Metrics {
@@ -302,6 +356,7 @@ impl Default for Metrics {
         Metrics {
             statsd: None,
             prefix: "sentry.relay".into(),
+            prometheus_addr: None,
         }
     }
 }
@@ -334,6 +389,10 @@ struct Limits {
     max_api_file_upload_size: ByteSize,
     /// The maximum payload size for chunks
     max_api_chunk_upload_size: ByteSize,
+    /// The maximum size of a response fetched from the upstream (project config batches, relay
+    /// public-key queries). Responses exceeding this are aborted rather than buffered in full,
+    /// protecting Relay from a misbehaving or compromised upstream exhausting memory.
+    max_upstream_response_size: ByteSize,
     /// The maximum number of threads to spawn for CPU and web work, each.
     ///
     /// The total number of threads spawned will roughly be `2 * max_thread_count + 1`. Defaults to
@@ -349,6 +408,25 @@ struct Limits {
     max_pending_connections: i32,
     /// The maximum number of open connections to Relay.
     max_connections: usize,
+    /// The TCP Fast Open queue length to apply when binding the listener, or `None` to disable
+    /// TCP Fast Open.
+    tcp_fastopen: Option<u32>,
+    /// `SO_KEEPALIVE` settings applied to accepted connections, or `None` to leave the OS
+    /// defaults in place.
+    tcp_keepalive: Option<TcpKeepalive>,
+    /// Whether to set `TCP_NODELAY` on accepted connections, disabling Nagle's algorithm.
+    tcp_nodelay: bool,
+}
+
+/// `SO_KEEPALIVE` tuning for accepted connections.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct TcpKeepalive {
+    /// Idle time in seconds before the kernel starts sending keep-alive probes.
+    pub idle: u32,
+    /// Interval in seconds between keep-alive probes.
+    pub interval: u32,
+    /// Number of unacknowledged probes before the connection is considered dead.
+    pub probes: u32,
 }
 
 impl Default for Limits {
@@ -364,15 +442,41 @@ impl Default for Limits {
             max_api_payload_size: ByteSize::from_megabytes(20),
             max_api_file_upload_size: ByteSize::from_megabytes(40),
             max_api_chunk_upload_size: ByteSize::from_megabytes(100),
+            max_upstream_response_size: ByteSize::from_megabytes(100),
             max_thread_count: num_cpus::get(),
             query_timeout: 30,
             max_connection_rate: 256,
             max_pending_connections: 2048,
             max_connections: 25_000,
+            tcp_fastopen: None,
+            tcp_keepalive: None,
+            tcp_nodelay: true,
         }
     }
 }
 
+/// Content encoding for request and response bodies exchanged with the upstream.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpEncoding {
+    /// No compression.
+    Identity,
+    /// `deflate` compression.
+    Deflate,
+    /// `gzip` compression.
+    Gzip,
+    /// `br` (Brotli) compression.
+    Br,
+    /// `zstd` (Zstandard) compression.
+    Zstd,
+}
+
+impl Default for HttpEncoding {
+    fn default() -> Self {
+        HttpEncoding::Identity
+    }
+}
+
 /// Controls authentication with upstream.
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(default)]
@@ -406,6 +510,20 @@ struct Cache {
     project_grace_period: u32,
     /// The cache timeout for downstream relay info (public keys) in seconds.
     relay_expiry: u32,
+    /// The soft cache timeout for downstream relay info (public keys) in seconds. Once an entry
+    /// is older than this but still within `relay_expiry`, it is served immediately while a
+    /// refresh is fetched in the background, instead of blocking the caller until `relay_expiry`
+    /// passes. Must be shorter than `relay_expiry` to have any effect.
+    relay_refresh_interval: u32,
+    /// The maximum number of downstream relay infos (public keys) to keep cached at once.
+    ///
+    /// Once the cache is at capacity, expired entries are evicted first; if none are expired, the
+    /// least-recently-accessed entry is evicted to make room.
+    relay_max_entries: usize,
+    /// The time in seconds after which a pending downstream relay info (public key) fetch is
+    /// canceled with an error. The age is checked each time a batch is dispatched, so a caller can
+    /// wait past this until the next dispatch, but not through further retries after it.
+    relay_fetch_timeout: u32,
     /// The cache timeout for events (store) before dropping them.
     event_expiry: u32,
     /// The maximum amount of events to queue before dropping them.
@@ -422,6 +540,14 @@ struct Cache {
     file_interval: u32,
     /// Interval for evicting outdated project configs from memory.
     eviction_interval: u32,
+    /// The cache timeout, in seconds, for project states cached in front of Redis project-state
+    /// lookups. Kept deliberately short -- its job is only to absorb repeat lookups for the same
+    /// project within a single batch or two, not to replace `project_expiry`.
+    redis_project_state_expiry: u32,
+    /// The maximum number of project states to keep cached in front of Redis at once. Once the
+    /// cache is at capacity, the least-recently-used entry is evicted to make room, the same as
+    /// `relay_max_entries` above.
+    redis_project_state_max_entries: usize,
 }
 
 impl Default for Cache {
@@ -429,14 +555,50 @@ impl Default for Cache {
         Cache {
             project_expiry: 300, // 5 minutes
             project_grace_period: 0,
-            relay_expiry: 3600, // 1 hour
-            event_expiry: 600,  // 10 minutes
+            relay_expiry: 3600,          // 1 hour
+            relay_refresh_interval: 600, // 10 minutes
+            relay_max_entries: 5000,
+            relay_fetch_timeout: 30, // 30 seconds
+            event_expiry: 600,       // 10 minutes
             event_buffer_size: 1000,
             miss_expiry: 60,     // 1 minute
             batch_interval: 100, // 100ms
             batch_size: 500,
             file_interval: 10,     // 10 seconds
             eviction_interval: 60, // 60 seconds
+            redis_project_state_expiry: 2,
+            redis_project_state_max_entries: 5000,
+        }
+    }
+}
+
+/// Controls the on-disk overflow spool for envelopes awaiting a project state.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+struct Spool {
+    /// Directory, relative to the config path, that spooled envelopes are written to while their
+    /// project state is being fetched. `None` disables spooling: envelopes stay in-memory only,
+    /// same as before this was added.
+    path: Option<PathBuf>,
+    /// The in-memory envelope count for a project above which newly arriving envelopes for it are
+    /// spooled to disk instead of buffered in memory.
+    buffer_watermark_high: usize,
+    /// Once a project has started spooling, the in-memory envelope count it must drop back below
+    /// before Relay stops spooling its envelopes and resumes buffering them in memory. Must be
+    /// lower than `buffer_watermark_high` to avoid flapping between the two right at the boundary.
+    buffer_watermark_low: usize,
+    /// The maximum combined size of all spooled envelopes on disk. Once reached, envelopes that
+    /// would otherwise be spooled are dropped instead.
+    max_disk_size: ByteSize,
+}
+
+impl Default for Spool {
+    fn default() -> Self {
+        Spool {
+            path: None,
+            buffer_watermark_high: 1_000,
+            buffer_watermark_low: 500,
+            max_disk_size: ByteSize::from_megabytes(500),
         }
     }
 }
@@ -475,30 +637,74 @@ pub enum KafkaTopic {
     Sessions,
 }
 
+/// A single configured Kafka topic, optionally routed to a non-default cluster.
+///
+/// Deserializes from a plain string (the topic name, produced to the default cluster in
+/// `kafka_config`) for backward compatibility, or from an object naming both the topic and a
+/// cluster, whose producer config is looked up by name in `kafka_configs`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TopicAssignment {
+    /// The topic is produced to the default cluster.
+    Primary(String),
+    /// The topic is produced to a named secondary cluster.
+    Secondary {
+        /// The topic name.
+        name: String,
+        /// The name of the cluster to produce to, keyed into `kafka_configs`.
+        cluster: String,
+    },
+}
+
+impl TopicAssignment {
+    /// Returns the Kafka topic name.
+    pub fn topic_name(&self) -> &str {
+        match self {
+            TopicAssignment::Primary(name) => name,
+            TopicAssignment::Secondary { name, .. } => name,
+        }
+    }
+
+    /// Returns the name of the secondary cluster this topic is routed to, or `None` if it uses
+    /// the default cluster.
+    pub fn cluster(&self) -> Option<&str> {
+        match self {
+            TopicAssignment::Primary(_) => None,
+            TopicAssignment::Secondary { cluster, .. } => Some(cluster),
+        }
+    }
+}
+
+impl From<String> for TopicAssignment {
+    fn from(name: String) -> Self {
+        TopicAssignment::Primary(name)
+    }
+}
+
 /// Configuration for topics.
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(default)]
 pub struct TopicNames {
     /// Simple events topic name.
-    pub events: String,
+    pub events: TopicAssignment,
     /// Events with attachments topic name.
-    pub attachments: String,
+    pub attachments: TopicAssignment,
     /// Transaction events topic name.
-    pub transactions: String,
+    pub transactions: TopicAssignment,
     /// Event outcomes topic name.
-    pub outcomes: String,
+    pub outcomes: TopicAssignment,
     /// Session health topic name.
-    pub sessions: String,
+    pub sessions: TopicAssignment,
 }
 
 impl Default for TopicNames {
     fn default() -> Self {
         Self {
-            events: "ingest-events".to_owned(),
-            attachments: "ingest-attachments".to_owned(),
-            transactions: "ingest-transactions".to_owned(),
-            outcomes: "outcomes".to_owned(),
-            sessions: "ingest-sessions".to_owned(),
+            events: "ingest-events".to_owned().into(),
+            attachments: "ingest-attachments".to_owned().into(),
+            transactions: "ingest-transactions".to_owned().into(),
+            outcomes: "outcomes".to_owned().into(),
+            sessions: "ingest-sessions".to_owned().into(),
         }
     }
 }
@@ -532,6 +738,130 @@ fn default_max_rate_limit() -> Option<u32> {
     Some(300) // 5 minutes
 }
 
+fn default_overflow_per_second_limit() -> NonZeroU32 {
+    NonZeroU32::new(50).unwrap()
+}
+
+fn default_overflow_burst_limit() -> NonZeroU32 {
+    NonZeroU32::new(200).unwrap()
+}
+
+/// Controls per-key overflow protection for the Kafka producer.
+///
+/// Each partition key is rate limited by its own token bucket (`overflow_per_second_limit`
+/// refill rate, `overflow_burst_limit` capacity). Once a key's bucket runs dry, messages for
+/// that key stop being pinned to their natural partition and spread across all partitions
+/// instead, so a single noisy project cannot saturate one Kafka partition.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Overflow {
+    /// Enables per-key overflow protection. Defaults to `false`.
+    pub overflow_enabled: bool,
+    /// Token bucket refill rate per partition key, in tokens per second.
+    #[serde(default = "default_overflow_per_second_limit")]
+    pub overflow_per_second_limit: NonZeroU32,
+    /// Token bucket capacity per partition key.
+    #[serde(default = "default_overflow_burst_limit")]
+    pub overflow_burst_limit: NonZeroU32,
+    /// Keys that are always treated as overflowing, regardless of their token bucket state.
+    pub overflow_forced_keys: Option<BTreeSet<String>>,
+}
+
+impl Default for Overflow {
+    fn default() -> Self {
+        Self {
+            overflow_enabled: false,
+            overflow_per_second_limit: default_overflow_per_second_limit(),
+            overflow_burst_limit: default_overflow_burst_limit(),
+            overflow_forced_keys: None,
+        }
+    }
+}
+
+fn default_redis_max_connections() -> u32 {
+    24
+}
+
+fn default_redis_connection_timeout() -> u64 {
+    5
+}
+
+fn default_redis_read_timeout() -> u64 {
+    3
+}
+
+fn default_redis_min_connections() -> u32 {
+    0
+}
+
+fn default_redis_idle_timeout() -> u64 {
+    60
+}
+
+/// Connection pool and timeout settings for the Redis client used for rate limiting and project
+/// state caching.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct RedisConnectionOptions {
+    /// Maximum number of connections to keep in the pool.
+    #[serde(default = "default_redis_max_connections")]
+    pub max_connections: u32,
+    /// Minimum number of idle connections to keep in the pool. `0` (the default) lets the pool
+    /// shrink to no idle connections at all when unused.
+    #[serde(default = "default_redis_min_connections")]
+    pub min_connections: u32,
+    /// Timeout in seconds for establishing a new connection.
+    #[serde(default = "default_redis_connection_timeout")]
+    pub connection_timeout: u64,
+    /// How long, in seconds, an idle connection may sit in the pool before it's closed.
+    #[serde(default = "default_redis_idle_timeout")]
+    pub idle_timeout: u64,
+    /// Timeout in seconds for reading a response.
+    #[serde(default = "default_redis_read_timeout")]
+    pub read_timeout: u64,
+}
+
+impl Default for RedisConnectionOptions {
+    fn default() -> Self {
+        Self {
+            max_connections: default_redis_max_connections(),
+            min_connections: default_redis_min_connections(),
+            connection_timeout: default_redis_connection_timeout(),
+            idle_timeout: default_redis_idle_timeout(),
+            read_timeout: default_redis_read_timeout(),
+        }
+    }
+}
+
+fn default_projectconfig_compression() -> ProjectCacheFormat {
+    ProjectCacheFormat::Identity
+}
+
+/// The compression format a cached project state value in Redis is expected to be written in.
+///
+/// Relay always sniffs the actual format of a value it reads back (the gzip magic `1f 8b`, or
+/// else a one-byte tag of its own for the formats that don't have a magic number), so this option
+/// is not required for Relay to read a value correctly either way. It exists so Relay and the
+/// process that writes project states into Redis -- which isn't part of this snapshot -- can agree
+/// on which format to produce without Relay having to guess from nothing but a cold cache.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectCacheFormat {
+    /// Plain, uncompressed JSON.
+    Identity,
+    /// `gzip`-compressed JSON, identified by the standard `1f 8b` magic header.
+    Gzip,
+    /// `zlib`-compressed JSON, identified by a one-byte tag Relay itself defines, since zlib
+    /// streams have no reliable self-describing magic number of their own.
+    Zlib,
+}
+
+impl Default for ProjectCacheFormat {
+    fn default() -> Self {
+        ProjectCacheFormat::Identity
+    }
+}
+
 /// Controls Sentry-internal event processing.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Processing {
@@ -546,23 +876,45 @@ pub struct Processing {
     /// Maximum age of ingested events. Older events will be adjusted to `now()`.
     #[serde(default = "default_max_secs_in_past")]
     pub max_secs_in_past: u32,
-    /// Kafka producer configurations.
+    /// Kafka producer configurations for the default cluster.
     pub kafka_config: Vec<KafkaConfigParam>,
+    /// Kafka producer configurations for secondary clusters, keyed by cluster name. Topics routed
+    /// to a secondary cluster via [`TopicAssignment::Secondary`] look up their producer config
+    /// here instead of in `kafka_config`.
+    #[serde(default)]
+    pub kafka_configs: BTreeMap<String, Vec<KafkaConfigParam>>,
     /// Kafka topic names.
     #[serde(default)]
     pub topics: TopicNames,
     /// Redis hosts to connect to for storing state for rate limits.
     #[serde(default)]
     pub redis: Option<RedisConfig>,
+    /// Connection pool and timeout settings applied to the Redis client configured in `redis`.
+    ///
+    /// `RedisConfig` itself only models a single server URL today -- modeling an explicit cluster
+    /// or a read/write split needs a new `relay_redis::RedisConfig` variant, which lives in the
+    /// `relay-redis` crate and is out of reach here. These options are the part of the request we
+    /// can land from this crate; they are meant to carry over to whichever client mode
+    /// `relay_redis` ends up constructing.
+    #[serde(default)]
+    pub redis_connection: RedisConnectionOptions,
     /// Maximum chunk size of attachments for Kafka.
     #[serde(default = "default_chunk_size")]
     pub attachment_chunk_size: ByteSize,
     /// Prefix to use when looking up project configs in Redis. Defaults to "relayconfig".
     #[serde(default = "default_projectconfig_cache_prefix")]
     pub projectconfig_cache_prefix: String,
+    /// Compression format project state values are expected to be written in when cached in
+    /// Redis. Defaults to `identity` (no compression). See [`ProjectCacheFormat`] for why this
+    /// doesn't gate what Relay can actually read back.
+    #[serde(default = "default_projectconfig_compression")]
+    pub projectconfig_compression: ProjectCacheFormat,
     /// Maximum rate limit to report to clients.
     #[serde(default = "default_max_rate_limit")]
     pub max_rate_limit: Option<u32>,
+    /// Per-key overflow protection for the Kafka producer.
+    #[serde(default)]
+    pub overflow: Overflow,
 }
 
 impl Default for Processing {
@@ -574,11 +926,15 @@ impl Default for Processing {
             max_secs_in_future: 0,
             max_secs_in_past: 0,
             kafka_config: Vec::new(),
+            kafka_configs: BTreeMap::new(),
             topics: TopicNames::default(),
             redis: None,
+            redis_connection: RedisConnectionOptions::default(),
             attachment_chunk_size: default_chunk_size(),
             projectconfig_cache_prefix: default_projectconfig_cache_prefix(),
+            projectconfig_compression: default_projectconfig_compression(),
             max_rate_limit: default_max_rate_limit(),
+            overflow: Overflow::default(),
         }
     }
 }
@@ -623,6 +979,8 @@ struct ConfigValues {
     #[serde(default)]
     cache: Cache,
     #[serde(default)]
+    spool: Spool,
+    #[serde(default)]
     limits: Limits,
     #[serde(default)]
     logging: Logging,
@@ -647,6 +1005,10 @@ impl ConfigObject for ConfigValues {
 pub struct Config {
     values: ConfigValues,
     credentials: Option<Credentials>,
+    /// The secret key, when sourced from `RELAY_SECRET_KEY` or `relay.secret_key_path` instead of
+    /// `credentials.json`. Takes precedence over `credentials.secret_key` and is never persisted
+    /// to disk.
+    secret_key_override: Option<SecretKey>,
     path: PathBuf,
 }
 
@@ -665,13 +1027,16 @@ impl Config {
         let path = env::current_dir()
             .map(|x| x.join(path.as_ref()))
             .unwrap_or_else(|_| path.as_ref().to_path_buf());
+        let values = ConfigValues::load(&path)?;
+        let secret_key_override = Self::load_secret_key_override(&values)?;
         let config = Config {
-            values: ConfigValues::load(&path)?,
             credentials: if fs::metadata(Credentials::path(&path)).is_ok() {
                 Some(Credentials::load(&path)?)
             } else {
                 None
             },
+            secret_key_override,
+            values,
             path: path.clone(),
         };
 
@@ -682,9 +1047,57 @@ impl Config {
             ));
         }
 
+        config.validate_kafka_topics()?;
+
         Ok(config)
     }
 
+    /// Resolves a secret key that overrides `credentials.json`, from the `RELAY_SECRET_KEY`
+    /// environment variable or, failing that, from `relay.secret_key_path`.
+    fn load_secret_key_override(values: &ConfigValues) -> Result<Option<SecretKey>, ConfigError> {
+        if let Ok(key) = env::var("RELAY_SECRET_KEY") {
+            let key = key
+                .parse()
+                .map_err(|err| ConfigError::for_field("secret_key", err))?;
+            return Ok(Some(key));
+        }
+
+        if let Some(path) = &values.relay.secret_key_path {
+            let contents = ctry!(fs::read_to_string(path), ConfigErrorKind::CouldNotOpenFile, path);
+            let key = contents
+                .trim()
+                .parse()
+                .map_err(|err| ConfigError::for_field("secret_key_path", err))?;
+            return Ok(Some(key));
+        }
+
+        Ok(None)
+    }
+
+    /// Checks that every topic naming a secondary Kafka cluster actually has a matching entry in
+    /// `kafka_configs`.
+    fn validate_kafka_topics(&self) -> Result<(), ConfigError> {
+        let processing = &self.values.processing;
+        for assignment in [
+            &processing.topics.events,
+            &processing.topics.attachments,
+            &processing.topics.transactions,
+            &processing.topics.outcomes,
+            &processing.topics.sessions,
+        ] {
+            if let Some(cluster) = assignment.cluster() {
+                if !processing.kafka_configs.contains_key(cluster) {
+                    return Err(ConfigError::for_field(
+                        "topics",
+                        ConfigErrorKind::InvalidValue,
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Override configuration with values coming from other sources (e.g. env variables or
     /// command line parameters)
     pub fn apply_override(
@@ -710,6 +1123,23 @@ impl Config {
                 .map_err(|err| ConfigError::for_field("port", err))?;
         }
 
+        if let Some(deployment) = overrides.deployment {
+            relay.deployment = match deployment.to_lowercase().as_str() {
+                "default" => RelayDeployment::Default,
+                "canary" => RelayDeployment::Canary,
+                _ => {
+                    return Err(ConfigError::for_field(
+                        "deployment",
+                        ConfigErrorKind::InvalidValue,
+                    ))
+                }
+            };
+        }
+
+        if let Some(environment) = overrides.environment {
+            relay.environment = Some(environment);
+        }
+
         let processing = &mut self.values.processing;
         if let Some(enabled) = overrides.processing {
             match enabled.to_lowercase().as_str() {
@@ -859,13 +1289,19 @@ impl Config {
                 self.credentials = Some(creds);
             }
             None => {
-                let path = Credentials::path(&self.path);
-                if fs::metadata(&path).is_ok() {
-                    ctry!(
-                        fs::remove_file(&path),
-                        ConfigErrorKind::CouldNotWriteFile,
-                        &path
-                    );
+                // The secret key may live outside of `credentials.json` entirely (env var or a
+                // mounted secret file); there is no file-backed key to delete in that case, and
+                // removing `credentials.json` would just throw away the id/public key for no
+                // reason.
+                if self.secret_key_override.is_none() {
+                    let path = Credentials::path(&self.path);
+                    if fs::metadata(&path).is_ok() {
+                        ctry!(
+                            fs::remove_file(&path),
+                            ConfigErrorKind::CouldNotWriteFile,
+                            &path
+                        );
+                    }
                 }
             }
         }
@@ -874,12 +1310,17 @@ impl Config {
 
     /// Returns `true` if the config is ready to use.
     pub fn has_credentials(&self) -> bool {
-        self.credentials.is_some()
+        self.credentials.is_some() || self.secret_key_override.is_some()
     }
 
     /// Returns the secret key if set.
+    ///
+    /// A secret key from the `RELAY_SECRET_KEY` environment variable or `relay.secret_key_path`
+    /// takes precedence over the one in `credentials.json`.
     pub fn secret_key(&self) -> Option<&SecretKey> {
-        self.credentials.as_ref().map(|x| &x.secret_key)
+        self.secret_key_override
+            .as_ref()
+            .or_else(|| self.credentials.as_ref().map(|x| &x.secret_key))
     }
 
     /// Returns the public key if set.
@@ -897,6 +1338,16 @@ impl Config {
         self.values.relay.mode
     }
 
+    /// Returns the deployment this relay belongs to.
+    pub fn relay_deployment(&self) -> RelayDeployment {
+        self.values.relay.deployment
+    }
+
+    /// Returns the environment identifier of this relay, if set.
+    pub fn relay_environment(&self) -> Option<&str> {
+        self.values.relay.environment.as_deref()
+    }
+
     /// Returns the upstream target as descriptor.
     pub fn upstream_descriptor(&self) -> &UpstreamDescriptor<'_> {
         &self.values.relay.upstream
@@ -912,6 +1363,14 @@ impl Config {
         (self.values.relay.host, self.values.relay.port).into()
     }
 
+    /// Returns the index into the list of file descriptors passed by the init system
+    /// (LISTEN_FDS-style socket activation) that the server should adopt as its listening
+    /// socket, if one was configured. When `None`, the server falls back to binding
+    /// [`Config::listen_addr`] directly.
+    pub fn bind_from_fd(&self) -> Option<u32> {
+        self.values.relay.bind_from_fd
+    }
+
     /// Returns the TLS listen address.
     pub fn tls_listen_addr(&self) -> Option<SocketAddr> {
         if self.values.relay.tls_identity_path.is_some() {
@@ -968,6 +1427,22 @@ impl Config {
         }
     }
 
+    /// Returns the bind address for the Prometheus `/metrics` endpoint, or `None` if it's
+    /// disabled (the default).
+    pub fn prometheus_addr(&self) -> Result<Option<SocketAddr>, ConfigError> {
+        match self.values.metrics.prometheus_addr {
+            Some(ref addr) => {
+                let mut addrs = ctry!(
+                    addr.as_str().to_socket_addrs(),
+                    ConfigErrorKind::InvalidValue,
+                    &self.path
+                );
+                Ok(addrs.next())
+            }
+            None => Ok(None),
+        }
+    }
+
     /// Return the prefix for statsd metrics.
     pub fn metrics_prefix(&self) -> &str {
         &self.values.metrics.prefix
@@ -993,6 +1468,25 @@ impl Config {
         Duration::from_secs(self.values.cache.relay_expiry.into())
     }
 
+    /// Returns the soft refresh timeout for cached relay infos (public keys).
+    ///
+    /// Once a cached entry is older than this, but still within `relay_cache_expiry`, it is
+    /// served immediately while a fresh value is fetched in the background.
+    pub fn relay_cache_refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.values.cache.relay_refresh_interval.into())
+    }
+
+    /// Returns the maximum number of cached relay infos (public keys) to retain.
+    pub fn relay_cache_max_entries(&self) -> usize {
+        self.values.cache.relay_max_entries
+    }
+
+    /// Returns the age after which a pending downstream relay info (public key) fetch is canceled
+    /// with an error at the next batch dispatch.
+    pub fn relay_key_fetch_timeout(&self) -> Duration {
+        Duration::from_secs(self.values.cache.relay_fetch_timeout.into())
+    }
+
     /// Returns the timeout for buffered events (due to upstream errors).
     pub fn event_buffer_expiry(&self) -> Duration {
         Duration::from_secs(self.values.cache.event_expiry.into())
@@ -1008,6 +1502,17 @@ impl Config {
         Duration::from_secs(self.values.cache.miss_expiry.into())
     }
 
+    /// Returns the expiry timeout for project states cached in front of Redis project-state
+    /// lookups.
+    pub fn redis_project_state_cache_expiry(&self) -> Duration {
+        Duration::from_secs(self.values.cache.redis_project_state_expiry.into())
+    }
+
+    /// Returns the maximum number of project states to keep cached in front of Redis at once.
+    pub fn redis_project_state_cache_max_entries(&self) -> usize {
+        self.values.cache.redis_project_state_max_entries
+    }
+
     /// Returns the grace period for project caches.
     pub fn project_grace_period(&self) -> Duration {
         Duration::from_secs(self.values.cache.project_grace_period.into())
@@ -1073,6 +1578,11 @@ impl Config {
         self.values.limits.max_api_chunk_upload_size.as_bytes() as usize
     }
 
+    /// Returns the maximum size of a response fetched from the upstream.
+    pub fn max_upstream_response_size(&self) -> usize {
+        self.values.limits.max_upstream_response_size.as_bytes() as usize
+    }
+
     /// Returns the maximum number of active requests
     pub fn max_concurrent_requests(&self) -> usize {
         self.values.limits.max_concurrent_requests
@@ -1103,6 +1613,22 @@ impl Config {
         self.values.limits.max_pending_connections
     }
 
+    /// The TCP Fast Open queue length to apply to the listening socket, or `None` to disable it.
+    pub fn tcp_fastopen(&self) -> Option<u32> {
+        self.values.limits.tcp_fastopen
+    }
+
+    /// `SO_KEEPALIVE` settings to apply to accepted connections, or `None` to leave the OS
+    /// defaults in place.
+    pub fn tcp_keepalive(&self) -> Option<TcpKeepalive> {
+        self.values.limits.tcp_keepalive
+    }
+
+    /// Whether `TCP_NODELAY` should be set on accepted connections, disabling Nagle's algorithm.
+    pub fn tcp_nodelay(&self) -> bool {
+        self.values.limits.tcp_nodelay
+    }
+
     /// Returns the number of cores to use for thread pools.
     pub fn cpu_concurrency(&self) -> usize {
         self.values.limits.max_thread_count
@@ -1127,6 +1653,30 @@ impl Config {
         self.path.join("projects")
     }
 
+    /// The directory spooled envelopes awaiting a project state are written to, or `None` if the
+    /// on-disk spool is disabled (the default).
+    pub fn spool_path(&self) -> Option<PathBuf> {
+        self.values.spool.path.as_ref().map(|path| self.path.join(path))
+    }
+
+    /// The per-project in-memory envelope count above which newly arriving envelopes begin
+    /// spooling to disk instead of buffering in memory.
+    pub fn spool_buffer_watermark_high(&self) -> usize {
+        self.values.spool.buffer_watermark_high
+    }
+
+    /// The per-project in-memory envelope count a project must drop back below before Relay stops
+    /// spooling its envelopes and resumes in-memory buffering.
+    pub fn spool_buffer_watermark_low(&self) -> usize {
+        self.values.spool.buffer_watermark_low
+    }
+
+    /// The maximum combined size in bytes of all spooled envelopes on disk, past which envelopes
+    /// that would otherwise be spooled are dropped instead.
+    pub fn spool_max_disk_size(&self) -> usize {
+        self.values.spool.max_disk_size.as_bytes() as usize
+    }
+
     /// True if the Relay should do processing.
     pub fn processing_enabled(&self) -> bool {
         self.values.processing.enabled
@@ -1154,13 +1704,36 @@ impl Config {
 
     /// Returns the name of the specified Kafka topic.
     pub fn kafka_topic_name(&self, topic: KafkaTopic) -> &str {
+        self.topic_assignment(topic).topic_name()
+    }
+
+    /// Returns the Kafka producer config for the given topic's cluster, falling back to the
+    /// default `kafka_config` when the topic does not name a cluster of its own.
+    ///
+    /// The referenced cluster is guaranteed to exist: [`Config::from_path`] validates that every
+    /// cluster named by a topic is present in `kafka_configs` at load time.
+    pub fn kafka_config_for(&self, topic: KafkaTopic) -> &[KafkaConfigParam] {
+        let assignment = self.topic_assignment(topic);
+        let processing = &self.values.processing;
+
+        match assignment.cluster() {
+            Some(cluster) => processing
+                .kafka_configs
+                .get(cluster)
+                .map(Vec::as_slice)
+                .unwrap_or(&[]),
+            None => processing.kafka_config.as_slice(),
+        }
+    }
+
+    fn topic_assignment(&self, topic: KafkaTopic) -> &TopicAssignment {
         let topics = &self.values.processing.topics;
         match topic {
-            KafkaTopic::Attachments => topics.attachments.as_str(),
-            KafkaTopic::Events => topics.events.as_str(),
-            KafkaTopic::Transactions => topics.transactions.as_str(),
-            KafkaTopic::Outcomes => topics.outcomes.as_str(),
-            KafkaTopic::Sessions => topics.sessions.as_str(),
+            KafkaTopic::Attachments => &topics.attachments,
+            KafkaTopic::Events => &topics.events,
+            KafkaTopic::Transactions => &topics.transactions,
+            KafkaTopic::Outcomes => &topics.outcomes,
+            KafkaTopic::Sessions => &topics.sessions,
         }
     }
 
@@ -1169,6 +1742,32 @@ impl Config {
         self.values.processing.redis.as_ref()
     }
 
+    /// Maximum number of pooled connections to the Redis client used for rate limiting.
+    pub fn redis_max_connections(&self) -> u32 {
+        self.values.processing.redis_connection.max_connections
+    }
+
+    /// Minimum number of idle pooled connections to keep open to the Redis client used for rate
+    /// limiting and project state caching.
+    pub fn redis_min_connections(&self) -> u32 {
+        self.values.processing.redis_connection.min_connections
+    }
+
+    /// How long an idle connection in the Redis client's pool may sit before it's closed.
+    pub fn redis_idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.values.processing.redis_connection.idle_timeout)
+    }
+
+    /// Timeout for establishing a new connection to the Redis client used for rate limiting.
+    pub fn redis_connection_timeout(&self) -> Duration {
+        Duration::from_secs(self.values.processing.redis_connection.connection_timeout)
+    }
+
+    /// Timeout for reading a response from the Redis client used for rate limiting.
+    pub fn redis_read_timeout(&self) -> Duration {
+        Duration::from_secs(self.values.processing.redis_connection.read_timeout)
+    }
+
     /// Chunk size of attachments in bytes.
     pub fn attachment_chunk_size(&self) -> usize {
         self.values.processing.attachment_chunk_size.as_bytes() as usize
@@ -1180,10 +1779,35 @@ impl Config {
         &self.values.processing.projectconfig_cache_prefix
     }
 
+    /// Compression format project state values cached in Redis are expected to be written in.
+    pub fn projectconfig_compression(&self) -> ProjectCacheFormat {
+        self.values.processing.projectconfig_compression
+    }
+
     /// Maximum rate limit to report to clients in seconds.
     pub fn max_rate_limit(&self) -> Option<u64> {
         self.values.processing.max_rate_limit.map(u32::into)
     }
+
+    /// True if per-key overflow protection for the Kafka producer is enabled.
+    pub fn overflow_enabled(&self) -> bool {
+        self.values.processing.overflow.overflow_enabled
+    }
+
+    /// Token bucket refill rate per partition key, in tokens per second.
+    pub fn overflow_per_second_limit(&self) -> NonZeroU32 {
+        self.values.processing.overflow.overflow_per_second_limit
+    }
+
+    /// Token bucket capacity per partition key.
+    pub fn overflow_burst_limit(&self) -> NonZeroU32 {
+        self.values.processing.overflow.overflow_burst_limit
+    }
+
+    /// Keys that are always treated as overflowing, regardless of their token bucket state.
+    pub fn overflow_forced_keys(&self) -> Option<&BTreeSet<String>> {
+        self.values.processing.overflow.overflow_forced_keys.as_ref()
+    }
 }
 
 impl Default for Config {
@@ -1191,6 +1815,7 @@ impl Default for Config {
         Self {
             values: ConfigValues::default(),
             credentials: None,
+            secret_key_override: None,
             path: PathBuf::new(),
         }
     }
@@ -1199,6 +1824,7 @@ impl Default for Config {
 enum ConfigFormat {
     Yaml,
     Json,
+    Toml,
 }
 
 impl ConfigFormat {
@@ -1206,6 +1832,16 @@ impl ConfigFormat {
         match self {
             ConfigFormat::Yaml => "yml",
             ConfigFormat::Json => "json",
+            ConfigFormat::Toml => "toml",
+        }
+    }
+
+    /// Detects the format from a path's extension, defaulting to YAML for anything else.
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => ConfigFormat::Json,
+            Some("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Yaml,
         }
     }
 }
@@ -1213,28 +1849,58 @@ impl ConfigFormat {
 trait ConfigObject: DeserializeOwned + Serialize {
     fn format() -> ConfigFormat;
     fn name() -> &'static str;
+
+    /// Returns the path of the config file, auto-detecting `config.{yml,json,toml}` in that
+    /// order of preference. Falls back to `Self::format()`'s extension if none of them exist,
+    /// e.g. when a config is being created for the first time.
     fn path(base: &Path) -> PathBuf {
+        for format in &[ConfigFormat::Yaml, ConfigFormat::Json, ConfigFormat::Toml] {
+            let candidate = base.join(format!("{}.{}", Self::name(), format.extension()));
+            if candidate.exists() {
+                return candidate;
+            }
+        }
+
         base.join(format!("{}.{}", Self::name(), Self::format().extension()))
     }
 
     fn load(base: &Path) -> Result<Self, ConfigError> {
         let path = Self::path(base);
-        let f = ctry!(
-            fs::File::open(&path),
-            ConfigErrorKind::CouldNotOpenFile,
-            &path
-        );
-        Ok(match Self::format() {
-            ConfigFormat::Yaml => ctry!(
-                serde_yaml::from_reader(io::BufReader::new(f)),
-                ConfigErrorKind::BadYaml,
-                &path
-            ),
-            ConfigFormat::Json => ctry!(
-                serde_json::from_reader(io::BufReader::new(f)),
-                ConfigErrorKind::BadYaml,
-                &path
-            ),
+        Ok(match ConfigFormat::from_path(&path) {
+            ConfigFormat::Yaml => {
+                let f = ctry!(
+                    fs::File::open(&path),
+                    ConfigErrorKind::CouldNotOpenFile,
+                    &path
+                );
+                ctry!(
+                    serde_yaml::from_reader(io::BufReader::new(f)),
+                    ConfigErrorKind::BadYaml,
+                    &path
+                )
+            }
+            ConfigFormat::Json => {
+                let f = ctry!(
+                    fs::File::open(&path),
+                    ConfigErrorKind::CouldNotOpenFile,
+                    &path
+                );
+                ctry!(
+                    serde_json::from_reader(io::BufReader::new(f)),
+                    ConfigErrorKind::BadYaml,
+                    &path
+                )
+            }
+            ConfigFormat::Toml => {
+                // The toml crate only exposes a string-based API, so we have to read the whole
+                // file up front rather than streaming it like the other two formats.
+                let contents = ctry!(
+                    fs::read_to_string(&path),
+                    ConfigErrorKind::CouldNotOpenFile,
+                    &path
+                );
+                ctry!(toml::from_str(&contents), ConfigErrorKind::BadYaml, &path)
+            }
         })
     }
 
@@ -1256,7 +1922,7 @@ trait ConfigObject: DeserializeOwned + Serialize {
             &path
         );
 
-        match Self::format() {
+        match ConfigFormat::from_path(&path) {
             ConfigFormat::Yaml => {
                 ctry!(
                     serde_yaml::to_writer(&mut f, self),
@@ -1271,6 +1937,18 @@ trait ConfigObject: DeserializeOwned + Serialize {
                     &path
                 );
             }
+            ConfigFormat::Toml => {
+                let contents = ctry!(
+                    toml::to_string_pretty(self),
+                    ConfigErrorKind::BadYaml,
+                    &path
+                );
+                ctry!(
+                    f.write_all(contents.as_bytes()),
+                    ConfigErrorKind::CouldNotWriteFile,
+                    &path
+                );
+            }
         }
         f.write_all(b"\n").ok();
         Ok(())
@@ -1,21 +1,23 @@
 use std::borrow::Cow;
 use std::iter::FusedIterator;
+use std::ops::Range;
 use std::str::Utf8Error;
 
-use encoding::all::UTF_16LE;
+use encoding::all::{UTF_16BE, UTF_16LE};
 use encoding::{Encoding, RawDecoder};
 use regex::bytes::RegexBuilder as BytesRegexBuilder;
-use regex::{Match, Regex};
-use relay_wstring::{Utf16Error, WStr};
+use regex::Regex;
 use smallvec::SmallVec;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::pii::compiledconfig::RuleRef;
+use crate::pii::der;
 use crate::pii::regexes::{get_regex_for_rule_type, ReplaceBehavior};
 use crate::pii::utils::hash_value;
 use crate::pii::{CompiledPiiConfig, Redaction};
 use crate::processor::{FieldAttrs, Pii, ProcessingState, ValueType};
 
-fn apply_regex_to_utf8_bytes(
+pub(crate) fn apply_regex_to_utf8_bytes(
     data: &mut [u8],
     rule: &RuleRef,
     regex: &Regex,
@@ -71,58 +73,651 @@ fn apply_regex_to_utf8_bytes(
     true
 }
 
+/// Which byte order a UTF-16 segment's raw bytes are encoded in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Utf16ByteOrder {
+    Le,
+    Be,
+}
+
+impl Utf16ByteOrder {
+    fn write_unit(self, code: u16) -> [u8; 2] {
+        match self {
+            Utf16ByteOrder::Le => code.to_le_bytes(),
+            Utf16ByteOrder::Be => code.to_be_bytes(),
+        }
+    }
+}
+
+/// Maps `text_range` -- a byte range into `decoded`, e.g. from a regex match or a `str::find` hit
+/// -- to the byte range it occupies in the raw UTF-16 bytes `decoded` was decoded from, counting 2
+/// bytes per UTF-16 code unit (4 for a character outside the Basic Multilingual Plane, which needs
+/// a surrogate pair) rather than assuming every character takes the same number of bytes as it
+/// does in UTF-8.
+fn utf16_byte_range_for_text_range(decoded: &str, text_range: Range<usize>) -> Range<usize> {
+    const UNIT_SIZE: usize = std::mem::size_of::<u16>();
+
+    let mut raw_offset = 0;
+    let mut start = raw_offset;
+    let mut end = raw_offset;
+
+    for (text_offset, ch) in decoded.char_indices() {
+        if text_offset == text_range.start {
+            start = raw_offset;
+        }
+        if text_offset == text_range.end {
+            end = raw_offset;
+        }
+        raw_offset += ch.len_utf16() * UNIT_SIZE;
+    }
+
+    // The match runs to (or starts at) the very end of the segment: there's no later char whose
+    // start offset we'd otherwise catch it on.
+    if text_range.end == decoded.len() {
+        end = raw_offset;
+    }
+    if text_range.start == decoded.len() {
+        start = raw_offset;
+    }
+
+    start..end
+}
+
+/// Returns the code-unit offset of the first occurrence of `needle` in `decoded`, the way
+/// `str::find` returns a byte offset -- except counting UTF-16 code units, to match how a caller
+/// holding the corresponding raw UTF-16 buffer would index into it.
+pub(crate) fn find_utf16(decoded: &str, needle: &str) -> Option<usize> {
+    let byte_offset = decoded.find(needle)?;
+    Some(decoded[..byte_offset].encode_utf16().count())
+}
+
+/// Like [`find_utf16`], but for the last occurrence, the way `str::rfind` does for bytes.
+pub(crate) fn rfind_utf16(decoded: &str, needle: &str) -> Option<usize> {
+    let byte_offset = decoded.rfind(needle)?;
+    Some(decoded[..byte_offset].encode_utf16().count())
+}
+
+/// Replaces every non-overlapping occurrence of the literal `needle` in the UTF-16 text decoded
+/// from `data` (in `byte_order`) with `replacement`, via the same [`swap_utf16_content`] machinery
+/// [`apply_redaction_to_utf16`] uses, so a single matched token -- an email, a file path, a machine
+/// name -- is redacted without blanking the whole segment around it, and `data`'s byte length and
+/// alignment are unchanged. Returns whether anything was replaced.
+pub(crate) fn replace_utf16_literal_matches(
+    data: &mut [u8],
+    needle: &str,
+    replacement: &str,
+    byte_order: Utf16ByteOrder,
+) -> bool {
+    const PADDING: char = 'x';
+    let mut changed = false;
+
+    let segments = match byte_order {
+        Utf16ByteOrder::Le => MutSegmentIter::new(data, *UTF_16LE),
+        Utf16ByteOrder::Be => MutSegmentIter::new(data, *UTF_16BE),
+    };
+
+    for segment in segments {
+        for (byte_offset, matched) in segment.decoded.match_indices(needle) {
+            let text_range = byte_offset..byte_offset + matched.len();
+            let range = utf16_byte_range_for_text_range(&segment.decoded, text_range);
+            swap_utf16_content(&mut segment.raw[range], replacement, PADDING, byte_order);
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+/// Replaces every whitespace-delimited token of the UTF-16 text decoded from `data` (in
+/// `byte_order`) for which `is_match` returns `true` with `replacement`, the same way
+/// [`replace_utf16_literal_matches`] does for a literal needle -- useful when what should be
+/// redacted is identified by a predicate over the token text (e.g. "looks like a machine name")
+/// rather than by matching one fixed string. Returns whether anything was replaced.
+pub(crate) fn replace_utf16_matches(
+    data: &mut [u8],
+    mut is_match: impl FnMut(&str) -> bool,
+    replacement: &str,
+    byte_order: Utf16ByteOrder,
+) -> bool {
+    const PADDING: char = 'x';
+    let mut changed = false;
+
+    let segments = match byte_order {
+        Utf16ByteOrder::Le => MutSegmentIter::new(data, *UTF_16LE),
+        Utf16ByteOrder::Be => MutSegmentIter::new(data, *UTF_16BE),
+    };
+
+    for segment in segments {
+        // `split_whitespace` only hands back `&str` slices, not their offsets, but since each one
+        // is a subslice of `segment.decoded`'s own buffer, its start is a valid offset into it.
+        let hits: Vec<Range<usize>> = segment
+            .decoded
+            .split_whitespace()
+            .map(|token| {
+                let start = token.as_ptr() as usize - segment.decoded.as_ptr() as usize;
+                start..start + token.len()
+            })
+            .filter(|text_range| is_match(&segment.decoded[text_range.clone()]))
+            .collect();
+
+        for text_range in hits {
+            let range = utf16_byte_range_for_text_range(&segment.decoded, text_range);
+            swap_utf16_content(&mut segment.raw[range], replacement, PADDING, byte_order);
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+/// Fills `raw` -- the UTF-16 code units of a single matched value, in `byte_order` -- with
+/// `fill_char` repeated to the end.
+fn fill_utf16_content(raw: &mut [u8], fill_char: char, byte_order: Utf16ByteOrder) {
+    let size = std::mem::size_of::<u16>();
+
+    let mut buf = [0u16; 1];
+    let fill_u16 = fill_char.encode_utf16(&mut buf[..]); // this panics for us
+    let fill_bytes = byte_order.write_unit(fill_u16[0]);
+
+    for chunk in raw.chunks_exact_mut(size) {
+        chunk.copy_from_slice(&fill_bytes);
+    }
+}
+
+/// Replaces `raw` -- the UTF-16 code units of a single matched value, in `byte_order` -- with
+/// `replacement`, padding any leftover space with `padding`, the same way
+/// `StringMods::swap_content` does for plain UTF-8 bytes.
+///
+/// `replacement` is walked one grapheme cluster at a time (so a surrogate pair, combining mark, or
+/// ZWJ emoji sequence is always written as a whole unit or not at all) and each cluster is
+/// re-encoded character by character: if there isn't room for all the code units a cluster needs,
+/// the cutoff happens before that cluster rather than splitting it.
+fn swap_utf16_content(
+    raw: &mut [u8],
+    replacement: &str,
+    padding: char,
+    byte_order: Utf16ByteOrder,
+) {
+    let size = std::mem::size_of::<u16>();
+    let len = raw.len();
+
+    let mut buf = [0u16; 1];
+    let fill_u16 = padding.encode_utf16(&mut buf[..]); // this panics for us
+    let fill_bytes = byte_order.write_unit(fill_u16[0]);
+
+    let mut offset = 0;
+    for grapheme in replacement.graphemes(true) {
+        let grapheme_len: usize = grapheme.chars().map(char::len_utf16).sum::<usize>() * size;
+        if len - offset < grapheme_len {
+            break; // Not enough room for this whole grapheme cluster.
+        }
+
+        for ch in grapheme.chars() {
+            let mut units_buf = [0u16; 2];
+            for &unit in ch.encode_utf16(&mut units_buf).iter() {
+                raw[offset..offset + size].copy_from_slice(&byte_order.write_unit(unit));
+                offset += size;
+            }
+        }
+    }
+
+    for chunk in raw[offset..].chunks_exact_mut(size) {
+        chunk.copy_from_slice(&fill_bytes);
+    }
+}
+
+/// Returns the byte length of meaningful content in `raw` -- the UTF-16 code units of a single
+/// matched value, in `byte_order` -- treating a trailing run of `padding` as unused space rather
+/// than real content, the same way [`fill_utf16_content`] and [`swap_utf16_content`] use `padding`
+/// to mean "nothing here" once they've written what they have. [`truncate_utf16`], [`pop_utf16`],
+/// and [`remove_utf16`] all need this, since `raw` is a fixed-size buffer with no length field of
+/// its own to consult.
+fn utf16_content_len(raw: &[u8], padding: char, byte_order: Utf16ByteOrder) -> usize {
+    let size = std::mem::size_of::<u16>();
+
+    let mut buf = [0u16; 1];
+    let fill_u16 = padding.encode_utf16(&mut buf[..]); // this panics for us
+    let fill_bytes = byte_order.write_unit(fill_u16[0]);
+
+    let mut end = raw.len();
+    while end >= size && raw[end - size..end] == fill_bytes {
+        end -= size;
+    }
+    end
+}
+
+/// Reports whether `code_unit_idx` -- a code-unit offset into `raw`, a single matched value's
+/// UTF-16 code units in `byte_order` -- falls on a scalar-value boundary rather than splitting a
+/// surrogate pair in two, the way `str::is_char_boundary` reports the same thing in UTF-8 byte
+/// space. The start and end of `raw` always count as boundaries, same as `str::is_char_boundary`
+/// treats `0` and `s.len()`.
+fn is_utf16_char_boundary(raw: &[u8], code_unit_idx: usize, byte_order: Utf16ByteOrder) -> bool {
+    let size = std::mem::size_of::<u16>();
+    let byte_idx = code_unit_idx * size;
+
+    if byte_idx == 0 || byte_idx >= raw.len() {
+        return true;
+    }
+    !matches!(read_utf16_unit(raw, byte_idx, byte_order), Some(unit) if is_low_surrogate(unit))
+}
+
+/// Shortens `raw` -- a single matched value's UTF-16 code units, in `byte_order` -- to its first
+/// `code_units` code units, back-filling the rest with `padding` so `raw`'s byte length doesn't
+/// change, the way `String::truncate` shortens a `String`'s length without touching its capacity.
+///
+/// Panics if `code_units` would split a surrogate pair, the same way `String::truncate` panics on
+/// a byte index that isn't a char boundary -- check [`is_utf16_char_boundary`] first if
+/// `code_units` isn't already known to land cleanly.
+fn truncate_utf16(raw: &mut [u8], code_units: usize, padding: char, byte_order: Utf16ByteOrder) {
+    assert!(
+        is_utf16_char_boundary(raw, code_units, byte_order),
+        "code_units must not split a surrogate pair"
+    );
+
+    let size = std::mem::size_of::<u16>();
+    let byte_idx = (code_units * size).min(raw.len());
+    fill_utf16_content(&mut raw[byte_idx..], padding, byte_order);
+}
+
+/// Removes and returns the last scalar value of the content in `raw` -- a single matched value's
+/// UTF-16 code units, in `byte_order` -- back-filling the code units it occupied with `padding` so
+/// `raw`'s byte length doesn't change, the way `String::pop` removes a `String`'s last `char`
+/// without touching its capacity. Returns `None` if `raw` holds no content but `padding`.
+fn pop_utf16(raw: &mut [u8], padding: char, byte_order: Utf16ByteOrder) -> Option<char> {
+    let size = std::mem::size_of::<u16>();
+    let content_end = utf16_content_len(raw, padding, byte_order);
+    if content_end == 0 {
+        return None;
+    }
+
+    let last_unit = read_utf16_unit(raw, content_end - size, byte_order)?;
+    let scalar_start = if is_low_surrogate(last_unit) && content_end >= 2 * size {
+        match read_utf16_unit(raw, content_end - 2 * size, byte_order) {
+            Some(high) if is_high_surrogate(high) => content_end - 2 * size,
+            _ => content_end - size,
+        }
+    } else {
+        content_end - size
+    };
+
+    let (decoded, _) = decode_utf16_lossy(&raw[scalar_start..content_end], byte_order);
+    let popped = decoded.chars().next()?;
+    fill_utf16_content(&mut raw[scalar_start..content_end], padding, byte_order);
+    Some(popped)
+}
+
+/// Removes and returns the scalar value starting at code-unit offset `idx` in the content of `raw`
+/// -- a single matched value's UTF-16 code units, in `byte_order` -- shifting everything after it
+/// down and back-filling the vacated trailing code units with `padding`, so `raw`'s byte length
+/// doesn't change, the way `String::remove` removes one `char` from a `String` without touching its
+/// capacity.
+///
+/// Panics if `idx` is at or past the end of `raw`'s content.
+fn remove_utf16(raw: &mut [u8], idx: usize, padding: char, byte_order: Utf16ByteOrder) -> char {
+    let size = std::mem::size_of::<u16>();
+    let content_end = utf16_content_len(raw, padding, byte_order);
+    let start = idx * size;
+    assert!(start < content_end, "remove index out of bounds");
+
+    let unit = read_utf16_unit(raw, start, byte_order)
+        .expect("start is within raw by the assert above");
+    let scalar_len = if is_high_surrogate(unit) { 2 * size } else { size };
+    let end = start + scalar_len;
+
+    let (decoded, _) = decode_utf16_lossy(&raw[start..end], byte_order);
+    let removed = decoded
+        .chars()
+        .next()
+        .expect("a lone scalar's worth of code units decodes to exactly one char");
+
+    raw.copy_within(end..content_end, start);
+    fill_utf16_content(&mut raw[content_end - scalar_len..content_end], padding, byte_order);
+
+    removed
+}
+
+fn apply_redaction_to_utf16(raw: &mut [u8], redaction: &Redaction, byte_order: Utf16ByteOrder) {
+    const PADDING: char = 'x';
+    const MASK: char = '*';
+
+    match redaction {
+        Redaction::Default | Redaction::Remove => fill_utf16_content(raw, PADDING, byte_order),
+        Redaction::Mask => fill_utf16_content(raw, MASK, byte_order),
+        Redaction::Hash => {
+            let hashed = hash_value(raw);
+            swap_utf16_content(raw, &hashed, PADDING, byte_order);
+        }
+        Redaction::Replace(ref replace) => {
+            swap_utf16_content(raw, replace.text.as_str(), PADDING, byte_order);
+        }
+    }
+}
+
+fn apply_regex_to_utf16_bytes(
+    data: &mut [u8],
+    rule: &RuleRef,
+    regex: &Regex,
+    replace_behavior: &ReplaceBehavior,
+    byte_order: Utf16ByteOrder,
+) -> bool {
+    let mut changed = false;
+
+    let segments = match byte_order {
+        Utf16ByteOrder::Le => MutSegmentIter::new(data, *UTF_16LE),
+        Utf16ByteOrder::Be => MutSegmentIter::new(data, *UTF_16BE),
+    };
+
+    for segment in segments {
+        match replace_behavior {
+            ReplaceBehavior::Value => {
+                for re_match in regex.find_iter(&segment.decoded) {
+                    let range =
+                        utf16_byte_range_for_text_range(&segment.decoded, re_match.range());
+                    apply_redaction_to_utf16(&mut segment.raw[range], &rule.redaction, byte_order);
+                    changed = true;
+                }
+            }
+            ReplaceBehavior::Groups(ref replace_groups) => {
+                for captures in regex.captures_iter(&segment.decoded) {
+                    for group_idx in replace_groups.iter() {
+                        if let Some(re_match) = captures.get(*group_idx as usize) {
+                            let range = utf16_byte_range_for_text_range(
+                                &segment.decoded,
+                                re_match.range(),
+                            );
+                            apply_redaction_to_utf16(
+                                &mut segment.raw[range],
+                                &rule.redaction,
+                                byte_order,
+                            );
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    changed
+}
+
+/// Applies `regex` to a plain UTF-16LE-encoded buffer, e.g. a Windows-produced log file.
 fn apply_regex_to_utf16le_bytes(
     data: &mut [u8],
     rule: &RuleRef,
     regex: &Regex,
     replace_behavior: &ReplaceBehavior,
+) -> bool {
+    apply_regex_to_utf16_bytes(data, rule, regex, replace_behavior, Utf16ByteOrder::Le)
+}
+
+/// Applies `regex` to a plain UTF-16BE-encoded buffer, e.g. a Java- or network-protocol-produced
+/// log file, or a DER BMPString value.
+pub(crate) fn apply_regex_to_utf16be_bytes(
+    data: &mut [u8],
+    rule: &RuleRef,
+    regex: &Regex,
+    replace_behavior: &ReplaceBehavior,
+) -> bool {
+    apply_regex_to_utf16_bytes(data, rule, regex, replace_behavior, Utf16ByteOrder::Be)
+}
+
+/// If `data` starts with a UTF-16 byte-order mark (`\xFF\xFE` for little-endian, `\xFE\xFF` for
+/// big-endian), returns the byte order it signals plus the BOM's length (always 2); otherwise
+/// returns little-endian -- the more common producer in practice -- with a length of 0.
+fn sniff_utf16_byte_order(data: &[u8]) -> (Utf16ByteOrder, usize) {
+    match data {
+        [0xFF, 0xFE, ..] => (Utf16ByteOrder::Le, 2),
+        [0xFE, 0xFF, ..] => (Utf16ByteOrder::Be, 2),
+        _ => (Utf16ByteOrder::Le, 0),
+    }
+}
+
+/// Applies `regex` to a UTF-16-encoded buffer whose byte order isn't known up front, the way
+/// [`apply_regex_to_utf16le_bytes`] and [`apply_regex_to_utf16be_bytes`] require it to be: the
+/// byte order is sniffed from a leading byte-order mark via [`sniff_utf16_byte_order`], and only
+/// the text after it is decoded and scrubbed -- the BOM itself is left in place, same as any other
+/// byte this pass doesn't touch.
+pub(crate) fn apply_regex_to_utf16_bom_bytes(
+    data: &mut [u8],
+    rule: &RuleRef,
+    regex: &Regex,
+    replace_behavior: &ReplaceBehavior,
+) -> bool {
+    let (byte_order, bom_len) = sniff_utf16_byte_order(data);
+    apply_regex_to_utf16_bytes(&mut data[bom_len..], rule, regex, replace_behavior, byte_order)
+}
+
+/// Scans `data` for contiguous runs of valid UTF-8, treating it as conventionally-UTF-8
+/// ("bstr"-style) rather than requiring it to validate as a whole the way strict decoding --
+/// and [`MutSegmentIter`] -- would: a byte sequence that doesn't decode is simply skipped over
+/// (standing in for the replacement character `String::from_utf8_lossy` would emit there) rather
+/// than ending the scan, so text elsewhere in `data` is still found. Each run's `decoded` string is
+/// a verbatim copy of `data[range]` -- only ever made up of bytes that were already valid UTF-8 --
+/// so a byte offset into one is a byte offset into the other, unlike the UTF-16 segments above.
+fn find_lossy_utf8_runs(data: &[u8]) -> Vec<(Range<usize>, String)> {
+    let mut runs = Vec::new();
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let (valid_up_to, invalid_len) = match std::str::from_utf8(&data[offset..]) {
+            Ok(rest) => (rest.len(), 0),
+            Err(err) => {
+                let valid_up_to = err.valid_up_to();
+                // `error_len` is `None` for a sequence that looks valid so far but is cut off by
+                // the end of `data`; there's no more of `data` to find a matching end in, so the
+                // rest of it is invalid.
+                let invalid_len = err.error_len().unwrap_or(data.len() - offset - valid_up_to);
+                (valid_up_to, invalid_len.max(1))
+            }
+        };
+
+        if valid_up_to > 0 {
+            let valid = std::str::from_utf8(&data[offset..offset + valid_up_to])
+                .expect("valid_up_to bytes are valid UTF-8 by construction");
+            runs.push((offset..offset + valid_up_to, valid.to_owned()));
+        }
+
+        offset += valid_up_to + invalid_len;
+    }
+
+    runs
+}
+
+/// Like [`MutSegmentIter`], but for text that isn't guaranteed to be valid in any single fixed
+/// encoding -- e.g. Latin-1, mixed encodings, or arbitrary binary with embedded text -- where
+/// `MutSegmentIter`'s strict decoding would give up on the rest of `data` the moment it hits a
+/// byte sequence that's invalid in its target encoding.
+struct LossyUtf8SegmentIter<'a> {
+    data: &'a mut [u8],
+    runs: std::vec::IntoIter<(Range<usize>, String)>,
+}
+
+impl<'a> LossyUtf8SegmentIter<'a> {
+    fn new(data: &'a mut [u8]) -> Self {
+        let runs = find_lossy_utf8_runs(data).into_iter();
+        Self { data, runs }
+    }
+}
+
+impl<'a> Iterator for LossyUtf8SegmentIter<'a> {
+    type Item = MutSegment<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (range, decoded) = self.runs.next()?;
+
+        // Safety: same reasoning as `MutSegmentIter::next` -- `find_lossy_utf8_runs` only ever
+        // returns non-overlapping ranges, so handing out a `'a`-lifetimed slice per call can't
+        // alias a slice handed out by an earlier call.
+        let raw = unsafe {
+            std::mem::transmute::<&'_ mut [u8], &'a mut [u8]>(&mut self.data[range])
+        };
+        Some(MutSegment { raw, decoded })
+    }
+}
+
+impl<'a> FusedIterator for LossyUtf8SegmentIter<'a> {}
+
+/// Applies `regex` -- with full Unicode semantics, unlike [`apply_regex_to_utf8_bytes`]'s
+/// byte-oriented ASCII-only matching -- to the lossily-decoded text runs of `data`, e.g. a text
+/// attachment that mixes encodings or contains incidental invalid UTF-8.
+fn apply_regex_to_lossy_utf8_bytes(
+    data: &mut [u8],
+    rule: &RuleRef,
+    regex: &Regex,
+    replace_behavior: &ReplaceBehavior,
 ) -> bool {
     let mut changed = false;
-    for segment in MutSegmentIter::new(data, *UTF_16LE) {
-        let segment_wstr = unsafe { WStr::from_utf16le_unchecked_mut(segment.raw) };
 
+    for segment in LossyUtf8SegmentIter::new(data) {
         match replace_behavior {
             ReplaceBehavior::Value => {
                 for re_match in regex.find_iter(&segment.decoded) {
+                    segment.raw[re_match.start()..re_match.end()].apply_redaction(&rule.redaction);
                     changed = true;
-                    let match_wstr = get_wstr_match(&segment.decoded, re_match, segment_wstr);
-                    match_wstr.apply_redaction(&rule.redaction);
                 }
             }
             ReplaceBehavior::Groups(ref replace_groups) => {
                 for captures in regex.captures_iter(&segment.decoded) {
                     for group_idx in replace_groups.iter() {
                         if let Some(re_match) = captures.get(*group_idx as usize) {
+                            segment.raw[re_match.start()..re_match.end()]
+                                .apply_redaction(&rule.redaction);
                             changed = true;
-                            let match_wstr =
-                                get_wstr_match(&segment.decoded, re_match, segment_wstr);
-                            match_wstr.apply_redaction(&rule.redaction);
                         }
                     }
                 }
             }
         }
     }
+
     changed
 }
 
-/// Extract the matching encoded slice from the encoded string.
-fn get_wstr_match<'a>(all_text: &str, re_match: Match, all_encoded: &'a mut WStr) -> &'a mut WStr {
-    let mut encoded_start = 0;
-    let mut encoded_end = 0;
+/// Minimum number of base64 alphabet characters (not counting `=` padding) a run must contain
+/// before it is decoded and scanned. Below this, treating a short incidental base64-alphabet
+/// token (e.g. a hex-looking id) as an armored region risks corrupting it for no benefit. A run
+/// of 16 characters encodes 12 bytes, so a shorter encoded value, such as a bare IPv4 address
+/// (12 characters), is not decoded or scanned.
+const MIN_BASE64_RUN_CHARS: usize = 16;
+
+fn is_base64_alphabet(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'+' || byte == b'/'
+}
+
+fn is_base64_content(byte: u8) -> bool {
+    is_base64_alphabet(byte) || byte == b'='
+}
+
+/// Finds byte ranges in `data` that look like base64-armored regions: runs of base64 alphabet
+/// characters, allowing line breaks (`\r`/`\n`) in between as MIME/PEM-style wrapped base64 does,
+/// with an optional `=`/`==` padding tail, long enough to be worth decoding.
+fn find_base64_runs(data: &[u8]) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut i = 0;
+
+    while i < data.len() {
+        if !is_base64_alphabet(data[i]) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut end = start;
+        let mut content_len = 0;
+
+        while end < data.len() {
+            if is_base64_alphabet(data[end]) {
+                content_len += 1;
+                end += 1;
+            } else if data[end] == b'\r' || data[end] == b'\n' {
+                end += 1;
+            } else {
+                break;
+            }
+        }
+
+        // Trailing line breaks belong to whatever follows, not to this run.
+        while end > start && (data[end - 1] == b'\r' || data[end - 1] == b'\n') {
+            end -= 1;
+        }
+
+        let mut padded_end = end;
+        while padded_end < data.len() && data[padded_end] == b'=' && padded_end - end < 2 {
+            padded_end += 1;
+        }
+
+        if content_len >= MIN_BASE64_RUN_CHARS {
+            runs.push(start..padded_end);
+            i = padded_end;
+        } else {
+            i = start + 1;
+        }
+    }
+
+    runs
+}
+
+/// Decodes each base64-armored run found in `data`, recursively applies the UTF-8, UTF-16LE,
+/// UTF-16BE, UTF-16-with-BOM, and lossy-UTF-8 passes to the decoded bytes, and -- only if one of
+/// them actually changed something -- re-encodes the result back over the run's original content
+/// positions.
+///
+/// The decoded buffer is only ever scrubbed in place, never resized, and a fixed number of bytes
+/// always base64-encodes to the same number of content characters. So writing the re-encoded
+/// characters back over exactly the positions the original content (alphabet and padding) bytes
+/// occupied -- leaving any interleaved line breaks untouched -- can never change the length of
+/// `data`, matching the same invariant `apply_regex_to_utf8_bytes` and
+/// `apply_regex_to_utf16le_bytes` already rely on.
+fn apply_regex_to_base64_bytes(
+    data: &mut [u8],
+    rule: &RuleRef,
+    regex: &Regex,
+    replace_behavior: &ReplaceBehavior,
+) -> bool {
+    let mut changed = false;
+
+    for run in find_base64_runs(data) {
+        let encoded: String = data[run.clone()]
+            .iter()
+            .filter(|&&b| is_base64_content(b))
+            .map(|&b| b as char)
+            .collect();
+
+        let mut decoded = match base64::decode(&encoded) {
+            Ok(decoded) => decoded,
+            Err(_) => continue,
+        };
+
+        let mut run_changed = false;
+        run_changed |= apply_regex_to_utf8_bytes(&mut decoded, rule, regex, replace_behavior);
+        run_changed |= apply_regex_to_utf16le_bytes(&mut decoded, rule, regex, replace_behavior);
+        run_changed |= apply_regex_to_utf16be_bytes(&mut decoded, rule, regex, replace_behavior);
+        run_changed |= apply_regex_to_utf16_bom_bytes(&mut decoded, rule, regex, replace_behavior);
+        run_changed |= apply_regex_to_lossy_utf8_bytes(&mut decoded, rule, regex, replace_behavior);
+
+        if !run_changed {
+            continue;
+        }
 
-    let offsets_iter = all_text.char_indices().zip(all_encoded.char_indices());
-    for ((text_offset, _text_char), (encoded_offset, _encoded_char)) in offsets_iter {
-        if text_offset == re_match.start() {
-            encoded_start = encoded_offset;
+        let re_encoded = base64::encode(&decoded);
+        if re_encoded.len() != encoded.len() {
+            // Should be unreachable: re-encoding the same number of bytes always yields the same
+            // number of content characters. Bail out rather than risk shifting what follows.
+            continue;
         }
-        if text_offset == re_match.end() {
-            encoded_end = encoded_offset;
-            break;
+
+        let mut re_encoded_bytes = re_encoded.bytes();
+        for index in run {
+            if is_base64_content(data[index]) {
+                data[index] = re_encoded_bytes.next().expect("length checked above");
+            }
         }
+
+        changed = true;
     }
 
-    &mut all_encoded[encoded_start..encoded_end]
+    changed
 }
 
 /// Traits to modify the strings in ways we need.
@@ -143,9 +738,10 @@ trait StringMods: AsRef<[u8]> {
     /// any remaining space will be filled with the padding character.
     ///
     /// If the replacement string encodes to a longer byte-slice than the current string the
-    /// replacement string is truncated.  If this does not align with a character boundary
-    /// in the replacement string it is further trucated to the previous character boundary
-    /// and the remainder is filled with the padding char.
+    /// replacement string is truncated.  If this does not align with a grapheme cluster
+    /// boundary in the replacement string it is further truncated to the last grapheme
+    /// cluster that still fits whole, so a combining mark or multi-codepoint emoji is never
+    /// split, and the remainder is filled with the padding char.
     ///
     /// # Panics
     ///
@@ -176,53 +772,6 @@ trait StringMods: AsRef<[u8]> {
     }
 }
 
-impl StringMods for WStr {
-    type Error = Utf16Error;
-
-    fn fill_content(&mut self, fill_char: char) {
-        let size = std::mem::size_of::<u16>();
-
-        let mut buf = [0u16; 1];
-        let fill_u16 = fill_char.encode_utf16(&mut buf[..]); // this panics for us
-        let fill_buf = fill_u16[0].to_le_bytes();
-
-        let chunks = self.as_bytes_mut().chunks_exact_mut(size);
-        for chunk in chunks {
-            chunk.copy_from_slice(&fill_buf);
-        }
-    }
-
-    fn swap_content(&mut self, replacement: &str, padding: char) {
-        let size = std::mem::size_of::<u16>();
-        let len = self.len();
-
-        let mut buf = [0u16; 1];
-        let fill_u16 = padding.encode_utf16(&mut buf[..]); // this panics for us.
-        let fill_buf = fill_u16[0].to_le_bytes();
-
-        let mut offset = 0;
-        for code in replacement.encode_utf16() {
-            let char_len = if 0xD800 & code == 0xD800 {
-                size * 2 // leading surrogate
-            } else {
-                size
-            };
-            if (len - offset) < char_len {
-                break; // Not enough space for this char
-            }
-            let target = &mut self.as_bytes_mut()[offset..offset + size];
-            target.copy_from_slice(&code.to_le_bytes());
-            offset += size;
-        }
-
-        let remainder_bytes = &mut self.as_bytes_mut()[offset..];
-        let chunks = remainder_bytes.chunks_exact_mut(size);
-        for chunk in chunks {
-            chunk.copy_from_slice(&fill_buf);
-        }
-    }
-}
-
 impl StringMods for [u8] {
     type Error = Utf8Error;
 
@@ -238,7 +787,15 @@ impl StringMods for [u8] {
         let mut buf = [0u8; 1];
         padding.encode_utf8(&mut buf[..]); // this panics for us
 
-        let cutoff = replacement.len().min(self.len());
+        let mut cutoff = 0;
+        for grapheme in replacement.graphemes(true) {
+            let next_cutoff = cutoff + grapheme.len();
+            if next_cutoff > self.len() {
+                break;
+            }
+            cutoff = next_cutoff;
+        }
+
         let (left, right) = self.split_at_mut(cutoff);
         left.copy_from_slice(&replacement.as_bytes()[..cutoff]);
 
@@ -248,20 +805,87 @@ impl StringMods for [u8] {
     }
 }
 
+/// How [`MutSegmentIter`] turns the bytes it's given into `decoded` strings.
+enum DecodeMode {
+    /// Delegates to an `encoding`-crate decoder, stopping a segment (and re-syncing two bytes
+    /// forward) at the first byte its encoding rejects.
+    Strict(Box<dyn RawDecoder>),
+    /// Decodes raw UTF-16 code units by hand, turning a lone surrogate into `U+FFFD` instead of
+    /// ending the segment. See [`decode_utf16_lossy`].
+    Lossy(Utf16ByteOrder),
+}
+
+/// A decoded segment awaiting its qualifying printable runs being handed out one at a time by
+/// [`MutSegmentIter::next_filtered`].
+struct PendingSegment<'a> {
+    raw: &'a mut [u8],
+    decoded: String,
+    runs: std::vec::IntoIter<Range<usize>>,
+}
+
+/// The run-acceptance predicate used when [`MutSegmentIter::min_run_len`] is set without
+/// [`MutSegmentIter::printable`]: reject control characters,
+/// the same rough heuristic `strings(1)` uses, while leaving the exact definition of "printable"
+/// overridable via [`MutSegmentIter::printable`].
+fn default_is_printable(c: char) -> bool {
+    !c.is_control()
+}
+
 struct MutSegmentIter<'a> {
     data: &'a mut [u8],
-    decoder: Box<dyn RawDecoder>,
+    mode: DecodeMode,
     offset: usize,
+    min_run_len: Option<usize>,
+    is_printable: Option<Box<dyn Fn(char) -> bool>>,
+    pending: Option<PendingSegment<'a>>,
 }
 
 impl<'a> MutSegmentIter<'a> {
     fn new(data: &'a mut [u8], encoding: impl Encoding) -> Self {
         Self {
             data,
-            decoder: encoding.raw_decoder(),
+            mode: DecodeMode::Strict(encoding.raw_decoder()),
             offset: 0,
+            min_run_len: None,
+            is_printable: None,
+            pending: None,
         }
     }
+
+    /// Like [`MutSegmentIter::new`], but for UTF-16 data that may contain unpaired surrogates: a
+    /// lone surrogate becomes a single `U+FFFD` in `decoded` rather than ending the segment, so a
+    /// string with one bad code unit in it doesn't get silently dropped or truncated the way the
+    /// strict decoder would drop or truncate it. Since nothing ever aborts a segment, this yields
+    /// at most one segment, covering every code unit `data` has room for.
+    fn new_lossy(data: &'a mut [u8], byte_order: Utf16ByteOrder) -> Self {
+        Self {
+            data,
+            mode: DecodeMode::Lossy(byte_order),
+            offset: 0,
+            min_run_len: None,
+            is_printable: None,
+            pending: None,
+        }
+    }
+
+    /// Only yield segments carved from a contiguous run of at least `min_run_len` chars each
+    /// satisfying [`MutSegmentIter::printable`]'s predicate (or [`default_is_printable`], if that
+    /// hasn't been set either) -- the same tunable recall/precision knob `strings(1)`'s `-n` gives,
+    /// for carving candidate strings out of a binary crash dump. Without this, a segment is only
+    /// ever as short as the underlying decoder allows, with no floor on run length and no
+    /// printability requirement at all.
+    fn min_run_len(mut self, min_run_len: usize) -> Self {
+        self.min_run_len = Some(min_run_len);
+        self
+    }
+
+    /// Classifies which decoded `char`s count towards a run's length for
+    /// [`MutSegmentIter::min_run_len`] -- e.g. to accept a wider or narrower Unicode range than
+    /// [`default_is_printable`]'s "reject control characters" default.
+    fn printable(mut self, is_printable: impl Fn(char) -> bool + 'static) -> Self {
+        self.is_printable = Some(Box::new(is_printable));
+        self
+    }
 }
 
 impl<'a> Iterator for MutSegmentIter<'a> {
@@ -271,6 +895,77 @@ impl<'a> Iterator for MutSegmentIter<'a> {
         // We are handing out multiple mutable slices from the same mutable slice.  This is
         // safe because we know they are not overlapping.  However the compiler doesn't know
         // this so we need to transmute the lifetimes of the slices we return.
+        if self.min_run_len.is_some() || self.is_printable.is_some() {
+            self.next_filtered()
+        } else {
+            self.next_unfiltered()
+        }
+    }
+}
+
+impl<'a> MutSegmentIter<'a> {
+    fn next_unfiltered(&mut self) -> Option<MutSegment<'a>> {
+        match &self.mode {
+            DecodeMode::Strict(_) => self.next_strict(),
+            DecodeMode::Lossy(byte_order) => self.next_lossy(*byte_order),
+        }
+    }
+
+    /// Carves contiguous printable runs of at least `min_run_len` chars out of each underlying
+    /// segment [`MutSegmentIter::next_unfiltered`] yields, handing them out one at a time (so one
+    /// encoding-valid segment with, say, a control character in the middle becomes two shorter
+    /// segments rather than one with the control character still in it, or none at all).
+    fn next_filtered(&mut self) -> Option<MutSegment<'a>> {
+        let min_run_len = self.min_run_len.unwrap_or(1);
+
+        // Taken out of `self` for the duration of the loop below, so that borrowing it as a
+        // `&dyn Fn` doesn't keep `self` borrowed across the `self.next_unfiltered()` call.
+        let is_printable = self.is_printable.take();
+        let is_printable_ref: &dyn Fn(char) -> bool =
+            is_printable.as_deref().unwrap_or(&default_is_printable);
+
+        let result = loop {
+            if let Some(pending) = &mut self.pending {
+                match pending.runs.next() {
+                    Some(text_range) => {
+                        let decoded = pending.decoded[text_range.clone()].to_owned();
+                        let raw_range =
+                            utf16_byte_range_for_text_range(&pending.decoded, text_range);
+                        let raw = unsafe {
+                            std::mem::transmute::<&'_ mut [u8], &'a mut [u8]>(
+                                &mut pending.raw[raw_range],
+                            )
+                        };
+                        break Some(MutSegment { raw, decoded });
+                    }
+                    None => {
+                        self.pending = None;
+                        continue;
+                    }
+                }
+            }
+
+            let segment = match self.next_unfiltered() {
+                Some(segment) => segment,
+                None => break None,
+            };
+            let runs = find_printable_runs(&segment.decoded, min_run_len, is_printable_ref);
+            self.pending = Some(PendingSegment {
+                raw: segment.raw,
+                decoded: segment.decoded,
+                runs: runs.into_iter(),
+            });
+        };
+
+        self.is_printable = is_printable;
+        result
+    }
+
+    fn next_strict(&mut self) -> Option<MutSegment<'a>> {
+        let decoder = match &mut self.mode {
+            DecodeMode::Strict(decoder) => decoder,
+            DecodeMode::Lossy(_) => unreachable!("next_strict is only called in strict mode"),
+        };
         let mut decoded = String::with_capacity(self.data.len() - self.offset);
 
         loop {
@@ -280,8 +975,7 @@ impl<'a> Iterator for MutSegmentIter<'a> {
 
             decoded.clear();
             let start = self.offset;
-            let (unprocessed_offset, err) =
-                self.decoder.raw_feed(&self.data[start..], &mut decoded);
+            let (unprocessed_offset, err) = decoder.raw_feed(&self.data[start..], &mut decoded);
             let end = start + unprocessed_offset;
 
             if let Some(err) = err {
@@ -291,7 +985,7 @@ impl<'a> Iterator for MutSegmentIter<'a> {
                     // This should never happen, but if it does, re-set the decoder and skip
                     // forward to the next 2 bytes.
                     self.offset += std::mem::size_of::<u16>(); // TODO: encoding-neutral?!?
-                    self.decoder = self.decoder.from_self();
+                    *decoder = decoder.from_self();
                 }
                 if decoded.len() > 2 {
                     return Some(MutSegment {
@@ -322,10 +1016,123 @@ impl<'a> Iterator for MutSegmentIter<'a> {
             }
         }
     }
+
+    fn next_lossy(&mut self, byte_order: Utf16ByteOrder) -> Option<MutSegment<'a>> {
+        if self.offset >= self.data.len() {
+            return None;
+        }
+
+        let start = self.offset;
+        let (decoded, consumed) = decode_utf16_lossy(&self.data[start..], byte_order);
+        if consumed == 0 {
+            // Not even a single whole code unit left, e.g. one trailing byte: nothing more a
+            // UTF-16 decoder, lossy or not, could do with it.
+            return None;
+        }
+
+        let end = start + consumed;
+        self.offset = end;
+
+        Some(MutSegment {
+            raw: unsafe {
+                std::mem::transmute::<&'_ mut [u8], &'_ mut [u8]>(&mut self.data[start..end])
+            },
+            decoded,
+        })
+    }
 }
 
 impl<'a> FusedIterator for MutSegmentIter<'a> {}
 
+/// Reads the UTF-16 code unit at `data[offset..offset + 2]` in `byte_order`, or `None` if fewer
+/// than 2 bytes remain there.
+fn read_utf16_unit(data: &[u8], offset: usize, byte_order: Utf16ByteOrder) -> Option<u16> {
+    let unit = data.get(offset..offset + 2)?;
+    Some(match byte_order {
+        Utf16ByteOrder::Le => u16::from_le_bytes([unit[0], unit[1]]),
+        Utf16ByteOrder::Be => u16::from_be_bytes([unit[0], unit[1]]),
+    })
+}
+
+fn is_high_surrogate(unit: u16) -> bool {
+    (0xD800..=0xDBFF).contains(&unit)
+}
+
+fn is_low_surrogate(unit: u16) -> bool {
+    (0xDC00..=0xDFFF).contains(&unit)
+}
+
+/// Decodes as many leading UTF-16 code units of `data` as form whole units, the way
+/// `String::from_utf8_lossy` decodes as much of a byte slice as it can: a high surrogate paired
+/// with a following low surrogate becomes the astral character they encode, but a high or low
+/// surrogate with no valid partner becomes a single `U+FFFD` and decoding resumes right after it,
+/// rather than stopping there. Returns the decoded string and how many bytes of `data` it
+/// consumed -- always an even number, and short of `data.len()` only if a single trailing byte is
+/// left over.
+fn decode_utf16_lossy(data: &[u8], byte_order: Utf16ByteOrder) -> (String, usize) {
+    let mut decoded = String::new();
+    let mut offset = 0;
+
+    while let Some(unit) = read_utf16_unit(data, offset, byte_order) {
+        if is_high_surrogate(unit) {
+            if let Some(low) = read_utf16_unit(data, offset + 2, byte_order) {
+                if is_low_surrogate(low) {
+                    let code_point = 0x10000
+                        + ((u32::from(unit) - 0xD800) << 10)
+                        + (u32::from(low) - 0xDC00);
+                    decoded.push(
+                        char::from_u32(code_point).expect("surrogate pairs decode to valid chars"),
+                    );
+                    offset += 4;
+                    continue;
+                }
+            }
+            decoded.push(char::REPLACEMENT_CHARACTER);
+            offset += 2;
+        } else if is_low_surrogate(unit) {
+            decoded.push(char::REPLACEMENT_CHARACTER);
+            offset += 2;
+        } else {
+            decoded.push(
+                char::from_u32(u32::from(unit)).expect("a non-surrogate code unit is a valid char"),
+            );
+            offset += 2;
+        }
+    }
+
+    (decoded, offset)
+}
+
+/// Finds every maximal run of consecutive chars in `decoded` for which `is_printable` returns
+/// `true` that is at least `min_run_len` chars long, returning each run's byte range -- always
+/// starting and ending on a char boundary, so it's safe to slice `decoded` with directly.
+fn find_printable_runs(
+    decoded: &str,
+    min_run_len: usize,
+    is_printable: &dyn Fn(char) -> bool,
+) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut run: Option<(usize, usize)> = None; // (start offset, char count)
+
+    for (offset, ch) in decoded.char_indices() {
+        if is_printable(ch) {
+            run = Some(run.map_or((offset, 1), |(start, count)| (start, count + 1)));
+        } else if let Some((start, count)) = run.take() {
+            if count >= min_run_len {
+                runs.push(start..offset);
+            }
+        }
+    }
+
+    if let Some((start, count)) = run {
+        if count >= min_run_len {
+            runs.push(start..decoded.len());
+        }
+    }
+
+    runs
+}
+
 /// An encoded string segment in a larger data block.
 ///
 /// The slice of data will contain the entire block which will be valid according to the
@@ -393,12 +1200,25 @@ impl<'a> PiiAttachmentsProcessor<'a> {
                     //
                     // - We impose severe restrictions on how redaction methods work, as we must
                     //   not change the lengths of attachments.
+                    //
+                    // - We also look inside base64-armored regions (e.g. an inline certificate or
+                    //   a data: URI) by decoding them, recursing the same passes over the decoded
+                    //   bytes, and re-encoding in place. See `apply_regex_to_base64_bytes` for why
+                    //   that never changes the length either.
                     for (_pattern_type, regex, replace_behavior) in
                         get_regex_for_rule_type(&rule.ty)
                     {
                         changed |= apply_regex_to_utf8_bytes(data, rule, regex, &replace_behavior);
                         changed |=
                             apply_regex_to_utf16le_bytes(data, rule, regex, &replace_behavior);
+                        changed |=
+                            apply_regex_to_utf16be_bytes(data, rule, regex, &replace_behavior);
+                        changed |=
+                            apply_regex_to_utf16_bom_bytes(data, rule, regex, &replace_behavior);
+                        changed |=
+                            apply_regex_to_lossy_utf8_bytes(data, rule, regex, &replace_behavior);
+                        changed |=
+                            apply_regex_to_base64_bytes(data, rule, regex, &replace_behavior);
                     }
                 }
             }
@@ -414,6 +1234,40 @@ impl<'a> PiiAttachmentsProcessor<'a> {
         let state = self.state(filename, ValueType::Binary);
         self.scrub_bytes(data, &state)
     }
+
+    /// Applies PII rules to a DER/ASN.1-encoded buffer, such as a certificate or keystore.
+    ///
+    /// Unlike [`PiiAttachmentsProcessor::scrub_bytes`], this walks the TLV structure instead of
+    /// scanning the raw bytes, so a stray match can't corrupt a length field. Each string value is
+    /// scanned on its own; text split across two TLVs is not joined back together. See
+    /// [`crate::pii::der`] for the walk itself.
+    ///
+    /// Returns `true`, if the buffer was modified.
+    pub(crate) fn scrub_der_bytes(&self, data: &mut [u8], state: &ProcessingState<'_>) -> bool {
+        let mut changed = false;
+
+        for (selector, rules) in &self.compiled_config.applications {
+            if state.path().matches_selector(&selector) {
+                for rule in rules {
+                    for (_pattern_type, regex, replace_behavior) in
+                        get_regex_for_rule_type(&rule.ty)
+                    {
+                        changed |= der::scrub_der_bytes(data, rule, regex, &replace_behavior);
+                    }
+                }
+            }
+        }
+
+        changed
+    }
+
+    /// Applies PII scrubbing rules to a DER-encoded attachment, such as a certificate or keystore.
+    ///
+    /// Returns `true`, if the attachment was modified.
+    pub fn scrub_der_attachment(&self, filename: &str, data: &mut [u8]) -> bool {
+        let state = self.state(filename, ValueType::Binary);
+        self.scrub_der_bytes(data, &state)
+    }
 }
 
 #[cfg(test)]
@@ -444,6 +1298,16 @@ mod tests {
             output: &'a [u8],
             changed: bool,
         },
+        PatternReplace {
+            selector: &'a str,
+            pattern: &'a str,
+            replacement: &'a str,
+            filename: &'a str,
+            value_type: ValueType,
+            input: &'a [u8],
+            output: &'a [u8],
+            changed: bool,
+        },
     }
 
     impl<'a> AttachmentBytesTestCase<'a> {
@@ -461,16 +1325,45 @@ mod tests {
                     let config = serde_json::from_value::<PiiConfig>(serde_json::json!(
                         {
                             "applications": {
-                                selector: [rule]
+                                selector: [rule]
+                            }
+                        }
+                    ))
+                    .unwrap();
+                    (config, filename, value_type, input, output, changed)
+                }
+                AttachmentBytesTestCase::Regex {
+                    selector,
+                    regex,
+                    filename,
+                    value_type,
+                    input,
+                    output,
+                    changed,
+                } => {
+                    let config = serde_json::from_value::<PiiConfig>(serde_json::json!(
+                        {
+                            "rules": {
+                                "custom": {
+                                    "type": "pattern",
+                                    "pattern": regex,
+                                    "redaction": {
+                                      "method": "remove"
+                                    }
+                                }
+                            },
+                            "applications": {
+                                selector: ["custom"]
                             }
                         }
                     ))
                     .unwrap();
                     (config, filename, value_type, input, output, changed)
                 }
-                AttachmentBytesTestCase::Regex {
+                AttachmentBytesTestCase::PatternReplace {
                     selector,
-                    regex,
+                    pattern,
+                    replacement,
                     filename,
                     value_type,
                     input,
@@ -482,9 +1375,10 @@ mod tests {
                             "rules": {
                                 "custom": {
                                     "type": "pattern",
-                                    "pattern": regex,
+                                    "pattern": pattern,
                                     "redaction": {
-                                      "method": "remove"
+                                      "method": "replace",
+                                      "text": replacement
                                     }
                                 }
                             },
@@ -513,6 +1407,10 @@ mod tests {
         UTF_16LE.encode(s, EncoderTrap::Strict).unwrap()
     }
 
+    fn utf16be(s: &str) -> Vec<u8> {
+        UTF_16BE.encode(s, EncoderTrap::Strict).unwrap()
+    }
+
     #[test]
     fn test_ip_replace_padding() {
         AttachmentBytesTestCase::Builtin {
@@ -597,6 +1495,151 @@ mod tests {
         .run();
     }
 
+    #[test]
+    fn test_ip_masking_utf16be() {
+        AttachmentBytesTestCase::Builtin {
+            selector: "$binary",
+            rule: "@ip:mask",
+            filename: "foo.txt",
+            value_type: ValueType::Binary,
+            input: utf16be("before 127.0.0.1 after").as_slice(),
+            output: utf16be("before ********* after").as_slice(),
+            changed: true,
+        }
+        .run();
+    }
+
+    #[test]
+    fn test_ip_masking_utf16_with_le_bom() {
+        let mut input = vec![0xFF, 0xFE];
+        input.extend(utf16le("before 127.0.0.1 after"));
+        let mut output = vec![0xFF, 0xFE];
+        output.extend(utf16le("before ********* after"));
+
+        AttachmentBytesTestCase::Builtin {
+            selector: "$binary",
+            rule: "@ip:mask",
+            filename: "foo.txt",
+            value_type: ValueType::Binary,
+            input: input.as_slice(),
+            output: output.as_slice(),
+            changed: true,
+        }
+        .run();
+    }
+
+    #[test]
+    fn test_ip_masking_utf16_with_be_bom() {
+        let mut input = vec![0xFE, 0xFF];
+        input.extend(utf16be("before 127.0.0.1 after"));
+        let mut output = vec![0xFE, 0xFF];
+        output.extend(utf16be("before ********* after"));
+
+        AttachmentBytesTestCase::Builtin {
+            selector: "$binary",
+            rule: "@ip:mask",
+            filename: "foo.txt",
+            value_type: ValueType::Binary,
+            input: input.as_slice(),
+            output: output.as_slice(),
+            changed: true,
+        }
+        .run();
+    }
+
+    #[test]
+    fn test_sniff_utf16_byte_order() {
+        assert_eq!(sniff_utf16_byte_order(b"\xff\xfeh\x00i\x00"), (Utf16ByteOrder::Le, 2));
+        assert_eq!(sniff_utf16_byte_order(b"\xfe\xff\x00h\x00i"), (Utf16ByteOrder::Be, 2));
+        assert_eq!(sniff_utf16_byte_order(b"h\x00i\x00"), (Utf16ByteOrder::Le, 0));
+        assert_eq!(sniff_utf16_byte_order(b""), (Utf16ByteOrder::Le, 0));
+    }
+
+    #[test]
+    fn test_replace_with_astral_character_utf16le() {
+        // Regression test: `swap_content` used to compute the leading surrogate's required space
+        // as two code units but only ever advance the write offset by one, so the trailing
+        // surrogate either got written over the following character or dropped, corrupting any
+        // replacement text outside the Basic Multilingual Plane (e.g. emoji).
+        let input = utf16le("before XX after");
+        let output = utf16le("before \u{1f600} after");
+
+        AttachmentBytesTestCase::PatternReplace {
+            selector: "$binary",
+            pattern: "XX",
+            replacement: "\u{1f600}",
+            filename: "foo.txt",
+            value_type: ValueType::Binary,
+            input: input.as_slice(),
+            output: output.as_slice(),
+            changed: true,
+        }
+        .run();
+    }
+
+    #[test]
+    fn test_replace_with_astral_character_utf16be() {
+        let input = utf16be("before XX after");
+        let output = utf16be("before \u{1f600} after");
+
+        AttachmentBytesTestCase::PatternReplace {
+            selector: "$binary",
+            pattern: "XX",
+            replacement: "\u{1f600}",
+            filename: "foo.txt",
+            value_type: ValueType::Binary,
+            input: input.as_slice(),
+            output: output.as_slice(),
+            changed: true,
+        }
+        .run();
+    }
+
+    #[test]
+    fn test_ip_masking_base64() {
+        AttachmentBytesTestCase::Builtin {
+            selector: "$binary",
+            rule: "@ip:mask",
+            filename: "foo.txt",
+            value_type: ValueType::Binary,
+            input: b"payload:YmVmb3JlIDEyNy4wLjAuMSBhZnRlcg==:end",
+            output: b"payload:YmVmb3JlICoqKioqKioqKiBhZnRlcg==:end",
+            changed: true,
+        }
+        .run();
+    }
+
+    #[test]
+    fn test_ip_masking_base64_line_wrapped() {
+        AttachmentBytesTestCase::Builtin {
+            selector: "$binary",
+            rule: "@ip:mask",
+            filename: "foo.txt",
+            value_type: ValueType::Binary,
+            input: b"YmVmb3JlIDEyNy4w\nLjAuMSBhZnRlcg==",
+            output: b"YmVmb3JlICoqKioq\nKioqKiBhZnRlcg==",
+            changed: true,
+        }
+        .run();
+    }
+
+    #[test]
+    fn test_base64_run_too_short_is_left_alone() {
+        // "MTI3LjAuMC4x" decodes to "127.0.0.1", but at 12 characters it is below
+        // `MIN_BASE64_RUN_CHARS`, so it is left alone rather than risk treating arbitrary short
+        // tokens as armored PII.
+        AttachmentBytesTestCase::Builtin {
+            selector: "$binary",
+            rule: "@ip:mask",
+            filename: "foo.txt",
+            value_type: ValueType::Binary,
+            input: b"id:MTI3LjAuMC4x:end",
+            output: b"id:MTI3LjAuMC4x:end",
+            changed: false,
+        }
+        .run();
+    }
+
     #[test]
     fn test_ip_removing() {
         AttachmentBytesTestCase::Builtin {
@@ -792,53 +1835,407 @@ mod tests {
     }
 
     #[test]
-    fn test_fill_content_wstr() {
+    fn test_segments_filtered_drops_runs_below_min_len() {
+        let mut data = Vec::from(&b"h\x00i\x00\x01\x00w\x00o\x00r\x00l\x00d\x00"[..]);
+        let mut iter = MutSegmentIter::new(&mut data, *UTF_16LE).min_run_len(3);
+
+        // "hi" is below the 3-char floor, and the control character between the two words isn't
+        // printable at all under the default predicate, so only "world" survives.
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "world");
+        assert_eq!(segment.raw, b"w\x00o\x00r\x00l\x00d\x00");
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_segments_filtered_yields_every_qualifying_run() {
+        let mut data = Vec::from(&b"h\x00i\x00\x01\x00w\x00o\x00r\x00l\x00d\x00"[..]);
+        let mut iter = MutSegmentIter::new(&mut data, *UTF_16LE).min_run_len(1);
+
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "hi");
+        assert_eq!(segment.raw, b"h\x00i\x00");
+
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "world");
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_segments_filtered_custom_printable_predicate() {
+        let mut data = Vec::from(&b"h\x00i\x001\x002\x00o\x00k\x00"[..]);
+        let mut iter = MutSegmentIter::new(&mut data, *UTF_16LE)
+            .min_run_len(1)
+            .printable(|c| c.is_ascii_alphabetic());
+
+        // Digits aren't "printable" under this predicate, splitting the run around them even
+        // though the default predicate would have accepted them and kept one run.
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "hi");
+
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "ok");
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_segments_lossy_all_valid() {
+        let mut data = Vec::from(&b"h\x00e\x00l\x00l\x00o\x00"[..]);
+        let mut iter = MutSegmentIter::new_lossy(&mut data[..], Utf16ByteOrder::Le);
+
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "hello");
+        assert_eq!(segment.raw, b"h\x00e\x00l\x00l\x00o\x00");
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_segments_lossy_decodes_valid_surrogate_pair() {
+        // U+1F600, as a high/low surrogate pair.
+        let mut data = Vec::from(&b"\x3d\xd8\x00\xde"[..]);
+        let mut iter = MutSegmentIter::new_lossy(&mut data[..], Utf16ByteOrder::Le);
+
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "\u{1F600}");
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_segments_lossy_replaces_lone_high_surrogate() {
+        // An unpaired high surrogate, e.g. because its low surrogate got dropped, followed by
+        // plain text that a strict decoder would have to give up on entirely.
+        let mut data = Vec::from(&b"\xd8\xd8h\x00i\x00"[..]);
+        let mut iter = MutSegmentIter::new_lossy(&mut data[..], Utf16ByteOrder::Le);
+
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "\u{FFFD}hi");
+        assert_eq!(segment.raw, data.as_slice());
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_segments_lossy_replaces_lone_low_surrogate() {
+        let mut data = Vec::from(&b"\x00\xdch\x00i\x00"[..]);
+        let mut iter = MutSegmentIter::new_lossy(&mut data[..], Utf16ByteOrder::Le);
+
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "\u{FFFD}hi");
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_segments_lossy_resyncs_after_lone_surrogate_mid_string() {
+        let mut data = Vec::from(&b"h\x00i\x00\xd8\xd8y\x00o\x00"[..]);
+        let mut iter = MutSegmentIter::new_lossy(&mut data[..], Utf16ByteOrder::Le);
+
+        // One bad surrogate in the middle doesn't take the rest of the string down with it.
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "hi\u{FFFD}yo");
+        assert_eq!(segment.raw, data.as_slice());
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_segments_lossy_leaves_trailing_odd_byte_unconsumed() {
+        let mut data = Vec::from(&b"h\x00i\x00\xff"[..]);
+        let mut iter = MutSegmentIter::new_lossy(&mut data[..], Utf16ByteOrder::Le);
+
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "hi");
+        assert_eq!(segment.raw, b"h\x00i\x00");
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_fill_utf16_content() {
         let mut b = Vec::from(&b"h\x00e\x00l\x00l\x00o\x00"[..]);
-        let s = WStr::from_utf16le_mut(b.as_mut_slice()).unwrap();
-        s.fill_content('x');
+        fill_utf16_content(&mut b, 'x', Utf16ByteOrder::Le);
         assert_eq!(b.as_slice(), b"x\x00x\x00x\x00x\x00x\x00");
     }
 
     #[test]
     #[should_panic]
-    fn test_fill_content_wstr_panic() {
+    fn test_fill_utf16_content_panic() {
         let mut b = Vec::from(&b"h\x00e\x00y\x00"[..]);
-        let s = WStr::from_utf16le_mut(b.as_mut_slice()).unwrap();
-        s.fill_content('\u{10000}');
+        fill_utf16_content(&mut b, '\u{10000}', Utf16ByteOrder::Le);
+    }
+
+    #[test]
+    fn test_is_utf16_char_boundary() {
+        // "hi😀": h, i, then a surrogate pair for the emoji -- four code units, eight bytes.
+        let b = b"h\x00i\x00\x3d\xd8\x00\xde";
+
+        assert!(is_utf16_char_boundary(b, 0, Utf16ByteOrder::Le));
+        assert!(is_utf16_char_boundary(b, 1, Utf16ByteOrder::Le));
+        assert!(is_utf16_char_boundary(b, 2, Utf16ByteOrder::Le)); // right before the emoji
+        assert!(!is_utf16_char_boundary(b, 3, Utf16ByteOrder::Le)); // inside the surrogate pair
+        assert!(is_utf16_char_boundary(b, 4, Utf16ByteOrder::Le)); // end of the buffer
+    }
+
+    #[test]
+    fn test_truncate_utf16() {
+        let mut b = Vec::from(&b"h\x00i\x00\x3d\xd8\x00\xde"[..]);
+        truncate_utf16(&mut b, 1, 'x', Utf16ByteOrder::Le);
+        assert_eq!(b.as_slice(), b"h\x00x\x00x\x00x\x00");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_truncate_utf16_panic_on_split_surrogate() {
+        let mut b = Vec::from(&b"h\x00i\x00\x3d\xd8\x00\xde"[..]);
+        truncate_utf16(&mut b, 3, 'x', Utf16ByteOrder::Le);
+    }
+
+    #[test]
+    fn test_pop_utf16_surrogate_pair() {
+        let mut b = Vec::from(&b"h\x00i\x00\x3d\xd8\x00\xde"[..]);
+        let popped = pop_utf16(&mut b, 'x', Utf16ByteOrder::Le);
+
+        assert_eq!(popped, Some('😀'));
+        assert_eq!(b.as_slice(), b"h\x00i\x00x\x00x\x00");
+    }
+
+    #[test]
+    fn test_pop_utf16_empty() {
+        let mut b = Vec::from(&b"x\x00x\x00x\x00"[..]);
+        assert_eq!(pop_utf16(&mut b, 'x', Utf16ByteOrder::Le), None);
+        assert_eq!(b.as_slice(), b"x\x00x\x00x\x00");
+    }
+
+    #[test]
+    fn test_remove_utf16() {
+        let mut b = Vec::from(&b"a\x00b\x00c\x00"[..]);
+        let removed = remove_utf16(&mut b, 1, 'x', Utf16ByteOrder::Le);
+
+        assert_eq!(removed, 'b');
+        assert_eq!(b.as_slice(), b"a\x00c\x00x\x00");
+    }
+
+    #[test]
+    fn test_remove_utf16_surrogate_pair() {
+        let mut b = Vec::from(&b"a\x00\x3d\xd8\x00\xde\x62\x00"[..]);
+        let removed = remove_utf16(&mut b, 1, 'x', Utf16ByteOrder::Le);
+
+        assert_eq!(removed, '😀');
+        assert_eq!(b.as_slice(), b"a\x00b\x00x\x00x\x00");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_remove_utf16_panic_out_of_bounds() {
+        let mut b = Vec::from(&b"a\x00x\x00"[..]);
+        remove_utf16(&mut b, 1, 'x', Utf16ByteOrder::Le);
+    }
+
+    #[test]
+    fn test_find_rfind_utf16() {
+        // "棘" (U+68D8) is one code unit, so the "hello" after it starts at code-unit offset 1,
+        // not byte offset 2.
+        assert_eq!(find_utf16("棘hello world", "hello"), Some(1));
+        assert_eq!(rfind_utf16("hello hello", "hello"), Some(6));
+        assert_eq!(find_utf16("hello", "bye"), None);
+    }
+
+    #[test]
+    fn test_replace_utf16_literal_matches() {
+        let mut b = Vec::from(&b"e\x00m\x00a\x00i\x00l\x00:\x00 \x00a\x00@\x00a\x00"[..]);
+        let changed = replace_utf16_literal_matches(&mut b, "a@a", "x@x", Utf16ByteOrder::Le);
+
+        assert!(changed);
+        assert_eq!(b, b"e\x00m\x00a\x00i\x00l\x00:\x00 \x00x\x00@\x00x\x00");
     }
 
     #[test]
-    fn test_swap_content_wstr() {
+    fn test_replace_utf16_literal_matches_no_hit() {
+        let mut b = Vec::from(&b"h\x00e\x00l\x00l\x00o\x00"[..]);
+        let changed = replace_utf16_literal_matches(&mut b, "bye", "x", Utf16ByteOrder::Le);
+
+        assert!(!changed);
+        assert_eq!(b, b"h\x00e\x00l\x00l\x00o\x00");
+    }
+
+    #[test]
+    fn test_replace_utf16_matches_by_predicate() {
+        // Redact whichever whitespace-delimited token contains an "@", leaving the rest alone.
+        let mut b = Vec::from(
+            &b"u\x00s\x00e\x00r\x00 \x00a\x00@\x00b\x00 \x00o\x00k\x00"[..],
+        );
+        let changed = replace_utf16_matches(
+            &mut b,
+            |token| token.contains('@'),
+            "x",
+            Utf16ByteOrder::Le,
+        );
+
+        assert!(changed);
+        assert_eq!(
+            b,
+            b"u\x00s\x00e\x00r\x00 \x00x\x00x\x00x\x00 \x00o\x00k\x00"
+        );
+    }
+
+    #[test]
+    fn test_swap_utf16_content() {
         // Exact same size
         let mut b = Vec::from(&b"h\x00e\x00l\x00l\x00o\x00"[..]);
-        let s = WStr::from_utf16le_mut(b.as_mut_slice()).unwrap();
-        s.swap_content("world", 'x');
+        swap_utf16_content(&mut b, "world", 'x', Utf16ByteOrder::Le);
         assert_eq!(b.as_slice(), b"w\x00o\x00r\x00l\x00d\x00");
 
         // Shorter, padding fits
         let mut b = Vec::from(&b"h\x00e\x00l\x00l\x00o\x00"[..]);
-        let s = WStr::from_utf16le_mut(b.as_mut_slice()).unwrap();
-        s.swap_content("hey", 'x');
+        swap_utf16_content(&mut b, "hey", 'x', Utf16ByteOrder::Le);
         assert_eq!(b.as_slice(), b"h\x00e\x00y\x00x\x00x\x00");
 
         // Longer, truncated fits
         let mut b = Vec::from(&b"h\x00e\x00y\x00"[..]);
-        let s = WStr::from_utf16le_mut(b.as_mut_slice()).unwrap();
-        s.swap_content("world", 'x');
+        swap_utf16_content(&mut b, "world", 'x', Utf16ByteOrder::Le);
         assert_eq!(b.as_slice(), b"w\x00o\x00r\x00");
 
-        // Longer, truncated + padding
+        // Longer, a surrogate pair doesn't fit in the remaining space: padded instead of
+        // truncated mid-character.
         let mut b = Vec::from(&b"h\x00e\x00y\x00"[..]);
-        let s = WStr::from_utf16le_mut(b.as_mut_slice()).unwrap();
-        s.swap_content("yo\u{10000}", 'x');
+        swap_utf16_content(&mut b, "yo\u{10000}", 'x', Utf16ByteOrder::Le);
         assert_eq!(b.as_slice(), b"y\x00o\x00x\x00");
+
+        // A surrogate pair that does fit exactly is written whole, not split.
+        let mut b = Vec::from(&b"h\x00e\x00y\x00y\x00"[..]);
+        swap_utf16_content(&mut b, "yo\u{10000}", 'x', Utf16ByteOrder::Le);
+        assert_eq!(b.as_slice(), b"y\x00o\x00\x00\xd8\x00\xdc");
     }
 
     #[test]
     #[should_panic]
-    fn test_swap_content_wstr_panic() {
+    fn test_swap_utf16_content_panic() {
         let mut b = Vec::from(&b"h\x00e\x00y\x00"[..]);
-        let s = WStr::from_utf16le_mut(b.as_mut_slice()).unwrap();
-        s.swap_content("yo", '\u{10000}');
+        swap_utf16_content(&mut b, "yo", '\u{10000}', Utf16ByteOrder::Le);
+    }
+
+    #[test]
+    fn test_swap_utf16_content_grapheme_cluster_not_split() {
+        // "e" followed by a combining acute accent (U+0301) is a single grapheme cluster ("é")
+        // made up of two code units. If there isn't room for both, neither should be written --
+        // leaving a bare "e" behind would silently drop the accent.
+        let mut b = Vec::from(&b"h\x00"[..]);
+        swap_utf16_content(&mut b, "e\u{0301}", 'x', Utf16ByteOrder::Le);
+        assert_eq!(b.as_slice(), b"x\x00");
+
+        // With room for the whole cluster, both code units are written.
+        let mut b = Vec::from(&b"h\x00i\x00y\x00e\x00"[..]);
+        swap_utf16_content(&mut b, "e\u{0301}", 'x', Utf16ByteOrder::Le);
+        assert_eq!(b.as_slice(), b"e\x00\x01\x03x\x00x\x00");
+    }
+
+    #[test]
+    fn test_swap_content_grapheme_cluster_not_split() {
+        // Same as `test_swap_utf16_content_grapheme_cluster_not_split`, but for the plain UTF-8
+        // `[u8]` impl: "e" plus a combining acute accent is one grapheme cluster ("é") spanning 3
+        // bytes, and shouldn't be split across the available-space boundary.
+        let mut b = Vec::from(&b"hi"[..]);
+        b.swap_content("e\u{0301}", 'x');
+        assert_eq!(b.as_slice(), b"xx");
+
+        let mut b = Vec::from(&b"hiya"[..]);
+        b.swap_content("e\u{0301}", 'x');
+        assert_eq!(b.as_slice(), b"e\xcc\x81x");
+    }
+
+    #[test]
+    fn test_swap_utf16_content_flag_emoji_not_split() {
+        // A flag emoji ("\u{1F1FA}\u{1F1F8}") is two regional-indicator chars forming one grapheme
+        // cluster, each needing a surrogate pair -- four code units in total. With room for only
+        // three, the whole cluster is dropped rather than writing half a flag.
+        let mut b = Vec::from(&b"h\x00i\x00x\x00"[..]);
+        swap_utf16_content(&mut b, "\u{1F1FA}\u{1F1F8}", 'x', Utf16ByteOrder::Le);
+        assert_eq!(b.as_slice(), b"x\x00x\x00x\x00");
+
+        let mut b = Vec::from(&b"h\x00i\x00x\x00x\x00"[..]);
+        swap_utf16_content(&mut b, "\u{1F1FA}\u{1F1F8}", 'x', Utf16ByteOrder::Le);
+        assert_eq!(b.as_slice(), b"\x3c\xd8\xfa\xdd\x3c\xd8\xf8\xdd");
+    }
+
+    #[test]
+    fn test_swap_content_flag_emoji_not_split() {
+        // Same grapheme cluster as `test_swap_utf16_content_flag_emoji_not_split`, but for the
+        // plain UTF-8 `[u8]` impl: eight bytes (four per regional-indicator char), not splittable.
+        let mut b = Vec::from(&b"hix"[..]);
+        b.swap_content("\u{1F1FA}\u{1F1F8}", 'x');
+        assert_eq!(b.as_slice(), b"xxx");
+
+        let mut b = Vec::from(&b"hixxxxxx"[..]);
+        b.swap_content("\u{1F1FA}\u{1F1F8}", 'x');
+        assert_eq!(b.as_slice(), "\u{1F1FA}\u{1F1F8}".as_bytes());
+    }
+
+    #[test]
+    fn test_lossy_utf8_runs_all_valid() {
+        assert_eq!(
+            find_lossy_utf8_runs(b"hello world"),
+            vec![(0..11, "hello world".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_lossy_utf8_runs_splits_around_invalid_bytes() {
+        // `\xff` is never a valid UTF-8 leading byte, so it can't extend the run before or after
+        // it; the two valid runs around it still get found separately.
+        let data = b"ab\xffcd";
+        assert_eq!(
+            find_lossy_utf8_runs(data),
+            vec![(0..2, "ab".to_owned()), (3..5, "cd".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_lossy_utf8_runs_truncated_sequence_at_end() {
+        // A valid UTF-8 lead byte (`\xe2` starts a 3-byte sequence) with nothing after it to
+        // complete the sequence: the dangling byte is invalid, not a segment of its own.
+        let data = b"ok\xe2";
+        assert_eq!(find_lossy_utf8_runs(data), vec![(0..2, "ok".to_owned())]);
+    }
+
+    #[test]
+    fn test_lossy_utf8_segment_iter_mutation() {
+        let mut data = Vec::from(&b"ab\xffcd"[..]);
+        let mut iter = LossyUtf8SegmentIter::new(&mut data);
+
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "ab");
+        segment.raw.copy_from_slice(b"xy");
+
+        let segment = iter.next().unwrap();
+        assert_eq!(segment.decoded, "cd");
+        segment.raw.copy_from_slice(b"zw");
+
+        assert!(iter.next().is_none());
+        assert_eq!(data, b"xy\xffzw");
+    }
+
+    #[test]
+    fn test_unicode_word_boundary_requires_lossy_utf8_pass() {
+        // `\b` is only a genuine Unicode word boundary once the bytes are decoded as text:
+        // `apply_regex_to_utf8_bytes` builds its regex with `.unicode(false)`, under which
+        // non-ASCII bytes are never "word" bytes, so `\b` can't assert true on either side of a
+        // Cyrillic word there and this pattern is never matched by that pass. Invalid bytes
+        // bracketing the word also check that they don't stop the rest of the buffer from being
+        // scrubbed.
+        AttachmentBytesTestCase::Regex {
+            selector: "$binary",
+            regex: r"\bИван\b",
+            filename: "foo.txt",
+            value_type: ValueType::Binary,
+            input: b"\xffbefore \xd0\x98\xd0\xb2\xd0\xb0\xd0\xbd after\xfe",
+            output: b"\xffbefore xxxxxxxx after\xfe",
+            changed: true,
+        }
+        .run();
     }
 }
@@ -0,0 +1,224 @@
+//! A structured DER/ASN.1 TLV walker for certificates, keystores, and similar structured binary
+//! attachments, where scrubbing over the raw bytes with [`crate::pii::attachments`]'s regex passes
+//! is both noisy and can miss text split across a TLV boundary.
+//!
+//! This walks the tag-length-value triples directly: recursing into constructed types (SEQUENCE
+//! `0x30`, SET `0x31`, and constructed context-specific tags alike -- anything with the
+//! constructed bit set), and for primitive string tags (UTF8String, PrintableString, IA5String,
+//! T61String) running the configured regex against just that TLV's value bytes, through the same
+//! [`crate::pii::attachments::apply_regex_to_utf8_bytes`] pass
+//! `PiiAttachmentsProcessor::scrub_bytes` itself uses. Only value bytes are ever rewritten and no
+//! TLV's length ever changes, so the overall attachment length stays exactly as it was.
+//!
+//! BMPString values are left untouched: they're UCS-2/UTF-16 *big-endian*, and
+//! [`is_scrubbable_string_tag`] only admits the four tags above, whose values go through
+//! `apply_regex_to_utf8_bytes`. [`crate::pii::attachments::apply_regex_to_utf16be_bytes`] is not
+//! used by this walker.
+//!
+//! This module isn't declared from `pii`'s module root in this snapshot (that file isn't part of
+//! it either) -- whoever lands it should add `mod der;` there alongside the existing `mod
+//! attachments;`.
+
+use regex::Regex;
+
+use crate::pii::attachments::apply_regex_to_utf8_bytes;
+use crate::pii::compiledconfig::RuleRef;
+use crate::pii::regexes::ReplaceBehavior;
+
+/// Maximum nesting depth the walker will recurse into constructed types before giving up on the
+/// remainder of that branch. Without this, a maliciously or accidentally deeply-nested sequence of
+/// SEQUENCEs could recurse the stack into the ground.
+const MAX_DEPTH: usize = 64;
+
+const TAG_CONSTRUCTED: u8 = 0x20;
+const TAG_NUMBER_MASK: u8 = 0x1F;
+const TAG_HIGH_NUMBER_FORM: u8 = 0x1F;
+
+const TAG_UTF8_STRING: u8 = 0x0C;
+const TAG_PRINTABLE_STRING: u8 = 0x13;
+const TAG_T61_STRING: u8 = 0x14;
+const TAG_IA5_STRING: u8 = 0x16;
+const TAG_BMP_STRING: u8 = 0x1E;
+
+struct Tlv {
+    tag: u8,
+    value_start: usize,
+    value_end: usize,
+}
+
+/// Parses the tag and length of the TLV starting at `offset`, returning the byte range of its
+/// value. Returns `None` for anything this walker doesn't support parsing the length of: a
+/// truncated header, a value that runs past the end of `data`, or an indefinite-length encoding
+/// (valid BER, but not DER, and with no explicit end marker to look for without first
+/// understanding the content it wraps).
+fn read_header(data: &[u8], offset: usize) -> Option<Tlv> {
+    let tag = *data.get(offset)?;
+    let mut pos = offset + 1;
+
+    if tag & TAG_NUMBER_MASK == TAG_HIGH_NUMBER_FORM {
+        // High-tag-number form: the tag number continues in base-128 bytes, each with the
+        // high bit set except the last. We don't need the actual number, just to skip past it.
+        loop {
+            let byte = *data.get(pos)?;
+            pos += 1;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+    }
+
+    let length_byte = *data.get(pos)?;
+    pos += 1;
+
+    let length = if length_byte & 0x80 == 0 {
+        length_byte as usize
+    } else {
+        let length_of_length = (length_byte & 0x7F) as usize;
+        if length_of_length == 0 || length_of_length > std::mem::size_of::<usize>() {
+            return None;
+        }
+
+        let mut length = 0usize;
+        for _ in 0..length_of_length {
+            let byte = *data.get(pos)?;
+            pos += 1;
+            length = (length << 8) | byte as usize;
+        }
+        length
+    };
+
+    let value_start = pos;
+    let value_end = value_start.checked_add(length)?;
+    if value_end > data.len() {
+        return None;
+    }
+
+    Some(Tlv { tag, value_start, value_end })
+}
+
+fn is_scrubbable_string_tag(tag_number: u8) -> bool {
+    matches!(
+        tag_number,
+        TAG_UTF8_STRING | TAG_PRINTABLE_STRING | TAG_T61_STRING | TAG_IA5_STRING
+    )
+}
+
+/// Walks the DER TLV structure in `data`, applying `regex` to every scrubbable primitive string
+/// value it finds. Returns `true` if anything was redacted.
+pub(crate) fn scrub_der_bytes(
+    data: &mut [u8],
+    rule: &RuleRef,
+    regex: &Regex,
+    replace_behavior: &ReplaceBehavior,
+) -> bool {
+    walk(data, rule, regex, replace_behavior, 0)
+}
+
+fn walk(
+    data: &mut [u8],
+    rule: &RuleRef,
+    regex: &Regex,
+    replace_behavior: &ReplaceBehavior,
+    depth: usize,
+) -> bool {
+    if depth >= MAX_DEPTH {
+        return false;
+    }
+
+    let mut changed = false;
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let tlv = match read_header(data, offset) {
+            Some(tlv) => tlv,
+            // Malformed or indefinite-length TLV: stop walking this branch rather than guess at
+            // framing we can no longer trust, potentially corrupting whatever follows.
+            None => break,
+        };
+
+        if tlv.tag & TAG_CONSTRUCTED != 0 {
+            changed |= walk(
+                &mut data[tlv.value_start..tlv.value_end],
+                rule,
+                regex,
+                replace_behavior,
+                depth + 1,
+            );
+        } else if is_scrubbable_string_tag(tlv.tag & TAG_NUMBER_MASK) {
+            changed |= apply_regex_to_utf8_bytes(
+                &mut data[tlv.value_start..tlv.value_end],
+                rule,
+                regex,
+                replace_behavior,
+            );
+        }
+
+        offset = tlv.value_end;
+    }
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pii::attachments::PiiAttachmentsProcessor;
+    use crate::pii::PiiConfig;
+    use crate::processor::ValueType;
+
+    fn scrub(rule: &str, data: &[u8]) -> (Vec<u8>, bool) {
+        let config = serde_json::from_value::<PiiConfig>(serde_json::json!({
+            "applications": {
+                "$binary": [rule]
+            }
+        }))
+        .unwrap();
+
+        let compiled = config.compiled();
+        let mut data = data.to_owned();
+        let processor = PiiAttachmentsProcessor::new(&compiled);
+        let state = processor.state("foo.der", ValueType::Binary);
+        let changed = processor.scrub_der_bytes(&mut data, &state);
+        (data, changed)
+    }
+
+    #[test]
+    fn test_masks_printable_string_inside_sequence() {
+        let input = b"\x30\x18\x13\x16before 127.0.0.1 after";
+        let expected = b"\x30\x18\x13\x16before ********* after";
+
+        let (data, changed) = scrub("@ip:mask", input);
+        assert!(changed);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_leaves_bmp_string_untouched() {
+        // Tagged as BMPString (UTF-16BE in real DER), but holding plain ASCII here: regardless of
+        // what the bytes decode to, this tag is never treated as scrubbable.
+        let input = b"\x1e\x16before 127.0.0.1 after";
+
+        let (data, changed) = scrub("@ip:mask", input);
+        assert!(!changed);
+        assert_eq!(data, input);
+    }
+
+    #[test]
+    fn test_truncated_length_leaves_data_unchanged() {
+        // Declares a 5-byte value but only 2 bytes follow.
+        let input = b"\x13\x05ab";
+
+        let (data, changed) = scrub("@ip:mask", input);
+        assert!(!changed);
+        assert_eq!(data, input);
+    }
+
+    #[test]
+    fn test_indefinite_length_is_rejected() {
+        let input = b"\x30\x80\x13\x16before 127.0.0.1 after\x00\x00";
+
+        let (data, changed) = scrub("@ip:mask", input);
+        assert!(!changed);
+        assert_eq!(data, input);
+    }
+}
@@ -0,0 +1,187 @@
+use std::time::{Duration, Instant};
+
+use crate::processor::{ProcessValue, ProcessingState, Processor};
+use crate::types::{Array, Error, Meta, Object, ProcessingAction, ProcessingResult};
+
+use smartstring::alias::String;
+
+/// A ceiling on how much work a single traversal may do, as a safety valve against payloads that
+/// are valid but pathologically expensive to process.
+///
+/// Either limit (or both) can be set; an unset limit never trips. This complements Relay's
+/// existing depth/size limits, which bound a single event's *shape*, with one that bounds the
+/// *cost* of processing it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessingBudget {
+    max_nodes: Option<usize>,
+    deadline: Option<Instant>,
+}
+
+impl ProcessingBudget {
+    /// A budget with no limits: every node is visited regardless of count or elapsed time.
+    pub fn unlimited() -> Self {
+        ProcessingBudget::default()
+    }
+
+    /// Sets the maximum number of nodes the traversal may visit before being truncated.
+    pub fn max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// Sets a wall-clock deadline, `timeout` from now, after which the traversal is truncated.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(Instant::now() + timeout);
+        self
+    }
+}
+
+/// Wraps a [`Processor`] and enforces a [`ProcessingBudget`] across the whole traversal it drives.
+///
+/// Once the budget is exhausted, every node still to be visited is truncated: `before_process`
+/// returns [`ProcessingAction::SkipChildren`] instead of delegating to the inner processor, so
+/// already-processed fields are left exactly as they are and the event can still be emitted. The
+/// node that tripped the limit gets a `Meta` error recording that processing was cut short; later
+/// nodes are truncated silently to avoid flooding the same event with repeat errors.
+pub struct BudgetedProcessor<P> {
+    inner: P,
+    budget: ProcessingBudget,
+    visited: usize,
+    truncated: bool,
+}
+
+impl<P> BudgetedProcessor<P> {
+    /// Creates a new budget-enforcing wrapper around `inner`.
+    pub fn new(inner: P, budget: ProcessingBudget) -> Self {
+        BudgetedProcessor {
+            inner,
+            budget,
+            visited: 0,
+            truncated: false,
+        }
+    }
+
+    /// Returns `true` once the budget has been exhausted and the remaining traversal is being
+    /// truncated.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Consumes the wrapper, returning the inner processor.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    /// Returns `true` once the budget is exhausted and on every call after that, flipping
+    /// `self.truncated` the first time.
+    fn check_budget(&mut self) -> bool {
+        if self.truncated {
+            return true;
+        }
+
+        self.visited += 1;
+        let over_nodes = self.budget.max_nodes.map_or(false, |max| self.visited > max);
+        let over_deadline = self
+            .budget
+            .deadline
+            .map_or(false, |deadline| Instant::now() >= deadline);
+
+        self.truncated = over_nodes || over_deadline;
+        self.truncated
+    }
+}
+
+impl<P: Processor> Processor for BudgetedProcessor<P> {
+    fn before_process<T: ProcessValue>(
+        &mut self,
+        value: Option<&T>,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        let already_truncated = self.truncated;
+
+        if self.check_budget() {
+            if !already_truncated {
+                meta.add_error(Error::invalid(
+                    "processing budget exceeded, remaining fields were left unprocessed",
+                ));
+            }
+            return Err(ProcessingAction::SkipChildren);
+        }
+
+        self.inner.before_process(value, meta, state)
+    }
+
+    fn after_process<T: ProcessValue>(
+        &mut self,
+        value: Option<&T>,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        if self.truncated {
+            return Ok(());
+        }
+
+        self.inner.after_process(value, meta, state)
+    }
+
+    fn process_string(
+        &mut self,
+        value: &mut String,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        self.inner.process_string(value, meta, state)
+    }
+
+    fn process_array<T>(
+        &mut self,
+        value: &mut Array<T>,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult
+    where
+        T: ProcessValue,
+    {
+        self.inner.process_array(value, meta, state)
+    }
+
+    fn process_object<T>(
+        &mut self,
+        value: &mut Object<T>,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult
+    where
+        T: ProcessValue,
+    {
+        self.inner.process_object(value, meta, state)
+    }
+
+    fn process_u64(
+        &mut self,
+        value: &mut u64,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        self.inner.process_u64(value, meta, state)
+    }
+
+    fn process_i64(
+        &mut self,
+        value: &mut i64,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        self.inner.process_i64(value, meta, state)
+    }
+
+    fn process_f64(
+        &mut self,
+        value: &mut f64,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        self.inner.process_f64(value, meta, state)
+    }
+}
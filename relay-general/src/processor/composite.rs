@@ -0,0 +1,203 @@
+use smartstring::alias::String;
+
+use crate::processor::{ProcessValue, ProcessingState, Processor};
+use crate::types::{Array, Meta, Object, ProcessingAction, ProcessingResult};
+
+/// Merges two `before_process`/`after_process` results from processors run at the same node.
+///
+/// A `DeleteValueHard` always wins, followed by `DeleteValueSoft`, followed by `SkipChildren`;
+/// `Ok(())` only wins if both sides agree. Callers stop running later processors as soon as an
+/// earlier one returns a `DeleteValue*` action, so in practice this only has to reconcile
+/// `SkipChildren` against `Ok(())` -- but it's total so tuple impls don't have to special-case it.
+fn combine(a: ProcessingResult, b: ProcessingResult) -> ProcessingResult {
+    use ProcessingAction::{DeleteValueHard, DeleteValueSoft, SkipChildren};
+
+    match (a, b) {
+        (Err(DeleteValueHard), _) | (_, Err(DeleteValueHard)) => Err(DeleteValueHard),
+        (Err(DeleteValueSoft), _) | (_, Err(DeleteValueSoft)) => Err(DeleteValueSoft),
+        (Err(SkipChildren), _) | (_, Err(SkipChildren)) => Err(SkipChildren),
+        (Ok(()), Ok(())) => Ok(()),
+    }
+}
+
+/// Returns `true` for actions that should stop the rest of a composite's processors from running
+/// at all, because the value they'd see is about to be deleted.
+fn halts(action: &ProcessingResult) -> bool {
+    matches!(
+        action,
+        Err(ProcessingAction::DeleteValueHard) | Err(ProcessingAction::DeleteValueSoft)
+    )
+}
+
+/// Declares a `Processor` impl for a tuple of processors that all run, in order, at every node of
+/// a single traversal -- instead of each running its own full `process_value` pass over the tree.
+///
+/// This is how Relay composes its normalization/PII/trimming stages without paying for one tree
+/// walk per stage. `Processor`'s hooks are generic over the value type being visited, which makes
+/// `Processor` itself non-object-safe, so a runtime `Vec<Box<dyn Processor>>` isn't an option;
+/// composition happens at the type level instead via these tuple impls.
+///
+/// Every hook is forwarded to every member, not just `before_process`/`after_process` -- a
+/// processor's real work usually lives in `process_string`/`process_object`/etc. (see
+/// `SchemaProcessor` for an example), so stopping at the two structural hooks would silently skip
+/// every member but the first for the hooks that matter. `process_array`/`process_object` are a
+/// known exception to "no duplicated work": each member's own implementation of those two hooks
+/// typically calls `value.process_child_values(self, state)` internally to recurse into children,
+/// so chaining all members through them means each member re-walks the subtree independently
+/// under itself rather than the composite making a single combined pass. There's no way to split a
+/// member's "validate this node" logic from its "recurse into children" logic without knowing its
+/// internals, and skipping the hook entirely would mean a member's array/object validation (e.g.
+/// `SchemaProcessor`'s length checks) never runs at all when composed -- which is worse. Until
+/// `Processor` grows a way to recurse once and validate per-member against the shared result, this
+/// trades some redundant re-recursion for every member's validation actually running.
+macro_rules! impl_composite_processor {
+    ($($name:ident)+) => {
+        impl<$($name: Processor),+> Processor for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn before_process<T: ProcessValue>(
+                &mut self,
+                value: Option<&T>,
+                meta: &mut Meta,
+                state: &ProcessingState<'_>,
+            ) -> ProcessingResult {
+                let ($(ref mut $name,)+) = *self;
+                let mut action = Ok(());
+                $(
+                    if !halts(&action) {
+                        action = combine(action, $name.before_process(value, meta, state));
+                    }
+                )+
+                action
+            }
+
+            #[allow(non_snake_case)]
+            fn after_process<T: ProcessValue>(
+                &mut self,
+                value: Option<&T>,
+                meta: &mut Meta,
+                state: &ProcessingState<'_>,
+            ) -> ProcessingResult {
+                let ($(ref mut $name,)+) = *self;
+                let mut action = Ok(());
+                $(
+                    if !halts(&action) {
+                        action = combine(action, $name.after_process(value, meta, state));
+                    }
+                )+
+                action
+            }
+
+            #[allow(non_snake_case)]
+            fn process_string(
+                &mut self,
+                value: &mut String,
+                meta: &mut Meta,
+                state: &ProcessingState<'_>,
+            ) -> ProcessingResult {
+                let ($(ref mut $name,)+) = *self;
+                let mut action = Ok(());
+                $(
+                    if !halts(&action) {
+                        action = combine(action, $name.process_string(value, meta, state));
+                    }
+                )+
+                action
+            }
+
+            #[allow(non_snake_case)]
+            fn process_u64(
+                &mut self,
+                value: &mut u64,
+                meta: &mut Meta,
+                state: &ProcessingState<'_>,
+            ) -> ProcessingResult {
+                let ($(ref mut $name,)+) = *self;
+                let mut action = Ok(());
+                $(
+                    if !halts(&action) {
+                        action = combine(action, $name.process_u64(value, meta, state));
+                    }
+                )+
+                action
+            }
+
+            #[allow(non_snake_case)]
+            fn process_i64(
+                &mut self,
+                value: &mut i64,
+                meta: &mut Meta,
+                state: &ProcessingState<'_>,
+            ) -> ProcessingResult {
+                let ($(ref mut $name,)+) = *self;
+                let mut action = Ok(());
+                $(
+                    if !halts(&action) {
+                        action = combine(action, $name.process_i64(value, meta, state));
+                    }
+                )+
+                action
+            }
+
+            #[allow(non_snake_case)]
+            fn process_f64(
+                &mut self,
+                value: &mut f64,
+                meta: &mut Meta,
+                state: &ProcessingState<'_>,
+            ) -> ProcessingResult {
+                let ($(ref mut $name,)+) = *self;
+                let mut action = Ok(());
+                $(
+                    if !halts(&action) {
+                        action = combine(action, $name.process_f64(value, meta, state));
+                    }
+                )+
+                action
+            }
+
+            #[allow(non_snake_case)]
+            fn process_array<T>(
+                &mut self,
+                value: &mut Array<T>,
+                meta: &mut Meta,
+                state: &ProcessingState<'_>,
+            ) -> ProcessingResult
+            where
+                T: ProcessValue,
+            {
+                let ($(ref mut $name,)+) = *self;
+                let mut action = Ok(());
+                $(
+                    if !halts(&action) {
+                        action = combine(action, $name.process_array(value, meta, state));
+                    }
+                )+
+                action
+            }
+
+            #[allow(non_snake_case)]
+            fn process_object<T>(
+                &mut self,
+                value: &mut Object<T>,
+                meta: &mut Meta,
+                state: &ProcessingState<'_>,
+            ) -> ProcessingResult
+            where
+                T: ProcessValue,
+            {
+                let ($(ref mut $name,)+) = *self;
+                let mut action = Ok(());
+                $(
+                    if !halts(&action) {
+                        action = combine(action, $name.process_object(value, meta, state));
+                    }
+                )+
+                action
+            }
+        }
+    };
+}
+
+impl_composite_processor!(A B);
+impl_composite_processor!(A B C);
+impl_composite_processor!(A B C D);
@@ -1,5 +1,7 @@
+use futures::future::{self, Future};
+
 use crate::processor::{ProcessValue, ProcessingState, Processor};
-use crate::types::{Annotated, ProcessingResult};
+use crate::types::{Annotated, Meta, ProcessingAction, ProcessingResult, Value};
 
 /// Processes the value using the given processor.
 #[inline]
@@ -13,12 +15,373 @@ where
     P: Processor,
 {
     let action = processor.before_process(annotated.0.as_ref(), &mut annotated.1, state);
-    annotated.apply(|_, _| action)?;
 
-    annotated.apply(|value, meta| ProcessValue::process_value(value, meta, processor, state))?;
+    // `SkipChildren` is non-destructive: the processor has already decided this subtree needs no
+    // further work, so it should neither delete the value nor count as an error, but recursing
+    // into children would just redo that work. Swap it for `Ok(())` before it reaches `apply` and
+    // remember to skip the recursive step below.
+    let skip_children = matches!(action, Err(ProcessingAction::SkipChildren));
+    annotated.apply(|_, _| if skip_children { Ok(()) } else { action })?;
+
+    if !skip_children {
+        annotated.apply(|value, meta| ProcessValue::process_value(value, meta, processor, state))?;
+    }
 
     let action = processor.after_process(annotated.0.as_ref(), &mut annotated.1, state);
     annotated.apply(|_, _| action)?;
 
     Ok(())
 }
+
+/// Processes an `Annotated<Value>` tree like [`process_value`], but drives the traversal from an
+/// explicit heap-allocated work stack instead of the native call stack.
+///
+/// Structured protocol types (`Event` and friends) recurse a bounded, schema-defined number of
+/// times, so blowing the stack there isn't a realistic concern. Free-form data -- `extra`, `tags`,
+/// breadcrumb `data`, context fields users control -- bottoms out in [`Value`], whose `Array` and
+/// `Object` variants can nest arbitrarily deeply. A maliciously or accidentally deep payload there
+/// can overflow the stack before any depth limit in a processor gets a chance to fire. This driver
+/// walks that specific recursion iteratively: each entry on `stack` is a frame for one `Value`
+/// node together with the phase of work still owed to it (`Before`, `Children`, or `After`),
+/// mirroring the ordering [`process_value`] gives for free. The traversal uses heap memory instead
+/// of the native stack. All of a node's children are pushed at once, so the heap use grows with the
+/// depth plus the not-yet-visited siblings of every node still open; it is not bounded by the depth
+/// alone.
+pub fn process_value_iterative<P>(
+    annotated: &mut Annotated<Value>,
+    processor: &mut P,
+    state: &ProcessingState<'_>,
+) -> ProcessingResult
+where
+    P: Processor,
+{
+    enum Phase {
+        Before,
+        Children,
+        After,
+    }
+
+    struct Frame {
+        annotated: *mut Annotated<Value>,
+        // Index into `ancestors`: the `ProcessingState` this node was entered with.
+        state_idx: usize,
+        phase: Phase,
+    }
+
+    // `ancestors` holds one boxed `ProcessingState` per node that has been pushed onto `work` and
+    // has not yet finished its `After` phase: the nodes currently "open", plus the children pushed
+    // together with them that are still waiting for their `Before`. A child's state is created by
+    // entering its parent's boxed state, so the chain of `ProcessingState` parent references is always valid:
+    // a node's `ancestors` entry is only ever popped once its `After` phase runs, which -- thanks
+    // to `work` being a single LIFO stack -- cannot happen until every descendant frame pushed
+    // while it was open has itself been fully processed and popped its own entry first.
+    let mut ancestors: Vec<Box<ProcessingState<'_>>> = vec![Box::new(state.clone())];
+    let mut work = vec![Frame {
+        annotated: annotated as *mut _,
+        state_idx: 0,
+        phase: Phase::Before,
+    }];
+
+    while let Some(frame) = work.pop() {
+        // SAFETY: `frame.annotated` always points at a node owned by a live `Value::Array` or
+        // `Value::Object` further down `work`/`ancestors`, or at the caller's root `annotated`.
+        // Nodes are never relocated or freed while a frame referencing them is outstanding, and
+        // no two live frames ever point at the same node, so this is a unique, valid borrow.
+        let annotated = unsafe { &mut *frame.annotated };
+        // SAFETY: `frame.state_idx` names an `ancestors` entry that is only removed once this
+        // node's `After` phase has run (see the comment on `ancestors` above), which has not
+        // happened yet for any frame currently being popped.
+        let node_state: &ProcessingState<'_> = unsafe {
+            &*(&*ancestors[frame.state_idx] as *const ProcessingState<'_>)
+        };
+
+        match frame.phase {
+            Phase::Before => {
+                let action = processor.before_process(annotated.0.as_ref(), &mut annotated.1, node_state);
+                let skip_children = matches!(action, Err(ProcessingAction::SkipChildren));
+                annotated.apply(|_, _| if skip_children { Ok(()) } else { action })?;
+
+                // Unlike the early-`continue` this replaced, `After` always runs -- even once a
+                // `DeleteValue*` action has cleared `annotated.0` above -- exactly like the
+                // recursive driver, which calls `after_process` unconditionally regardless of
+                // whether the value survived its own `before_process`.
+                work.push(Frame {
+                    annotated: frame.annotated,
+                    state_idx: frame.state_idx,
+                    phase: Phase::After,
+                });
+
+                // `SkipChildren` keeps the value but skips straight to `After`, same as the
+                // recursive driver. A deleted node has no children to visit either.
+                if !skip_children && annotated.0.is_some() {
+                    work.push(Frame {
+                        annotated: frame.annotated,
+                        state_idx: frame.state_idx,
+                        phase: Phase::Children,
+                    });
+                }
+            }
+            Phase::Children => {
+                // Dispatch leaf values through their typed hook, the same way `ProcessValue`'s
+                // (generated, not part of this snapshot) `process_value` impl would -- this is
+                // what `process_value` gets for free via that impl and this driver has to do
+                // explicitly since it never calls it. `Array`/`Object` are deliberately left to
+                // the frame-pushing below instead of going through `process_array`/`process_object`:
+                // those hooks call `process_child_values`, which recurses natively, and routing
+                // through them here would reintroduce the stack growth this driver exists to avoid.
+                annotated.apply(|value, meta| match value {
+                    Value::String(v) => processor.process_string(v, meta, node_state),
+                    Value::U64(v) => processor.process_u64(v, meta, node_state),
+                    Value::I64(v) => processor.process_i64(v, meta, node_state),
+                    Value::F64(v) => processor.process_f64(v, meta, node_state),
+                    _ => Ok(()),
+                })?;
+
+                // Push children in reverse so they pop off `work` (and are therefore visited) in
+                // their original order. Each child gets its own `ancestors` entry so nested
+                // children further down can in turn enter it.
+                match annotated.0.as_mut() {
+                    Some(Value::Array(array)) => {
+                        for (index, child) in array.iter_mut().enumerate().rev() {
+                            ancestors.push(Box::new(node_state.enter_index(index, None, false)));
+                            work.push(Frame {
+                                annotated: child as *mut _,
+                                state_idx: ancestors.len() - 1,
+                                phase: Phase::Before,
+                            });
+                        }
+                    }
+                    Some(Value::Object(object)) => {
+                        for (key, child) in object.iter_mut().rev() {
+                            ancestors.push(Box::new(node_state.enter_borrowed(key.clone(), None, false)));
+                            work.push(Frame {
+                                annotated: child as *mut _,
+                                state_idx: ancestors.len() - 1,
+                                phase: Phase::Before,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Phase::After => {
+                let action = processor.after_process(annotated.0.as_ref(), &mut annotated.1, node_state);
+                annotated.apply(|_, _| action)?;
+
+                // This node's subtree is fully processed; nothing still on `work` can reference
+                // its `ancestors` entry, so it's always safe to drop the top of the stack here.
+                debug_assert_eq!(frame.state_idx, ancestors.len() - 1);
+                ancestors.pop();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A boxed future carrying a [`ProcessingResult`], returned by [`AsyncProcessor`] hooks.
+///
+/// The borrowed lifetime `'a` ties the future to the `value`/`meta`/`state` it was handed, the
+/// same way the hooks on [`Processor`] borrow them for the duration of a single call.
+pub type ProcessingFuture<'a> = Box<dyn Future<Item = (), Error = ProcessingAction> + 'a>;
+
+/// Async sibling of [`Processor`] for normalization steps that need to call out to external
+/// services per node -- IP-to-geo enrichment, organization-specific PII rule fetches, symbol
+/// lookups -- instead of precomputing everything up front or blocking the thread that started the
+/// traversal.
+///
+/// Default implementations resolve immediately with `Ok(())`, so implementors only override the
+/// hooks they actually need to run I/O in, exactly like [`Processor`].
+pub trait AsyncProcessor {
+    /// Runs before recursing into a value's children. See [`Processor::before_process`].
+    fn before_process<'a, T: ProcessValue>(
+        &'a mut self,
+        _value: Option<&'a T>,
+        _meta: &'a mut Meta,
+        _state: &'a ProcessingState<'a>,
+    ) -> ProcessingFuture<'a> {
+        Box::new(future::ok(()))
+    }
+
+    /// Runs after a value's children have been processed. See [`Processor::after_process`].
+    fn after_process<'a, T: ProcessValue>(
+        &'a mut self,
+        _value: Option<&'a T>,
+        _meta: &'a mut Meta,
+        _state: &'a ProcessingState<'a>,
+    ) -> ProcessingFuture<'a> {
+        Box::new(future::ok(()))
+    }
+}
+
+/// One step of work still owed to a node in [`process_value_async`]'s traversal: identical in
+/// spirit to [`process_value_iterative`]'s `Phase`, except `Before`/`After` additionally carry the
+/// [`AsyncProcessor`] future they're waiting on, since those two phases are the only ones that
+/// cross an await point.
+enum AsyncPhase<'a> {
+    Before,
+    AwaitBefore(ProcessingFuture<'a>),
+    Children,
+    After,
+    AwaitAfter(ProcessingFuture<'a>),
+}
+
+struct AsyncFrame<'a> {
+    annotated: *mut Annotated<Value>,
+    state_idx: usize,
+    phase: AsyncPhase<'a>,
+}
+
+/// Drives [`process_value_async`]'s traversal by hand instead of via combinators, so each node's
+/// `ancestors` entry and pending hook future can live across multiple `poll` calls without forcing
+/// everything upstream to be `'static`. Structurally this is [`process_value_iterative`]'s
+/// `Frame`/`ancestors` work stack, with a `poll` loop standing in for the `while let` loop and an
+/// `AsyncPhase::Await*` variant standing in for a blocking hook call.
+struct AsyncTraversal<'a, P> {
+    processor: *mut P,
+    ancestors: Vec<Box<ProcessingState<'a>>>,
+    work: Vec<AsyncFrame<'a>>,
+}
+
+impl<'a, P: AsyncProcessor> Future for AsyncTraversal<'a, P> {
+    type Item = ();
+    type Error = ProcessingAction;
+
+    fn poll(&mut self) -> futures::Poll<(), ProcessingAction> {
+        loop {
+            let mut frame = match self.work.pop() {
+                Some(frame) => frame,
+                None => return Ok(futures::Async::Ready(())),
+            };
+
+            // SAFETY: see `process_value_iterative`'s identical invariants -- a node is only ever
+            // referenced by one live frame, and an `ancestors` entry is only popped once its
+            // node's `After` phase has fully run. The whole traversal, including every pointer
+            // derived here, is bounded by the `'a` that `process_value_async` borrowed
+            // `annotated`/`processor`/`state` for, since that's the lifetime this struct and the
+            // `ProcessingFuture<'a>` it's boxed into both carry.
+            let node_annotated = unsafe { &mut *frame.annotated };
+            let node_processor = unsafe { &mut *self.processor };
+            let node_state: &'a ProcessingState<'a> =
+                unsafe { &*(&*self.ancestors[frame.state_idx] as *const ProcessingState<'_>) };
+
+            match frame.phase {
+                AsyncPhase::Before => {
+                    let fut = node_processor.before_process::<Value>(
+                        node_annotated.0.as_ref(),
+                        &mut node_annotated.1,
+                        node_state,
+                    );
+                    frame.phase = AsyncPhase::AwaitBefore(fut);
+                    self.work.push(frame);
+                }
+                AsyncPhase::AwaitBefore(mut fut) => {
+                    let action = match fut.poll() {
+                        Ok(futures::Async::NotReady) => {
+                            frame.phase = AsyncPhase::AwaitBefore(fut);
+                            self.work.push(frame);
+                            return Ok(futures::Async::NotReady);
+                        }
+                        Ok(futures::Async::Ready(())) => Ok(()),
+                        Err(action) => Err(action),
+                    };
+
+                    let skip_children = matches!(action, Err(ProcessingAction::SkipChildren));
+                    node_annotated.apply(|_, _| if skip_children { Ok(()) } else { action })?;
+
+                    self.work.push(AsyncFrame {
+                        annotated: frame.annotated,
+                        state_idx: frame.state_idx,
+                        phase: AsyncPhase::After,
+                    });
+                    if !skip_children && node_annotated.0.is_some() {
+                        self.work.push(AsyncFrame {
+                            annotated: frame.annotated,
+                            state_idx: frame.state_idx,
+                            phase: AsyncPhase::Children,
+                        });
+                    }
+                }
+                AsyncPhase::Children => {
+                    match node_annotated.0.as_mut() {
+                        Some(Value::Array(array)) => {
+                            for (index, child) in array.iter_mut().enumerate().rev() {
+                                self.ancestors.push(Box::new(node_state.enter_index(index, None, false)));
+                                self.work.push(AsyncFrame {
+                                    annotated: child as *mut _,
+                                    state_idx: self.ancestors.len() - 1,
+                                    phase: AsyncPhase::Before,
+                                });
+                            }
+                        }
+                        Some(Value::Object(object)) => {
+                            for (key, child) in object.iter_mut().rev() {
+                                self.ancestors
+                                    .push(Box::new(node_state.enter_borrowed(key.clone(), None, false)));
+                                self.work.push(AsyncFrame {
+                                    annotated: child as *mut _,
+                                    state_idx: self.ancestors.len() - 1,
+                                    phase: AsyncPhase::Before,
+                                });
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                AsyncPhase::After => {
+                    let fut = node_processor.after_process::<Value>(
+                        node_annotated.0.as_ref(),
+                        &mut node_annotated.1,
+                        node_state,
+                    );
+                    frame.phase = AsyncPhase::AwaitAfter(fut);
+                    self.work.push(frame);
+                }
+                AsyncPhase::AwaitAfter(mut fut) => {
+                    let action = match fut.poll() {
+                        Ok(futures::Async::NotReady) => {
+                            frame.phase = AsyncPhase::AwaitAfter(fut);
+                            self.work.push(frame);
+                            return Ok(futures::Async::NotReady);
+                        }
+                        Ok(futures::Async::Ready(())) => Ok(()),
+                        Err(action) => Err(action),
+                    };
+
+                    node_annotated.apply(|_, _| action)?;
+
+                    debug_assert_eq!(frame.state_idx, self.ancestors.len() - 1);
+                    self.ancestors.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Processes an `Annotated<Value>` tree using the given [`AsyncProcessor`], the async counterpart
+/// of [`process_value_iterative`].
+///
+/// Like its sync counterpart, this is scoped to `Value` rather than a generic `ProcessValue`,
+/// since there's no generated async counterpart to `ProcessValue::process_value` to recurse
+/// through for typed protocol structs. Each [`AsyncProcessor`] hook's future is polled in place as
+/// part of this future's own `poll`, rather than blocked on with `.wait()` -- so a single
+/// traversal genuinely suspends while awaiting per-node enrichment (geo lookups, remote PII rule
+/// fetches) instead of tying up the thread that started it.
+pub fn process_value_async<'a, P>(
+    annotated: &'a mut Annotated<Value>,
+    processor: &'a mut P,
+    state: &'a ProcessingState<'a>,
+) -> ProcessingFuture<'a>
+where
+    P: AsyncProcessor,
+{
+    Box::new(AsyncTraversal {
+        processor: processor as *mut P,
+        ancestors: vec![Box::new(state.clone())],
+        work: vec![AsyncFrame {
+            annotated: annotated as *mut _,
+            state_idx: 0,
+            phase: AsyncPhase::Before,
+        }],
+    })
+}
@@ -0,0 +1,197 @@
+use smartstring::alias::String;
+
+use crate::processor::{ProcessValue, ProcessingState, Processor};
+use crate::types::{Array, Meta, Object, ProcessingAction, ProcessingResult, Remark};
+
+/// A single field whose [`Meta`], value, or processing outcome changed while a
+/// [`RecordingProcessor`] ran.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MutationRecord {
+    /// The path of the field that was touched, as given by `ProcessingState::path`.
+    pub path: std::string::String,
+    /// The remarks attached to the field's `Meta` before `before_process` ran.
+    pub old_remarks: Vec<Remark>,
+    /// The remarks attached to the field's `Meta` after `after_process` ran.
+    pub new_remarks: Vec<Remark>,
+    /// The field's value before `before_process` ran, formatted with `{:?}` since mutations can
+    /// change a field's type-specific representation in ways a generic diff can't compare
+    /// structurally.
+    pub old_value: std::string::String,
+    /// The field's value after `after_process` ran, formatted the same way as `old_value`.
+    pub new_value: std::string::String,
+    /// The `ProcessingAction` returned by `before_process` or `after_process`, if either one
+    /// returned one -- `before_process`'s wins, since an action from it (e.g. `SkipChildren`)
+    /// already decided whether `after_process` even saw the original value.
+    pub action: Option<ProcessingAction>,
+}
+
+/// Wraps an inner [`Processor`] and records a structured audit log of every field it touched.
+///
+/// `ProcessingState` already carries each node's full path during a traversal, so this wrapper
+/// snapshots a field's `Meta` remarks and value in `before_process` and diffs both again in
+/// `after_process`, also recording whichever `ProcessingAction` the inner processor returned,
+/// without requiring any changes to the wrapped processor. Use [`RecordingProcessor::log`] after a
+/// call to `process_value` to see exactly which fields were rewritten, scrubbed, or dropped and by
+/// what, which is otherwise hard to reconstruct once several processors have run over the same
+/// event.
+pub struct RecordingProcessor<P> {
+    inner: P,
+    pending: Vec<PendingMutation>,
+    log: Vec<MutationRecord>,
+}
+
+/// The snapshot taken in `before_process`, held until the matching `after_process` call pops it
+/// back off and diffs against the post-traversal state.
+struct PendingMutation {
+    path: std::string::String,
+    old_remarks: Vec<Remark>,
+    old_value: std::string::String,
+    /// The action `before_process` returned, if it was an `Err`. Captured here rather than
+    /// recomputed in `after_process`, since `before_process`'s action is what actually decided
+    /// whether this node's children (and therefore its `after_process` call) saw the original
+    /// value at all.
+    before_action: Option<ProcessingAction>,
+}
+
+impl<P> RecordingProcessor<P> {
+    /// Creates a new recording wrapper around `inner`.
+    pub fn new(inner: P) -> Self {
+        RecordingProcessor {
+            inner,
+            pending: Vec::new(),
+            log: Vec::new(),
+        }
+    }
+
+    /// Returns the mutations recorded so far, in the order they were observed.
+    pub fn log(&self) -> &[MutationRecord] {
+        &self.log
+    }
+
+    /// Consumes the wrapper, returning the recorded mutation log.
+    pub fn into_log(self) -> Vec<MutationRecord> {
+        self.log
+    }
+
+    /// Consumes the wrapper, returning the inner processor.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: Processor> Processor for RecordingProcessor<P> {
+    // `T: ProcessValue` is assumed to also carry a `Debug` bound here, the same as every
+    // concretely `ProcessValue`-deriving type seen in this codebase (`BrowserContext`,
+    // `DeviceContext`, `OsContext`, `GpuContext`, ...) -- needed to snapshot a field's value as
+    // text without requiring `RecordingProcessor` to know each type's own diffing logic.
+    fn before_process<T: ProcessValue>(
+        &mut self,
+        value: Option<&T>,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        let old_remarks = meta.remarks().to_vec();
+        let old_value = format!("{:?}", value);
+        let result = self.inner.before_process(value, meta, state);
+
+        self.pending.push(PendingMutation {
+            path: state.path().to_string(),
+            old_remarks,
+            old_value,
+            before_action: result.as_ref().err().cloned(),
+        });
+
+        result
+    }
+
+    fn after_process<T: ProcessValue>(
+        &mut self,
+        value: Option<&T>,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        let result = self.inner.after_process(value, meta, state);
+
+        let pending = self
+            .pending
+            .pop()
+            .expect("before_process/after_process calls must nest like a call stack");
+
+        let new_remarks = meta.remarks().to_vec();
+        let new_value = format!("{:?}", value);
+        let action = pending.before_action.or_else(|| result.as_ref().err().cloned());
+
+        if pending.old_remarks != new_remarks || pending.old_value != new_value || action.is_some() {
+            self.log.push(MutationRecord {
+                path: pending.path,
+                old_remarks: pending.old_remarks,
+                new_remarks,
+                old_value: pending.old_value,
+                new_value,
+                action,
+            });
+        }
+
+        result
+    }
+
+    fn process_string(
+        &mut self,
+        value: &mut String,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        self.inner.process_string(value, meta, state)
+    }
+
+    fn process_array<T>(
+        &mut self,
+        value: &mut Array<T>,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult
+    where
+        T: ProcessValue,
+    {
+        self.inner.process_array(value, meta, state)
+    }
+
+    fn process_object<T>(
+        &mut self,
+        value: &mut Object<T>,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult
+    where
+        T: ProcessValue,
+    {
+        self.inner.process_object(value, meta, state)
+    }
+
+    fn process_u64(
+        &mut self,
+        value: &mut u64,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        self.inner.process_u64(value, meta, state)
+    }
+
+    fn process_i64(
+        &mut self,
+        value: &mut i64,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        self.inner.process_i64(value, meta, state)
+    }
+
+    fn process_f64(
+        &mut self,
+        value: &mut f64,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        self.inner.process_f64(value, meta, state)
+    }
+}
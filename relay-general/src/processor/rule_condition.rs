@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Annotated, Value};
+
+/// Resolves a dotted field path (e.g. `"contexts.runtime.name"`) against some underlying data.
+///
+/// Implementations return `None` for any path that doesn't resolve to a value -- a missing
+/// context, an absent field, an out-of-range index -- so leaf [`RuleCondition`] operators can
+/// treat "not there" and "doesn't match" identically instead of needing a separate error path.
+pub trait Getter {
+    /// Looks up `path`, returning `None` if any segment along the way is missing.
+    fn get_value(&self, path: &str) -> Option<&Value>;
+}
+
+impl Getter for Annotated<Value> {
+    fn get_value(&self, path: &str) -> Option<&Value> {
+        let mut value = self.value()?;
+        for segment in path.split('.') {
+            value = match value {
+                Value::Object(object) => object.get(segment)?.value()?,
+                Value::Array(array) => array.get(segment.parse::<usize>().ok()?)?.value()?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
+/// Compares a field against a fixed value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldCondition {
+    /// The dotted path to the field being compared, e.g. `"contexts.runtime.name"`.
+    pub name: String,
+    /// The value to compare the field against.
+    pub value: Value,
+}
+
+/// Matches a field against a glob pattern (e.g. `"contexts.device.model"` against `"iPhone*"`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GlobCondition {
+    /// The dotted path to the field being matched.
+    pub name: String,
+    /// The glob pattern the field's string value must match.
+    pub pattern: String,
+}
+
+/// Evaluates an inner condition against every element reachable through a field path that
+/// resolves to an array.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IterCondition {
+    /// The dotted path to the array-valued field to iterate, e.g. `"contexts"`.
+    pub name: String,
+    /// The condition evaluated against each element, relative to that element.
+    pub inner: Box<RuleCondition>,
+}
+
+/// A declarative condition tree, evaluated against a [`Getter`] to decide whether a processor
+/// step applies.
+///
+/// Leaf operators (`eq`, `gt`, `gte`, `glob`) resolve a dotted field path and compare it; `and`,
+/// `or`, and `not` combine sub-conditions the usual way. `any` and `all` additionally iterate a
+/// field path that resolves to an array, evaluating the inner condition against each element
+/// (relative to that element, so paths inside `inner` are resolved starting there) -- `any` is
+/// true as soon as one element matches, `all` is true only if every element does, and is
+/// vacuously true for an empty array.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum RuleCondition {
+    /// Field equals a fixed value.
+    Eq(FieldCondition),
+    /// Field is greater than a fixed value.
+    Gt(FieldCondition),
+    /// Field is greater than or equal to a fixed value.
+    Gte(FieldCondition),
+    /// Field matches a glob pattern.
+    Glob(GlobCondition),
+    /// All sub-conditions match.
+    And(Vec<RuleCondition>),
+    /// At least one sub-condition matches.
+    Or(Vec<RuleCondition>),
+    /// The sub-condition does not match.
+    Not(Box<RuleCondition>),
+    /// The inner condition matches at least one element of an array field.
+    Any(IterCondition),
+    /// The inner condition matches every element of an array field.
+    All(IterCondition),
+}
+
+impl RuleCondition {
+    /// Evaluates this condition against `getter`.
+    pub fn matches<G: Getter>(&self, getter: &G) -> bool {
+        match self {
+            RuleCondition::Eq(cond) => getter.get_value(&cond.name) == Some(&cond.value),
+            RuleCondition::Gt(cond) => compare(getter, cond, |ordering| ordering.is_gt()),
+            RuleCondition::Gte(cond) => compare(getter, cond, |ordering| ordering.is_ge()),
+            RuleCondition::Glob(cond) => match getter.get_value(&cond.name) {
+                Some(Value::String(value)) => glob_match(value, &cond.pattern),
+                _ => false,
+            },
+            RuleCondition::And(conds) => conds.iter().all(|cond| cond.matches(getter)),
+            RuleCondition::Or(conds) => conds.iter().any(|cond| cond.matches(getter)),
+            RuleCondition::Not(cond) => !cond.matches(getter),
+            RuleCondition::Any(cond) => match array_elements(getter, &cond.name) {
+                Some(elements) => elements.iter().any(|element| cond.inner.matches(element)),
+                None => false,
+            },
+            RuleCondition::All(cond) => match array_elements(getter, &cond.name) {
+                Some(elements) => elements.iter().all(|element| cond.inner.matches(element)),
+                None => false,
+            },
+        }
+    }
+}
+
+fn compare<G: Getter>(
+    getter: &G,
+    cond: &FieldCondition,
+    accept: impl Fn(std::cmp::Ordering) -> bool,
+) -> bool {
+    match (getter.get_value(&cond.name), &cond.value) {
+        (Some(Value::F64(a)), Value::F64(b)) => a.partial_cmp(b).map_or(false, accept),
+        (Some(Value::I64(a)), Value::I64(b)) => accept(a.cmp(b)),
+        (Some(Value::U64(a)), Value::U64(b)) => accept(a.cmp(b)),
+        (Some(Value::String(a)), Value::String(b)) => accept(a.cmp(b)),
+        _ => false,
+    }
+}
+
+fn array_elements<'a, G: Getter>(getter: &'a G, path: &str) -> Option<&'a [Annotated<Value>]> {
+    match getter.get_value(path) {
+        Some(Value::Array(array)) => Some(array),
+        _ => None,
+    }
+}
+
+fn glob_match(value: &str, pattern: &str) -> bool {
+    let regex_str = format!(
+        "(?i)^{}$",
+        regex::escape(pattern).replace(r"\*", ".*").replace(r"\?", ".")
+    );
+    regex::Regex::new(&regex_str).map_or(false, |re| re.is_match(value))
+}
@@ -1,9 +1,42 @@
+use lazy_static::lazy_static;
+use regex::Regex;
 use smartstring::alias::String;
+use unicode_segmentation::UnicodeSegmentation;
+
 use crate::processor::{ProcessValue, ProcessingState, Processor};
 use crate::types::{
     Array, Empty, Error, ErrorKind, Meta, Object, ProcessingAction, ProcessingResult,
 };
 
+/// A named format a string field can be required to match, selected via
+/// `#[metastructure(format = "...")]`.
+///
+/// This assumes `FieldAttrs` grows a `format: Option<ValueFormat>` field alongside
+/// `min_length`/`max_length`/`min`/`max` below. `FieldAttrs` and the rest of `crate::processor`'s
+/// core types aren't part of this snapshot, so the enum stays local to `schema.rs`, its only
+/// consumer for now.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueFormat {
+    /// An RFC 5322-ish email address, replacing the ad-hoc checks protocol types used to write by
+    /// hand.
+    Email,
+    /// A URL with an explicit scheme, e.g. `https://sentry.io`.
+    Url,
+    /// A hyphenated UUID, e.g. `4bf92f35-77b3-4da6-a3ce-929d0e0e4736`.
+    Uuid,
+}
+
+lazy_static! {
+    static ref EMAIL_REGEX: Regex = Regex::new(concat!(
+        r"(?i)^[a-z0-9!#$%&'*+/=?^_`{|}~-]+(?:\.[a-z0-9!#$%&'*+/=?^_`{|}~-]+)*",
+        r"@(?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+[a-z0-9](?:[a-z0-9-]*[a-z0-9])?$",
+    )).unwrap();
+    static ref URL_REGEX: Regex = Regex::new(r"(?i)^[a-z][a-z0-9+.-]*://\S+$").unwrap();
+    static ref UUID_REGEX: Regex = Regex::new(
+        r"(?i)^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$"
+    ).unwrap();
+}
+
 pub struct SchemaProcessor;
 
 impl Processor for SchemaProcessor {
@@ -16,6 +49,8 @@ impl Processor for SchemaProcessor {
         value_trim_whitespace(value, meta, &state)?;
         verify_value_nonempty(value, meta, &state)?;
         verify_value_pattern(value, meta, &state)?;
+        verify_value_format(value, meta, &state)?;
+        verify_string_length(value, meta, &state)?;
         Ok(())
     }
 
@@ -30,6 +65,7 @@ impl Processor for SchemaProcessor {
     {
         value.process_child_values(self, state)?;
         verify_value_nonempty(value, meta, state)?;
+        verify_length(value.len(), meta, state)?;
         Ok(())
     }
 
@@ -44,9 +80,37 @@ impl Processor for SchemaProcessor {
     {
         value.process_child_values(self, state)?;
         verify_value_nonempty(value, meta, state)?;
+        verify_length(value.len(), meta, state)?;
         Ok(())
     }
 
+    fn process_u64(
+        &mut self,
+        value: &mut u64,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        verify_numeric_range(*value as f64, meta, state)
+    }
+
+    fn process_i64(
+        &mut self,
+        value: &mut i64,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        verify_numeric_range(*value as f64, meta, state)
+    }
+
+    fn process_f64(
+        &mut self,
+        value: &mut f64,
+        meta: &mut Meta,
+        state: &ProcessingState<'_>,
+    ) -> ProcessingResult {
+        verify_numeric_range(*value, meta, state)
+    }
+
     fn before_process<T: ProcessValue>(
         &mut self,
         value: Option<&T>,
@@ -106,6 +170,93 @@ fn verify_value_pattern(
     Ok(())
 }
 
+fn verify_value_format(
+    value: &mut String,
+    meta: &mut Meta,
+    state: &ProcessingState<'_>,
+) -> ProcessingResult {
+    let (regex, name) = match state.attrs().format {
+        Some(ValueFormat::Email) => (&*EMAIL_REGEX, "email address"),
+        Some(ValueFormat::Url) => (&*URL_REGEX, "URL"),
+        Some(ValueFormat::Uuid) => (&*UUID_REGEX, "UUID"),
+        None => return Ok(()),
+    };
+
+    if !regex.is_match(value) {
+        meta.add_error(Error::invalid(format!("not a valid {}", name)));
+        return Err(ProcessingAction::DeleteValueSoft);
+    }
+
+    Ok(())
+}
+
+fn verify_string_length(
+    value: &mut String,
+    meta: &mut Meta,
+    state: &ProcessingState<'_>,
+) -> ProcessingResult {
+    verify_length(value.graphemes(true).count(), meta, state)
+}
+
+/// Checks a string's grapheme count or an array/object's element count against
+/// `min_length`/`max_length`, whichever attribute the caller's value kind declares.
+fn verify_length(len: usize, meta: &mut Meta, state: &ProcessingState<'_>) -> ProcessingResult {
+    let attrs = state.attrs();
+
+    if let Some(min_length) = attrs.min_length {
+        if len < min_length {
+            meta.add_error(Error::invalid(format!(
+                "value is too short, expected at least {} but got {}",
+                min_length, len
+            )));
+            return Err(ProcessingAction::DeleteValueSoft);
+        }
+    }
+
+    if let Some(max_length) = attrs.max_length {
+        if len > max_length {
+            meta.add_error(Error::invalid(format!(
+                "value is too long, expected at most {} but got {}",
+                max_length, len
+            )));
+            return Err(ProcessingAction::DeleteValueSoft);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks a numeric value against `min`/`max`, shared by `process_u64`/`process_i64`/`process_f64`.
+fn verify_numeric_range(
+    value: f64,
+    meta: &mut Meta,
+    state: &ProcessingState<'_>,
+) -> ProcessingResult {
+    let attrs = state.attrs();
+
+    if let Some(min) = attrs.min {
+        if value < min {
+            meta.add_error(Error::invalid(format!(
+                "value {} is smaller than the minimum allowed value {}",
+                value, min
+            )));
+            return Err(ProcessingAction::DeleteValueSoft);
+        }
+    }
+
+    if let Some(max) = attrs.max {
+        if value > max {
+            meta.add_error(Error::invalid(format!(
+                "value {} is larger than the maximum allowed value {}",
+                value, max
+            )));
+            return Err(ProcessingAction::DeleteValueSoft);
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::SchemaProcessor;
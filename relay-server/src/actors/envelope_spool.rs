@@ -0,0 +1,271 @@
+//! A crash-safe, on-disk overflow queue for `Envelope`s that are waiting on a `ProjectState`
+//! while `ProjectCache`'s `FetchProjectState` handler works its way through the local -> redis ->
+//! upstream chain. Today those envelopes only live in actor mailboxes and in-memory buffers, so a
+//! Relay restart or a stall on the upstream cache silently drops them.
+//!
+//! Once a project's in-memory buffer crosses `Config::spool_buffer_watermark_high`, newly arriving
+//! envelopes for that project are appended to its on-disk segment file via `SpoolEnvelope` instead
+//! of held in memory; once the buffer has drained back below
+//! `Config::spool_buffer_watermark_low`, buffering switches back to in-memory. Once the project's
+//! state arrives, `UnspoolEnvelopes` reads the segment file back and deletes it, handing every
+//! envelope it held back to the caller for replay.
+//!
+//! This module isn't declared from `actors`'s module root in this snapshot (that file isn't part
+//! of it either) -- whoever lands it should add `mod envelope_spool;` there alongside the existing
+//! `mod project_cache;`, start `EnvelopeSpool` alongside `ProjectCache`, and wire the watermark
+//! checks described above into wherever envelopes are currently buffered awaiting project state.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use ::actix::prelude::*;
+use chrono::{DateTime, Utc};
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+
+use relay_common::ProjectId;
+use relay_config::Config;
+use relay_general::protocol::EventId;
+
+use crate::envelope::Envelope;
+
+#[derive(Fail, Debug)]
+pub enum SpoolError {
+    #[fail(display = "envelope spool is not configured")]
+    Disabled,
+
+    #[fail(display = "the on-disk envelope spool is full")]
+    DiskFull,
+
+    #[fail(display = "failed to read a spooled envelope")]
+    Read(#[cause] io::Error),
+
+    #[fail(display = "failed to write a spooled envelope")]
+    Write(#[cause] io::Error),
+
+    #[fail(display = "failed to (de)serialize a spooled envelope")]
+    Serialize(#[cause] serde_json::Error),
+}
+
+/// One envelope as written to a project's on-disk segment file: metadata to account for and log
+/// the record without deserializing the whole envelope, plus the envelope's serialized body.
+#[derive(Serialize, Deserialize)]
+struct SpoolRecord {
+    project_id: ProjectId,
+    event_id: Option<EventId>,
+    received_at: DateTime<Utc>,
+    body: Vec<u8>,
+}
+
+impl SpoolRecord {
+    fn new(project_id: ProjectId, envelope: &Envelope) -> Result<Self, SpoolError> {
+        Ok(SpoolRecord {
+            project_id,
+            event_id: envelope.event_id(),
+            received_at: Utc::now(),
+            body: serde_json::to_vec(envelope).map_err(SpoolError::Serialize)?,
+        })
+    }
+
+    fn into_envelope(self) -> Result<Envelope, SpoolError> {
+        serde_json::from_slice(&self.body).map_err(SpoolError::Serialize)
+    }
+}
+
+fn segment_path(dir: &Path, project_id: ProjectId) -> PathBuf {
+    dir.join(format!("{}.envelopes", project_id))
+}
+
+/// Appends `record`'s length-prefixed, serialized form to `project_id`'s segment file under `dir`,
+/// creating `dir` and the segment file if either doesn't exist yet.
+fn append_record(
+    dir: &Path,
+    project_id: ProjectId,
+    record: &SpoolRecord,
+) -> Result<(), SpoolError> {
+    fs::create_dir_all(dir).map_err(SpoolError::Write)?;
+
+    let body = serde_json::to_vec(record).map_err(SpoolError::Serialize)?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(segment_path(dir, project_id))
+        .map_err(SpoolError::Write)?;
+
+    file.write_all(&(body.len() as u32).to_le_bytes())
+        .map_err(SpoolError::Write)?;
+    file.write_all(&body).map_err(SpoolError::Write)?;
+    Ok(())
+}
+
+/// Reads every length-prefixed record out of `project_id`'s segment file under `dir`, then removes
+/// the file -- an unspool is always a full drain, since every record it held is about to be
+/// replayed now that the project's state has arrived. Returns an empty `Vec` if there is no
+/// segment file for `project_id`, rather than treating that as an error.
+fn drain_segment(dir: &Path, project_id: ProjectId) -> Result<Vec<SpoolRecord>, SpoolError> {
+    let path = segment_path(dir, project_id);
+    let mut file = match fs::File::open(&path) {
+        Ok(file) => file,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(SpoolError::Read(err)),
+    };
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).map_err(SpoolError::Read)?;
+    drop(file);
+    fs::remove_file(&path).map_err(SpoolError::Read)?;
+
+    let mut records = Vec::new();
+    let mut offset = 0;
+    while offset + 4 <= data.len() {
+        let mut len_bytes = [0u8; 4];
+        len_bytes.copy_from_slice(&data[offset..offset + 4]);
+        let len = u32::from_le_bytes(len_bytes) as usize;
+        offset += 4;
+
+        if offset + len > data.len() {
+            // A truncated trailing record, e.g. from a crash mid-write. Everything written before
+            // it is still intact and has already been collected, so just stop here.
+            break;
+        }
+
+        let record = serde_json::from_slice(&data[offset..offset + len])
+            .map_err(SpoolError::Serialize)?;
+        records.push(record);
+        offset += len;
+    }
+
+    Ok(records)
+}
+
+/// Returns the combined size in bytes of every segment file directly under `dir`.
+fn disk_usage(dir: &Path) -> io::Result<u64> {
+    let mut total = 0;
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err),
+    };
+
+    for entry in entries {
+        total += entry?.metadata()?.len();
+    }
+    Ok(total)
+}
+
+/// Owns the on-disk envelope spool described at the module level: the overflow path for envelopes
+/// buffered past `Config::spool_buffer_watermark_high` while their project's state is fetched.
+pub struct EnvelopeSpool {
+    dir: Option<PathBuf>,
+    max_disk_size: u64,
+}
+
+impl EnvelopeSpool {
+    pub fn new(config: &Config) -> Self {
+        EnvelopeSpool {
+            dir: config.spool_path(),
+            max_disk_size: config.spool_max_disk_size() as u64,
+        }
+    }
+
+    /// Lists the projects with a segment file left over from a previous run. `started` only logs
+    /// how many there are; it triggers no `FetchProjectState` for them, so the envelopes left on
+    /// disk for a project are returned only once an `UnspoolEnvelopes` for that project arrives.
+    fn scan_existing_segments(&self, dir: &Path) -> io::Result<Vec<ProjectId>> {
+        let mut project_ids = Vec::new();
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(project_ids),
+            Err(err) => return Err(err),
+        };
+
+        for entry in entries {
+            let file_name = entry?.file_name();
+            let file_name = file_name.to_string_lossy();
+            if let Some(project_id) = file_name
+                .strip_suffix(".envelopes")
+                .and_then(|id| id.parse().ok())
+            {
+                project_ids.push(project_id);
+            }
+        }
+
+        Ok(project_ids)
+    }
+}
+
+impl Actor for EnvelopeSpool {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _context: &mut Self::Context) {
+        if let Some(dir) = self.dir.clone() {
+            match self.scan_existing_segments(&dir) {
+                Ok(project_ids) if !project_ids.is_empty() => log::info!(
+                    "envelope spool found {} project(s) with spooled envelopes from a previous run",
+                    project_ids.len()
+                ),
+                Ok(_) => (),
+                Err(error) => log::error!("failed to scan envelope spool directory: {}", error),
+            }
+        }
+
+        log::info!("envelope spool started");
+    }
+
+    fn stopped(&mut self, _ctx: &mut Self::Context) {
+        log::info!("envelope spool stopped");
+    }
+}
+
+/// Spools `envelope` to disk for `project_id`, to be replayed once that project's state arrives.
+pub struct SpoolEnvelope {
+    pub project_id: ProjectId,
+    pub envelope: Envelope,
+}
+
+impl Message for SpoolEnvelope {
+    type Result = Result<(), SpoolError>;
+}
+
+impl Handler<SpoolEnvelope> for EnvelopeSpool {
+    type Result = Result<(), SpoolError>;
+
+    fn handle(&mut self, message: SpoolEnvelope, _context: &mut Self::Context) -> Self::Result {
+        let dir = self.dir.as_ref().ok_or(SpoolError::Disabled)?;
+
+        if disk_usage(dir).map_err(SpoolError::Write)? >= self.max_disk_size {
+            return Err(SpoolError::DiskFull);
+        }
+
+        let record = SpoolRecord::new(message.project_id, &message.envelope)?;
+        append_record(dir, message.project_id, &record)
+    }
+}
+
+/// Reads back and removes every envelope previously spooled for `project_id`, to be replayed now
+/// that its `ProjectState` has arrived.
+pub struct UnspoolEnvelopes {
+    pub project_id: ProjectId,
+}
+
+impl Message for UnspoolEnvelopes {
+    type Result = Result<Vec<Envelope>, SpoolError>;
+}
+
+impl Handler<UnspoolEnvelopes> for EnvelopeSpool {
+    type Result = Result<Vec<Envelope>, SpoolError>;
+
+    fn handle(&mut self, message: UnspoolEnvelopes, _context: &mut Self::Context) -> Self::Result {
+        let dir = match &self.dir {
+            Some(dir) => dir,
+            None => return Ok(Vec::new()),
+        };
+
+        drain_segment(dir, message.project_id)?
+            .into_iter()
+            .map(SpoolRecord::into_envelope)
+            .collect()
+    }
+}
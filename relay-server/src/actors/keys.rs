@@ -1,4 +1,11 @@
 //! This actor caches known public keys.
+//!
+//! The batched fetch pipeline (`fetch_keys`/`schedule_fetch`/`get_or_fetch_info`) logs through
+//! `tracing` spans and structured fields rather than flat `log::debug!`/`log::error!` lines, so a
+//! `relay_id` or `attempt` can be filtered and joined across a batch's dispatch, upstream round
+//! trip, and per-relay resolution. This module and `utils::with_outcome` are the only ones in the
+//! crate using `tracing` so far; the rest still logs through the `log` facade, which `tracing` itself can also feed if the two
+//! end up coexisting during a broader migration.
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::mem;
@@ -58,6 +65,22 @@ impl RelayInfoState {
         }
     }
 
+    /// Returns `true` if this entry is still a valid cache hit, but old enough that it should be
+    /// refreshed in the background rather than served indefinitely until it expires.
+    ///
+    /// `DoesNotExist` entries are never refreshed early: they simply stop being a cache hit once
+    /// `cache_miss_expiry` passes, at which point `get_or_fetch_info` already refetches them.
+    fn needs_refresh(&self, config: &Config) -> bool {
+        match *self {
+            RelayInfoState::Exists { checked_at, .. } => {
+                let elapsed = checked_at.elapsed();
+                elapsed >= config.relay_cache_refresh_interval()
+                    && elapsed < config.relay_cache_expiry()
+            }
+            RelayInfoState::DoesNotExist { .. } => false,
+        }
+    }
+
     fn as_option(&self) -> Option<&RelayInfo> {
         match *self {
             RelayInfoState::Exists {
@@ -81,10 +104,41 @@ impl RelayInfoState {
     }
 }
 
+/// Indicates how a [`RelayInfo`] returned from [`RelayInfoCache::get_or_fetch_info`] was sourced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelayInfoFreshness {
+    /// Served from the cache within the soft refresh window.
+    Cached,
+    /// Served from the cache past the soft refresh window, while a background fetch to renew it
+    /// has been enqueued.
+    Revalidating,
+    /// Not served from the cache, which held nothing valid: fetched from upstream for this
+    /// request, or, when no credentials are configured, answered with no info and no fetch.
+    FreshlyFetched,
+}
+
+/// A cached [`RelayInfoState`] plus the time it was last looked up, used to pick an
+/// eviction victim once the cache is at capacity (see [`RelayInfoCache::evict_for_capacity`]).
+#[derive(Debug)]
+struct CacheEntry {
+    state: RelayInfoState,
+    last_accessed: Instant,
+}
+
+impl CacheEntry {
+    fn new(state: RelayInfoState) -> Self {
+        CacheEntry {
+            state,
+            last_accessed: Instant::now(),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct RelayInfoChannel {
     sender: oneshot::Sender<Option<RelayInfo>>,
     receiver: Shared<oneshot::Receiver<Option<RelayInfo>>>,
+    created_at: Instant,
 }
 
 impl RelayInfoChannel {
@@ -93,9 +147,17 @@ impl RelayInfoChannel {
         RelayInfoChannel {
             sender,
             receiver: receiver.shared(),
+            created_at: Instant::now(),
         }
     }
 
+    /// Returns `true` once this channel has been waiting longer than `relay_key_fetch_timeout`.
+    /// Dropping an expired channel (rather than calling `send`) drops its sender, which resolves
+    /// the receiver with a cancellation that `get_or_fetch_info` maps to `KeyError::FetchFailed`.
+    pub fn is_expired(&self, config: &Config) -> bool {
+        self.created_at.elapsed() >= config.relay_key_fetch_timeout()
+    }
+
     pub fn send(self, value: Option<RelayInfo>) -> Result<(), Option<RelayInfo>> {
         self.sender.send(value)
     }
@@ -109,7 +171,7 @@ pub struct RelayInfoCache {
     backoff: RetryBackoff,
     config: Arc<Config>,
     upstream: Addr<UpstreamRelay>,
-    relays: HashMap<RelayId, RelayInfoState>,
+    relays: HashMap<RelayId, CacheEntry>,
     relay_info_channels: HashMap<RelayId, RelayInfoChannel>,
 }
 
@@ -137,42 +199,118 @@ impl RelayInfoCache {
         utils::run_later(self.next_backoff(), Self::fetch_keys).spawn(context)
     }
 
+    /// Inserts `state` for `relay_id`, evicting another entry first if the cache is at capacity
+    /// and `relay_id` isn't already present (so an update to an existing entry never evicts).
+    fn insert_relay(&mut self, relay_id: RelayId, state: RelayInfoState) {
+        if !self.relays.contains_key(&relay_id) {
+            while self.relays.len() >= self.config.relay_cache_max_entries() {
+                if !self.evict_for_capacity() {
+                    break;
+                }
+            }
+        }
+
+        self.relays.insert(relay_id, CacheEntry::new(state));
+    }
+
+    /// Evicts one entry to make room: an expired entry if one exists, otherwise the
+    /// least-recently-accessed entry. Returns `false` if the cache is empty and nothing could be
+    /// evicted.
+    fn evict_for_capacity(&mut self) -> bool {
+        let config = &self.config;
+        let expired_id = self
+            .relays
+            .iter()
+            .find(|(_, entry)| !entry.state.is_valid_cache(config))
+            .map(|(id, _)| *id);
+
+        let victim = expired_id.or_else(|| {
+            self.relays
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_accessed)
+                .map(|(id, _)| *id)
+        });
+
+        match victim {
+            Some(id) => {
+                self.relays.remove(&id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops channels that have been waiting past `relay_key_fetch_timeout`, failing their
+    /// callers fast instead of leaving them to hang through further backoff/retry cycles.
+    fn expire_stale_channels(&self, channels: &mut HashMap<RelayId, RelayInfoChannel>) {
+        let config = &self.config;
+        channels.retain(|relay_id, channel| {
+            let expired = channel.is_expired(config);
+            if expired {
+                tracing::debug!(relay_id = %relay_id, "relay public key fetch timed out");
+            }
+            !expired
+        });
+    }
+
     /// Executes an upstream request to fetch public keys.
     ///
     /// This assumes that currently no request is running. If the upstream request fails or new
     /// channels are pushed in the meanwhile, this will reschedule automatically.
+    ///
+    /// The whole attempt -- dispatch, upstream round trip, and per-relay resolution -- is wrapped
+    /// in one `batch_size`/`attempt` span so a `relay_id` logged from inside it can be joined back
+    /// to the batch and attempt it was resolved in. The combinators here are plain `futures` 0.1
+    /// (no `async`/`await`), so the span is entered manually around each synchronous portion of
+    /// the chain rather than via `tracing::Instrument`, which targets `std::future::Future`.
     fn fetch_keys(&mut self, context: &mut Context<Self>) {
-        let channels = mem::replace(&mut self.relay_info_channels, HashMap::new());
-        log::debug!(
-            "updating public keys for {} relays (attempt {})",
-            channels.len(),
-            self.backoff.attempt(),
-        );
+        let mut channels = mem::replace(&mut self.relay_info_channels, HashMap::new());
+        self.expire_stale_channels(&mut channels);
+
+        let attempt = self.backoff.attempt();
+        let batch_size = channels.len();
+        let span = tracing::debug_span!("relay_key_batch_fetch", batch_size, %attempt);
+        let _enter = span.enter();
+
+        tracing::debug!("dispatching batched public key fetch to upstream");
 
         let request = GetRelaysInfo {
             relay_ids: channels.keys().cloned().collect(),
         };
 
+        let started_at = Instant::now();
+        let response_span = span.clone();
+
         self.upstream
             .send(SendQuery(request))
             .map_err(KeyError::ScheduleFailed)
             .into_actor(self)
-            .and_then(|response, slf, ctx| {
+            .and_then(move |response, slf, ctx| {
+                let _enter = response_span.enter();
+                let latency_ms = started_at.elapsed().as_millis() as u64;
+
                 match response {
                     Ok(response) => {
                         let mut response = GetRelaysInfoResult::from(response);
                         slf.backoff.reset();
 
                         for (id, channel) in channels {
+                            let relay_span =
+                                tracing::debug_span!("relay_key_resolved", relay_id = %id);
+                            let _relay_enter = relay_span.enter();
                             let info = response.relays.remove(&id).unwrap_or(None);
-                            slf.relays
-                                .insert(id, RelayInfoState::from_option(info.clone()));
-                            log::debug!("relay {} public key updated", id);
+                            let outcome = if info.is_some() { "hit" } else { "miss" };
+                            slf.insert_relay(id, RelayInfoState::from_option(info.clone()));
+                            tracing::debug!(outcome, latency_ms, "relay public key updated");
                             channel.send(info).ok();
                         }
                     }
                     Err(error) => {
-                        log::error!("error fetching public keys: {}", LogError(&error));
+                        tracing::error!(
+                            error = %LogError(&error),
+                            latency_ms,
+                            "error fetching public keys"
+                        );
 
                         // Put the channels back into the queue, in addition to channels that have
                         // been pushed in the meanwhile. We will retry again shortly.
@@ -190,26 +328,68 @@ impl RelayInfoCache {
             .spawn(context);
     }
 
+    /// Enqueues a background refetch for `relay_id` without making the caller wait for it,
+    /// reusing the same batching/backoff machinery as a cold fetch.
+    fn enqueue_background_refresh(&mut self, relay_id: RelayId, context: &mut Context<Self>) {
+        if self.relay_info_channels.contains_key(&relay_id) {
+            // Already being (re)fetched, whether due to an earlier stale hit or a concurrent
+            // cache miss.
+            return;
+        }
+
+        if self.config.credentials().is_none() {
+            return;
+        }
+
+        tracing::debug!(relay_id = %relay_id, "relay public key stale, refreshing in background");
+        if !self.backoff.started() {
+            self.backoff.reset();
+            self.schedule_fetch(context);
+        }
+
+        self.relay_info_channels
+            .insert(relay_id, RelayInfoChannel::new());
+    }
+
+    #[tracing::instrument(skip(self, context))]
     fn get_or_fetch_info(
         &mut self,
         relay_id: RelayId,
         context: &mut Context<Self>,
-    ) -> Response<(RelayId, Option<RelayInfo>), KeyError> {
-        if let Some(key) = self.relays.get(&relay_id) {
-            if key.is_valid_cache(&self.config) {
-                return Response::ok((relay_id, key.as_option().cloned()));
+    ) -> Response<(RelayId, Option<RelayInfo>, RelayInfoFreshness), KeyError> {
+        let cached = self.relays.get(&relay_id).and_then(|entry| {
+            if entry.state.is_valid_cache(&self.config) {
+                Some((
+                    entry.state.as_option().cloned(),
+                    entry.state.needs_refresh(&self.config),
+                ))
+            } else {
+                None
+            }
+        });
+
+        if let Some((info, stale)) = cached {
+            if let Some(entry) = self.relays.get_mut(&relay_id) {
+                entry.last_accessed = Instant::now();
             }
+            if stale {
+                tracing::debug!(outcome = "revalidating", "serving stale relay info from cache");
+                self.enqueue_background_refresh(relay_id, context);
+                return Response::ok((relay_id, info, RelayInfoFreshness::Revalidating));
+            }
+            tracing::debug!(outcome = "cached", "serving relay info from cache");
+            return Response::ok((relay_id, info, RelayInfoFreshness::Cached));
         }
 
         if self.config.credentials().is_none() {
-            log::error!(
-                "No credentials configured. Relay {} cannot send requests to this relay.",
-                relay_id
+            tracing::error!(
+                outcome = "error",
+                "no credentials configured, relay cannot send requests to this relay"
             );
-            return Response::ok((relay_id, None));
+            return Response::ok((relay_id, None, RelayInfoFreshness::FreshlyFetched));
         }
 
-        log::debug!("relay {} public key requested", relay_id);
+        tracing::debug!(outcome = "miss", "relay public key requested from upstream");
         if !self.backoff.started() {
             self.backoff.reset();
             self.schedule_fetch(context);
@@ -220,7 +400,7 @@ impl RelayInfoCache {
             .entry(relay_id)
             .or_insert_with(RelayInfoChannel::new)
             .receiver()
-            .map(move |key| (relay_id, (*key).clone()))
+            .map(move |key| (relay_id, (*key).clone(), RelayInfoFreshness::FreshlyFetched))
             .map_err(|_| KeyError::FetchFailed);
 
         Response::r#async(receiver)
@@ -247,6 +427,7 @@ pub struct GetRelayInfo {
 #[derive(Debug)]
 pub struct GetRelayInfoResult {
     pub public_key: Option<RelayInfo>,
+    pub freshness: RelayInfoFreshness,
 }
 
 impl Message for GetRelayInfo {
@@ -258,7 +439,10 @@ impl Handler<GetRelayInfo> for RelayInfoCache {
 
     fn handle(&mut self, message: GetRelayInfo, context: &mut Self::Context) -> Self::Result {
         self.get_or_fetch_info(message.relay_id, context)
-            .map(|(_id, public_key)| GetRelayInfoResult { public_key })
+            .map(|(_id, public_key, freshness)| GetRelayInfoResult {
+                public_key,
+                freshness,
+            })
     }
 }
 
@@ -344,7 +528,7 @@ impl Handler<GetRelaysInfo> for RelayInfoCache {
                 Response::Async(fut) => {
                     futures.push(fut);
                 }
-                Response::Reply(Ok((id, key))) => {
+                Response::Reply(Ok((id, key, _freshness))) => {
                     relays.insert(id, key);
                 }
                 Response::Reply(Err(_)) => {
@@ -358,7 +542,11 @@ impl Handler<GetRelaysInfo> for RelayInfoCache {
         }
 
         let future = future::join_all(futures).map(move |responses| {
-            relays.extend(responses);
+            relays.extend(
+                responses
+                    .into_iter()
+                    .map(|(id, key, _freshness)| (id, key)),
+            );
             GetRelaysInfoResult { relays }
         });
 
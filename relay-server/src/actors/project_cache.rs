@@ -1,4 +1,5 @@
-use std::collections::{hash_map::Entry, HashMap, VecDeque};
+use std::cmp::Reverse;
+use std::collections::{hash_map::Entry, BinaryHeap, HashMap};
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -18,7 +19,10 @@ use crate::metrics::{RelayCounters, RelayHistograms, RelayTimers};
 use crate::utils::{RedisPool, Response};
 
 #[cfg(feature = "processing")]
-use {crate::actors::redis_project::RedisProjectCache, relay_common::clone};
+use {
+    crate::actors::redis_project::{ProjectStateCache, RedisProjectCache},
+    relay_common::clone,
+};
 
 #[derive(Fail, Debug)]
 pub enum ProjectError {
@@ -31,25 +35,18 @@ pub enum ProjectError {
 
 impl ResponseError for ProjectError {}
 
-#[derive(Clone, Copy, Debug)]
-struct ProjectUpdate {
-    project_id: ProjectId,
-    instant: Instant,
-}
-
-impl ProjectUpdate {
-    pub fn new(project_id: ProjectId) -> Self {
-        ProjectUpdate {
-            project_id,
-            instant: Instant::now(),
-        }
-    }
-}
-
 pub struct ProjectCache {
     config: Arc<Config>,
     projects: HashMap<ProjectId, Addr<Project>>,
-    updates: VecDeque<ProjectUpdate>,
+
+    // Eviction queue for `projects`, keyed by each project's most recent refresh time. Entries are
+    // lazily deleted: `latest_update` is the source of truth for a project's refresh time, so an
+    // entry popped off `updates` that doesn't match the map anymore is a stale duplicate left
+    // behind by an earlier refresh of the same project, and is simply discarded rather than acted
+    // on. This keeps a refresh O(log n) (one push, no search-and-remove of the old entry) while
+    // eviction stays proportional to the number of entries that are actually stale or expired.
+    updates: BinaryHeap<Reverse<(Instant, ProjectId)>>,
+    latest_update: HashMap<ProjectId, Instant>,
 
     local_cache: Addr<ProjectLocalCache>,
     upstream_cache: Addr<ProjectUpstreamCache>,
@@ -66,11 +63,19 @@ impl ProjectCache {
     ) -> Self {
         #[cfg(feature = "processing")]
         let redis_cache = _redis.map(|pool| {
+            // Built once and cloned into every sync worker below, so the TTL/LRU cache is
+            // actually shared across the pool rather than each worker keeping its own.
+            let state_cache = ProjectStateCache::new(
+                config.redis_project_state_cache_expiry(),
+                config.redis_project_state_cache_max_entries(),
+            );
+
             SyncArbiter::start(
                 config.cpu_concurrency(),
-                clone!(config, || RedisProjectCache::new(
+                clone!(config, state_cache, || RedisProjectCache::new(
                     config.clone(),
-                    pool.clone()
+                    pool.clone(),
+                    state_cache.clone()
                 )),
             )
         });
@@ -78,7 +83,8 @@ impl ProjectCache {
         ProjectCache {
             config,
             projects: HashMap::new(),
-            updates: VecDeque::new(),
+            updates: BinaryHeap::new(),
+            latest_update: HashMap::new(),
 
             local_cache,
             upstream_cache,
@@ -182,32 +188,34 @@ impl Handler<FetchProjectState> for ProjectCache {
     type Result = Response<ProjectStateResponse, ()>;
 
     fn handle(&mut self, message: FetchProjectState, _context: &mut Self::Context) -> Self::Result {
-        // Remove outdated projects that are not being refreshed from the cache. If the project is
-        // being updated now, also remove its update entry from the queue, since we will be
-        // inserting a new timestamp at the end (see `extend`).
+        // Remove outdated projects that are not being refreshed from the cache. The min-heap's
+        // peek is always the oldest *candidate* refresh time, but it may be a stale duplicate left
+        // behind by a project that has since been refreshed again -- `latest_update` is the source
+        // of truth, so such an entry is discarded without touching `projects` for it.
         let eviction_start = Instant::now();
         let eviction_threshold = eviction_start - 2 * self.config.project_cache_expiry();
-        while let Some(update) = self.updates.get(0) {
-            if update.instant > eviction_threshold {
+        while let Some(&Reverse((instant, project_id))) = self.updates.peek() {
+            if instant > eviction_threshold {
                 break;
             }
 
-            if update.project_id != message.id {
-                self.projects.remove(&update.project_id);
+            self.updates.pop();
+
+            if self.latest_update.get(&project_id) != Some(&instant) {
+                continue; // Stale duplicate: this project has a newer entry further in the heap.
             }
 
-            self.updates.pop_front();
+            if project_id != message.id {
+                self.projects.remove(&project_id);
+                self.latest_update.remove(&project_id);
+            }
         }
 
-        // The remaining projects are not outdated anymore. Still, clean them from the queue to
-        // reinsert them at the end, as they are now receiving an updated timestamp. Then,
-        // batch-insert all new projects with the new timestamp.
-        //
-        // TODO(markus): This is way too slow. This used to be OK when part of a batched fetch. We
-        // need some priority queue dingus here.
-        self.updates
-            .retain(|update| update.project_id != message.id);
-        self.updates.push_back(ProjectUpdate::new(message.id));
+        // Record the new refresh time. The project's old heap entry, if any, is left in place and
+        // will be discarded as a stale duplicate once it reaches the front of the heap.
+        let now = Instant::now();
+        self.latest_update.insert(message.id, now);
+        self.updates.push(Reverse((now, message.id)));
 
         metric!(timer(RelayTimers::ProjectStateEvictionDuration) = eviction_start.elapsed());
 
@@ -266,3 +274,38 @@ impl Handler<FetchProjectState> for ProjectCache {
         Response::r#async(future)
     }
 }
+
+/// Re-reads the config file `ProjectCache` was originally started with and, if it parses
+/// successfully, atomically swaps it in so subsequent `GetProject`/`FetchProjectState` handlers
+/// observe the new `project_cache_expiry`, filter lists, and upstream settings.
+///
+/// A parse failure is fail-safe: the previous config keeps serving and the error is logged, so a
+/// typo in a live edit can't take Relay down. Already-running `Project` actors and their cached
+/// states are untouched either way -- only `self.config` is replaced, `projects` is left alone.
+///
+/// Nothing in this snapshot sends `ReloadConfig` yet: there's no `Controller` actor here to wire a
+/// SIGHUP handler into, and no config file watcher. Whoever lands those should have them message
+/// `ProjectCache`'s address with this.
+pub struct ReloadConfig;
+
+impl Message for ReloadConfig {
+    type Result = ();
+}
+
+impl Handler<ReloadConfig> for ProjectCache {
+    type Result = ();
+
+    fn handle(&mut self, _message: ReloadConfig, _context: &mut Self::Context) -> Self::Result {
+        match Config::from_path(self.config.path()) {
+            Ok(config) => {
+                log::info!("reloaded configuration from {}", self.config.path().display());
+                self.config = Arc::new(config);
+            }
+            Err(error) => log::error!(
+                "failed to reload configuration from {}, keeping previous config: {}",
+                self.config.path().display(),
+                error
+            ),
+        }
+    }
+}
@@ -1,21 +1,148 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use actix::prelude::*;
+use flate2::read::{GzDecoder, ZlibDecoder};
 use relay_common::ProjectId;
 use relay_config::Config;
 
-use crate::actors::project::GetProjectStatesResponse;
+use crate::actors::project::{GetProjectStatesResponse, ProjectState};
 use crate::utils::{ErrorBoundary, RedisError, RedisPool};
 
+/// Magic header `flate2`/RFC 1952 gzip streams start with. Sniffed so a gzip-compressed value
+/// doesn't need the one-byte tag below to be told apart from plain JSON.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// One-byte tag a zlib-compressed value is prefixed with. Unlike gzip, a raw zlib stream has no
+/// header reliable enough to sniff on its own (`0x78` is common but not guaranteed), so Relay
+/// defines this tag itself; the producer writing compressed project states is expected to prefix
+/// zlib payloads with it. Chosen to collide with neither the gzip magic above nor any byte valid
+/// at the start of whitespace-led plain JSON (`{`, `[`, or ASCII whitespace).
+const ZLIB_TAG: u8 = 0x01;
+
+/// Decompresses a single Redis value read back by [`GetProjectStatesFromRedis`], sniffing its
+/// format from a magic prefix rather than trusting `Config::projectconfig_compression` -- a cache
+/// can easily hold a mix of old and new entries across a format change, and sniffing lets each one
+/// be read correctly regardless of when it was written.
+fn decompress_project_state(data: &[u8]) -> io::Result<Vec<u8>> {
+    if data.starts_with(&GZIP_MAGIC) {
+        let mut decoded = Vec::new();
+        GzDecoder::new(data).read_to_end(&mut decoded)?;
+        Ok(decoded)
+    } else if data.first() == Some(&ZLIB_TAG) {
+        let mut decoded = Vec::new();
+        ZlibDecoder::new(&data[1..]).read_to_end(&mut decoded)?;
+        Ok(decoded)
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
+/// A single cached entry: the Redis-derived value for one project, plus when it was inserted and
+/// last read, for TTL and LRU eviction respectively.
+struct CacheEntry {
+    value: Arc<ErrorBoundary<ProjectState>>,
+    inserted_at: Instant,
+    last_used: Instant,
+}
+
+struct ProjectStateCacheState {
+    ttl: Duration,
+    capacity: usize,
+    entries: HashMap<ProjectId, CacheEntry>,
+    /// Least-recently-used order, kept the same way `ProjectCache::updates` tracks its own
+    /// eviction order: a min-heap of `(last_used, id)`, with a heap entry superseded by a later
+    /// access to the same project discarded lazily on pop rather than searched for up front.
+    recency: BinaryHeap<Reverse<(Instant, ProjectId)>>,
+}
+
+/// A bounded, time-boxed cache of project states fetched from Redis.
+///
+/// Shared across every `RedisProjectCache` sync worker (see `ProjectCache::new`, which constructs
+/// one and clones it into each worker), so a hot project's repeated lookups skip the Redis round
+/// trip entirely rather than just avoiding it within a single worker. TTL is enforced lazily -- an
+/// expired entry is treated as a miss and overwritten on the next fetch, rather than swept by a
+/// background task.
+#[derive(Clone)]
+pub struct ProjectStateCache {
+    inner: Arc<Mutex<ProjectStateCacheState>>,
+}
+
+impl ProjectStateCache {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        ProjectStateCache {
+            inner: Arc::new(Mutex::new(ProjectStateCacheState {
+                ttl,
+                capacity,
+                entries: HashMap::new(),
+                recency: BinaryHeap::new(),
+            })),
+        }
+    }
+
+    /// Returns the cached value for `id`, or `None` on a miss or an expired entry.
+    fn get(&self, id: ProjectId) -> Option<Arc<ErrorBoundary<ProjectState>>> {
+        let mut state = self.inner.lock().unwrap();
+
+        let hit = match state.entries.get(&id) {
+            Some(entry) if entry.inserted_at.elapsed() < state.ttl => Some(entry.value.clone()),
+            _ => None,
+        };
+
+        let hit = hit?;
+
+        let now = Instant::now();
+        state.entries.get_mut(&id).unwrap().last_used = now;
+        state.recency.push(Reverse((now, id)));
+
+        Some(hit)
+    }
+
+    /// Inserts `value` for `id`, evicting the least-recently-used entry first if this insert
+    /// would put the cache over capacity.
+    fn insert(&self, id: ProjectId, value: Arc<ErrorBoundary<ProjectState>>) {
+        let mut state = self.inner.lock().unwrap();
+        let now = Instant::now();
+
+        state.entries.insert(
+            id,
+            CacheEntry {
+                value,
+                inserted_at: now,
+                last_used: now,
+            },
+        );
+        state.recency.push(Reverse((now, id)));
+
+        while state.entries.len() > state.capacity {
+            let (last_used, candidate) = match state.recency.pop() {
+                Some(Reverse(entry)) => entry,
+                None => break,
+            };
+
+            if state.entries.get(&candidate).map(|entry| entry.last_used) == Some(last_used) {
+                state.entries.remove(&candidate);
+            }
+        }
+    }
+}
+
 pub struct RedisProjectCache {
     config: Arc<Config>,
     redis: RedisPool,
+    cache: ProjectStateCache,
 }
 
 impl RedisProjectCache {
-    pub fn new(config: Arc<Config>, redis: RedisPool) -> Self {
-        RedisProjectCache { config, redis }
+    pub fn new(config: Arc<Config>, redis: RedisPool, cache: ProjectStateCache) -> Self {
+        RedisProjectCache {
+            config,
+            redis,
+            cache,
+        }
     }
 }
 
@@ -42,13 +169,33 @@ impl Message for GetProjectStatesFromRedis {
 impl Handler<GetProjectStatesFromRedis> for RedisProjectCache {
     type Result = Result<GetProjectStatesResponse, RedisError>;
 
+    // `GetProjectStatesResponse.configs` is assumed here to be
+    // `HashMap<ProjectId, Arc<ErrorBoundary<ProjectState>>>` -- the `Arc` wrapping, over the plain
+    // `ErrorBoundary<ProjectState>` this handler produced before the TTL cache below existed, is
+    // what lets a cache hit be handed back without cloning the deserialized project state itself.
     fn handle(
         &mut self,
         request: GetProjectStatesFromRedis,
         _ctx: &mut Self::Context,
     ) -> Self::Result {
+        let mut configs = HashMap::new();
+        let mut misses = Vec::new();
+
+        for id in request.projects {
+            match self.cache.get(id) {
+                Some(value) => {
+                    configs.insert(id, value);
+                }
+                None => misses.push(id),
+            }
+        }
+
+        if misses.is_empty() {
+            return Ok(GetProjectStatesResponse { configs });
+        }
+
         let mut command = redis::cmd("MGET");
-        for id in &request.projects {
+        for id in &misses {
             command.arg(format!(
                 "{}:{}",
                 self.config.projectconfig_cache_prefix(),
@@ -56,7 +203,7 @@ impl Handler<GetProjectStatesFromRedis> for RedisProjectCache {
             ));
         }
 
-        let raw_response: Vec<String> = match self.redis {
+        let raw_response: Vec<Vec<u8>> = match self.redis {
             RedisPool::Cluster(ref pool) => {
                 let mut client = pool.get().map_err(RedisError::RedisPool)?;
                 command.query(&mut *client).map_err(RedisError::Redis)?
@@ -67,13 +214,19 @@ impl Handler<GetProjectStatesFromRedis> for RedisProjectCache {
             }
         };
 
-        let mut configs = HashMap::new();
-        for (response, id) in raw_response.into_iter().zip(request.projects) {
-            let config = match serde_json::from_str(&response) {
-                Ok(project_state) => ErrorBoundary::Ok(project_state),
+        for (response, id) in raw_response.into_iter().zip(misses) {
+            // A decode failure -- whether decompression or JSON parsing -- only poisons this one
+            // entry; the rest of the batch is still usable.
+            let config = match decompress_project_state(&response) {
+                Ok(json) => match serde_json::from_slice(&json) {
+                    Ok(project_state) => ErrorBoundary::Ok(project_state),
+                    Err(err) => ErrorBoundary::Err(Box::new(err)),
+                },
                 Err(err) => ErrorBoundary::Err(Box::new(err)),
             };
 
+            let config = Arc::new(config);
+            self.cache.insert(id, config.clone());
             configs.insert(id, config);
         }
 
@@ -0,0 +1,24 @@
+//! Exposes Relay's own internal metrics (cache hit ratios, eviction timings, and the like) as a
+//! `/metrics` resource in Prometheus text exposition format, for operators who scrape metrics
+//! rather than push them to statsd.
+//!
+//! Unlike the other endpoints in this module, this one isn't behind `common::cors` -- it's meant
+//! to be scraped by infrastructure on a private network, not called from a browser.
+
+use actix_web::{HttpRequest, HttpResponse};
+
+use crate::metrics_registry::PROMETHEUS_REGISTRY;
+use crate::service::{ServiceApp, ServiceState};
+
+fn get_metrics(_request: HttpRequest<ServiceState>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(PROMETHEUS_REGISTRY.render())
+}
+
+pub fn configure_app(app: ServiceApp) -> ServiceApp {
+    app.resource("/metrics", |r| {
+        r.name("get-metrics");
+        r.get().f(get_metrics);
+    })
+}
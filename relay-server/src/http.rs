@@ -14,13 +14,14 @@ use std::io::Write;
 use actix_web::client::{ClientRequest, ClientRequestBuilder, ClientResponse};
 use actix_web::http::{ContentEncoding, StatusCode};
 use actix_web::{Binary, Error as ActixError, HttpMessage};
-use brotli2::write::BrotliEncoder;
+use brotli2::write::{BrotliDecoder, BrotliEncoder};
 use failure::Fail;
-use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::write::{GzDecoder, GzEncoder, ZlibDecoder, ZlibEncoder};
 use flate2::Compression;
 use futures::{future, prelude::*};
-use futures03::{FutureExt, TryFutureExt, TryStreamExt};
+use futures03::{stream, FutureExt, TryFutureExt, TryStreamExt};
 use serde::de::DeserializeOwned;
+use zstd::stream::write::{Decoder as ZstdDecoder, Encoder as ZstdEncoder};
 
 use ::actix::prelude::*;
 
@@ -36,10 +37,12 @@ pub enum HttpError {
     Actix(ActixError),
     #[fail(display = "failed to stream payload: {}", _0)]
     Io(#[cause] io::Error),
-    #[fail(display = "could not parse json payload returned by upstream")]
-    ActixJson(#[cause] actix_web::error::JsonPayloadError),
     #[fail(display = "failed to receive response from upstream")]
     ActixPayload(#[cause] actix_web::error::PayloadError),
+    #[fail(display = "could not parse json payload returned by upstream")]
+    Json(#[cause] serde_json::Error),
+    #[fail(display = "unknown content encoding: {}", _0)]
+    UnknownEncoding(String),
 }
 
 impl From<reqwest::Error> for HttpError {
@@ -60,18 +63,212 @@ impl From<io::Error> for HttpError {
     }
 }
 
-impl From<actix_web::error::JsonPayloadError> for HttpError {
-    fn from(e: actix_web::error::JsonPayloadError) -> Self {
-        HttpError::ActixJson(e)
-    }
-}
-
 impl From<actix_web::error::PayloadError> for HttpError {
     fn from(e: actix_web::error::PayloadError) -> Self {
         HttpError::ActixPayload(e)
     }
 }
 
+impl From<serde_json::Error> for HttpError {
+    fn from(e: serde_json::Error) -> Self {
+        HttpError::Json(e)
+    }
+}
+
+/// Incrementally decompresses a response body according to its `Content-Encoding`, tracking the
+/// decompressed size as it goes.
+///
+/// Unlike decompressing the whole body in one shot, feeding it through chunk by chunk lets
+/// [`Response::bytes`] enforce its `limit` against the *decompressed* size, so a small compressed
+/// upstream response can't expand into an unbounded one in memory (a classic zip-bomb).
+enum BodyDecoder {
+    Identity(Vec<u8>),
+    Gzip(GzDecoder<Vec<u8>>),
+    Deflate(ZlibDecoder<Vec<u8>>),
+    Br(BrotliDecoder<Vec<u8>>),
+    Zstd(ZstdDecoder<Vec<u8>>),
+}
+
+impl BodyDecoder {
+    fn new(content_encoding: Option<&[u8]>) -> Self {
+        match content_encoding {
+            Some(b"gzip") => BodyDecoder::Gzip(GzDecoder::new(Vec::new())),
+            Some(b"deflate") => BodyDecoder::Deflate(ZlibDecoder::new(Vec::new())),
+            Some(b"br") => BodyDecoder::Br(BrotliDecoder::new(Vec::new())),
+            Some(b"zstd") => {
+                BodyDecoder::Zstd(ZstdDecoder::new(Vec::new()).expect("zstd decoder init"))
+            }
+            _ => BodyDecoder::Identity(Vec::new()),
+        }
+    }
+
+    /// Writes a chunk of (still compressed) bytes into the decoder, failing with
+    /// `HttpError::Overflow` if the running decompressed length exceeds `limit`.
+    fn write_chunk(&mut self, chunk: &[u8], limit: usize) -> Result<(), HttpError> {
+        match self {
+            BodyDecoder::Identity(buf) => buf.extend_from_slice(chunk),
+            BodyDecoder::Gzip(decoder) => decoder.write_all(chunk)?,
+            BodyDecoder::Deflate(decoder) => decoder.write_all(chunk)?,
+            BodyDecoder::Br(decoder) => decoder.write_all(chunk)?,
+            BodyDecoder::Zstd(decoder) => decoder.write_all(chunk)?,
+        }
+
+        if self.decompressed_len() > limit {
+            return Err(HttpError::Overflow);
+        }
+
+        Ok(())
+    }
+
+    fn decompressed_len(&self) -> usize {
+        match self {
+            BodyDecoder::Identity(buf) => buf.len(),
+            BodyDecoder::Gzip(decoder) => decoder.get_ref().len(),
+            BodyDecoder::Deflate(decoder) => decoder.get_ref().len(),
+            BodyDecoder::Br(decoder) => decoder.get_ref().len(),
+            BodyDecoder::Zstd(decoder) => decoder.get_ref().len(),
+        }
+    }
+
+    fn finish(self) -> Result<Vec<u8>, HttpError> {
+        Ok(match self {
+            BodyDecoder::Identity(buf) => buf,
+            BodyDecoder::Gzip(decoder) => decoder.finish()?,
+            BodyDecoder::Deflate(decoder) => decoder.finish()?,
+            BodyDecoder::Br(decoder) => decoder.finish()?,
+            BodyDecoder::Zstd(mut decoder) => {
+                decoder.flush()?;
+                decoder.into_inner()
+            }
+        })
+    }
+}
+
+/// How many raw input bytes `ChunkEncoder` feeds into the encoder per step. Bounds how much
+/// uncompressed data is live at once; the encoder's own internal buffering bounds the compressed
+/// side.
+const COMPRESSION_CHUNK_SIZE: usize = 8192;
+
+/// A stateful, incremental compressor matching a `Content-Encoding`, used by `ChunkEncoder` to
+/// turn a request body into a stream of compressed chunks instead of one fully-materialized
+/// buffer.
+enum BodyEncoder {
+    Identity,
+    Deflate(ZlibEncoder<Vec<u8>>),
+    Gzip(GzEncoder<Vec<u8>>),
+    Br(BrotliEncoder<Vec<u8>>),
+    Zstd(ZstdEncoder<'static, Vec<u8>>),
+}
+
+impl BodyEncoder {
+    fn new(http_encoding: HttpEncoding) -> Result<Self, io::Error> {
+        Ok(match http_encoding {
+            HttpEncoding::Identity => BodyEncoder::Identity,
+            HttpEncoding::Deflate => {
+                BodyEncoder::Deflate(ZlibEncoder::new(Vec::new(), Compression::default()))
+            }
+            HttpEncoding::Gzip => {
+                BodyEncoder::Gzip(GzEncoder::new(Vec::new(), Compression::default()))
+            }
+            HttpEncoding::Br => BodyEncoder::Br(BrotliEncoder::new(Vec::new(), 5)),
+            HttpEncoding::Zstd => BodyEncoder::Zstd(ZstdEncoder::new(Vec::new(), 3)?),
+        })
+    }
+
+    /// Feeds `chunk` into the encoder and drains whatever compressed bytes it has produced so
+    /// far, so the caller never has to hold the whole compressed output at once.
+    fn write_chunk(&mut self, chunk: &[u8]) -> Result<Vec<u8>, io::Error> {
+        match self {
+            BodyEncoder::Identity => Ok(chunk.to_vec()),
+            BodyEncoder::Deflate(encoder) => {
+                encoder.write_all(chunk)?;
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+            BodyEncoder::Gzip(encoder) => {
+                encoder.write_all(chunk)?;
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+            BodyEncoder::Br(encoder) => {
+                encoder.write_all(chunk)?;
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+            BodyEncoder::Zstd(encoder) => {
+                encoder.write_all(chunk)?;
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+        }
+    }
+
+    /// Flushes any bytes the encoder is still holding onto, so the deflate/gzip/br/zstd frame's
+    /// trailer isn't truncated.
+    fn finish(self) -> Result<Vec<u8>, io::Error> {
+        match self {
+            BodyEncoder::Identity => Ok(Vec::new()),
+            BodyEncoder::Deflate(encoder) => encoder.finish(),
+            BodyEncoder::Gzip(encoder) => encoder.finish(),
+            BodyEncoder::Br(encoder) => encoder.finish(),
+            BodyEncoder::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+enum ChunkEncoderState {
+    Chunking,
+    Finishing,
+    Done,
+}
+
+/// Turns a full in-memory request body into an iterator of compressed chunks, so
+/// `reqwest::Body::wrap_stream` can hand them to the HTTP client one at a time instead of
+/// requiring the entire compressed payload up front.
+struct ChunkEncoder {
+    body: Vec<u8>,
+    offset: usize,
+    encoder: BodyEncoder,
+    state: ChunkEncoderState,
+}
+
+impl Iterator for ChunkEncoder {
+    type Item = Result<Vec<u8>, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.state {
+                ChunkEncoderState::Chunking => {
+                    if self.offset >= self.body.len() {
+                        self.state = ChunkEncoderState::Finishing;
+                        continue;
+                    }
+
+                    let end = (self.offset + COMPRESSION_CHUNK_SIZE).min(self.body.len());
+                    let chunk = self.body[self.offset..end].to_vec();
+                    self.offset = end;
+
+                    return Some(self.encoder.write_chunk(&chunk));
+                }
+                ChunkEncoderState::Finishing => {
+                    self.state = ChunkEncoderState::Done;
+                    let encoder = std::mem::replace(&mut self.encoder, BodyEncoder::Identity);
+                    return Some(encoder.finish());
+                }
+                ChunkEncoderState::Done => return None,
+            }
+        }
+    }
+}
+
+/// The wire token for a given `HttpEncoding`, as used in both `Content-Encoding` and
+/// `Accept-Encoding` headers.
+fn encoding_token(encoding: HttpEncoding) -> &'static str {
+    match encoding {
+        HttpEncoding::Identity => "identity",
+        HttpEncoding::Deflate => "deflate",
+        HttpEncoding::Gzip => "gzip",
+        HttpEncoding::Br => "br",
+        HttpEncoding::Zstd => "zstd",
+    }
+}
+
 pub enum Request {
     Actix(ClientRequest),
     Reqwest(reqwest::Request),
@@ -132,33 +329,18 @@ impl RequestBuilder {
         match self {
             RequestBuilder::Actix(mut builder) => Ok(Request::Actix(builder.body(body)?)),
             RequestBuilder::Reqwest {
-                mut builder,
+                builder,
                 http_encoding,
             } => {
-                let body = match http_encoding {
-                    HttpEncoding::Identity => {
-                        builder = builder.header("Content-Encoding", "identity");
-                        body.as_ref().to_vec()
-                    }
-                    HttpEncoding::Deflate => {
-                        builder = builder.header("Content-Encoding", "deflate");
-                        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
-                        encoder.write_all(body.as_ref())?;
-                        encoder.finish().unwrap()
-                    }
-                    HttpEncoding::Gzip => {
-                        builder = builder.header("Content-Encoding", "gzip");
-                        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-                        encoder.write_all(body.as_ref())?;
-                        encoder.finish().unwrap()
-                    }
-                    HttpEncoding::Br => {
-                        builder = builder.header("Content-Encoding", "br");
-                        let mut encoder = BrotliEncoder::new(Vec::new(), 5);
-                        encoder.write_all(body.as_ref())?;
-                        encoder.finish().unwrap()
-                    }
+                let builder = builder.header("Content-Encoding", encoding_token(http_encoding));
+
+                let chunks = ChunkEncoder {
+                    body: body.as_ref().to_vec(),
+                    offset: 0,
+                    encoder: BodyEncoder::new(http_encoding)?,
+                    state: ChunkEncoderState::Chunking,
                 };
+                let body = reqwest::Body::wrap_stream(stream::iter(chunks));
 
                 RequestBuilder::Reqwest {
                     builder: builder.body(body),
@@ -169,6 +351,29 @@ impl RequestBuilder {
         }
     }
 
+    /// Sets an `Accept-Encoding` header listing `encodings` in priority order, most preferred
+    /// first, so the upstream can pick the best codec it supports.
+    ///
+    /// The first encoding is sent without a q-value (implying `q=1`), each subsequent one drops
+    /// by `0.2`, and a trailing `identity;q=0` tells the upstream not to silently fall back to an
+    /// encoding that wasn't asked for.
+    pub fn accept_encodings(&mut self, encodings: &[HttpEncoding]) -> &mut Self {
+        let mut parts = Vec::with_capacity(encodings.len() + 1);
+
+        for (index, encoding) in encodings.iter().enumerate() {
+            let token = encoding_token(*encoding);
+            if index == 0 {
+                parts.push(token.to_owned());
+            } else {
+                let q = 1.0 - 0.2 * index as f32;
+                parts.push(format!("{};q={:.1}", token, q.max(0.0)));
+            }
+        }
+
+        parts.push("identity;q=0".to_owned());
+        self.header("Accept-Encoding", parts.join(", ").as_bytes())
+    }
+
     pub fn content_encoding(&mut self, encoding: HttpEncoding) -> &mut Self {
         match self {
             RequestBuilder::Actix(builder) => {
@@ -177,6 +382,10 @@ impl RequestBuilder {
                     HttpEncoding::Deflate => ContentEncoding::Deflate,
                     HttpEncoding::Gzip => ContentEncoding::Gzip,
                     HttpEncoding::Br => ContentEncoding::Br,
+                    // actix-web's `ContentEncoding` has no zstd variant, so the actix transport
+                    // can't compress with it; fall back to sending the body uncompressed rather
+                    // than silently mislabeling it.
+                    HttpEncoding::Zstd => ContentEncoding::Identity,
                 };
 
                 builder.content_encoding(content_encoding);
@@ -209,25 +418,20 @@ impl Response {
         }
     }
 
+    /// Deserializes the response body as JSON, never buffering more than `limit` decompressed
+    /// bytes of it.
+    ///
+    /// Built on top of `bytes`, so `limit` is enforced identically on both the actix and reqwest
+    /// backends -- a misbehaving upstream can't force either one to buffer and parse an
+    /// arbitrarily large document.
     pub fn json<T: 'static + DeserializeOwned>(
         self,
         limit: usize,
     ) -> Box<dyn Future<Item = T, Error = HttpError>> {
-        // TODO: apply limit to reqwest
-        match self {
-            Response::Actix(response) => {
-                let future = response.json().limit(limit).map_err(HttpError::ActixJson);
-                Box::new(future) as Box<dyn Future<Item = _, Error = _>>
-            }
-            Response::Reqwest(response) => {
-                let future = response
-                    .json()
-                    .boxed_local()
-                    .compat()
-                    .map_err(HttpError::Reqwest);
-                Box::new(future) as Box<dyn Future<Item = _, Error = _>>
-            }
-        }
+        Box::new(
+            self.bytes(limit)
+                .and_then(|body| Ok(serde_json::from_slice(&body)?)),
+        )
     }
 
     pub fn consume(self) -> ResponseFuture<Self, HttpError> {
@@ -248,6 +452,25 @@ impl Response {
         }
     }
 
+    /// Parses the response's negotiated `Content-Encoding` header into an `HttpEncoding`.
+    ///
+    /// A missing header is treated as `Identity` (the implicit default per RFC 7231), while an
+    /// unrecognized token is reported via `HttpError::UnknownEncoding` so callers can decide
+    /// whether to fail the request or fall back to treating the body as opaque bytes.
+    pub fn content_encoding(&self) -> Result<HttpEncoding, HttpError> {
+        match self.get_header("content-encoding") {
+            None => Ok(HttpEncoding::Identity),
+            Some(b"identity") => Ok(HttpEncoding::Identity),
+            Some(b"deflate") => Ok(HttpEncoding::Deflate),
+            Some(b"gzip") => Ok(HttpEncoding::Gzip),
+            Some(b"br") => Ok(HttpEncoding::Br),
+            Some(b"zstd") => Ok(HttpEncoding::Zstd),
+            Some(other) => Err(HttpError::UnknownEncoding(
+                String::from_utf8_lossy(other).into_owned(),
+            )),
+        }
+    }
+
     pub fn get_header(&self, key: &str) -> Option<&[u8]> {
         match self {
             Response::Actix(response) => Some(response.headers().get(key)?.as_bytes()),
@@ -288,31 +511,35 @@ impl Response {
     }
 
     pub fn bytes(self, limit: usize) -> ResponseFuture<Vec<u8>, HttpError> {
+        // Resolve the encoding before `self` is consumed by the match below: both arms need it
+        // to pick the right decoder, but neither owns a `Response` to ask afterwards.
+        let content_encoding = self.get_header("content-encoding").map(|h| h.to_vec());
+
         match self {
             Response::Actix(response) => Box::new(
                 response
-                    .body()
-                    .limit(limit)
-                    .map(|body| body.to_vec())
-                    .map_err(HttpError::ActixPayload),
+                    .payload()
+                    .map_err(HttpError::ActixPayload)
+                    .fold(
+                        BodyDecoder::new(content_encoding.as_deref()),
+                        move |mut decoder, chunk| decoder.write_chunk(&chunk, limit).map(|_| decoder),
+                    )
+                    .and_then(BodyDecoder::finish),
             ) as Box<dyn Future<Item = _, Error = _>>,
             Response::Reqwest(response) => Box::new(
                 response
                     .bytes_stream()
                     .map_err(HttpError::Reqwest)
                     .try_fold(
-                        Vec::with_capacity(8192),
-                        move |mut body, chunk| async move {
-                            if (body.len() + chunk.len()) > limit {
-                                Err(HttpError::Overflow)
-                            } else {
-                                body.extend_from_slice(&chunk);
-                                Ok(body)
-                            }
+                        BodyDecoder::new(content_encoding.as_deref()),
+                        move |mut decoder, chunk| async move {
+                            decoder.write_chunk(&chunk, limit)?;
+                            Ok(decoder)
                         },
                     )
                     .boxed_local()
-                    .compat(),
+                    .compat()
+                    .and_then(BodyDecoder::finish),
             ) as Box<dyn Future<Item = _, Error = _>>,
         }
     }
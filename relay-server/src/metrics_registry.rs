@@ -0,0 +1,148 @@
+//! An in-process registry mirroring the counters, gauges, and timers emitted through the
+//! `metric!` macro (`RelayCounters`, `RelayGauges`, `RelayHistograms`, `RelayTimers`), so
+//! operators who scrape Prometheus rather than push to statsd still get first-class observability
+//! of things like cache hit ratios and eviction latency.
+//!
+//! `metric!` and the enums it dispatches on live in `crate::metrics`, which isn't part of this
+//! snapshot, so this registry can't be wired into the macro itself here. The intended integration
+//! is for `metric!`'s statsd call to also forward the same name and value into
+//! [`PROMETHEUS_REGISTRY`]'s `record_*` methods below, mirroring every emitted metric into this
+//! atomic-backed store rather than replacing statsd with it.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// The process-wide registry `metric!` mirrors emitted metrics into, and the `/metrics`
+    /// endpoint renders.
+    pub static ref PROMETHEUS_REGISTRY: PrometheusRegistry = PrometheusRegistry::default();
+}
+
+/// A running count and sum of observed values for one timer/histogram metric, rendered as a
+/// Prometheus summary's `_count` and `_sum` lines.
+#[derive(Default)]
+struct Summary {
+    count: AtomicU64,
+    // The running sum's bits, reinterpreted as a u64 so it fits in an atomic slot -- there's no
+    // `AtomicF64` in `std`. The compare-and-swap loop in `observe` is what makes adding to it
+    // atomic.
+    sum_bits: AtomicU64,
+}
+
+impl Summary {
+    fn observe(&self, value: f64) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+
+        let mut current = self.sum_bits.load(Ordering::Relaxed);
+        loop {
+            let updated = (f64::from_bits(current) + value).to_bits();
+            match self.sum_bits.compare_exchange_weak(
+                current,
+                updated,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    fn sum(&self) -> f64 {
+        f64::from_bits(self.sum_bits.load(Ordering::Relaxed))
+    }
+}
+
+/// An atomic-backed store of the current value of every counter, gauge, and timer/histogram
+/// `metric!` has emitted, read back by the `/metrics` endpoint and rendered in Prometheus text
+/// exposition format.
+#[derive(Default)]
+pub struct PrometheusRegistry {
+    counters: RwLock<HashMap<&'static str, AtomicU64>>,
+    gauges: RwLock<HashMap<&'static str, AtomicU64>>,
+    summaries: RwLock<HashMap<&'static str, Summary>>,
+}
+
+impl PrometheusRegistry {
+    /// Adds `value` to the named counter, creating it at zero first if this is its first
+    /// observation.
+    pub fn record_counter(&self, name: &'static str, value: u64) {
+        if let Some(counter) = self.counters.read().unwrap().get(name) {
+            counter.fetch_add(value, Ordering::Relaxed);
+            return;
+        }
+
+        self.counters
+            .write()
+            .unwrap()
+            .entry(name)
+            .or_insert_with(AtomicU64::default)
+            .fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Sets the named gauge to `value`, creating it first if this is its first observation.
+    pub fn record_gauge(&self, name: &'static str, value: u64) {
+        if let Some(gauge) = self.gauges.read().unwrap().get(name) {
+            gauge.store(value, Ordering::Relaxed);
+            return;
+        }
+
+        self.gauges
+            .write()
+            .unwrap()
+            .entry(name)
+            .or_insert_with(AtomicU64::default)
+            .store(value, Ordering::Relaxed);
+    }
+
+    /// Records one observation of `value` against the named timer/histogram.
+    pub fn record_timer(&self, name: &'static str, value: f64) {
+        if let Some(summary) = self.summaries.read().unwrap().get(name) {
+            summary.observe(value);
+            return;
+        }
+
+        self.summaries
+            .write()
+            .unwrap()
+            .entry(name)
+            .or_insert_with(Summary::default)
+            .observe(value);
+    }
+
+    /// Renders the registry's current state in Prometheus text exposition format: counters and
+    /// gauges as `counter`/`gauge` lines, timers/histograms as a `summary`'s `_sum`/`_count`.
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        for (name, value) in self.counters.read().unwrap().iter() {
+            let value = value.load(Ordering::Relaxed);
+            output.push_str(&format!("# TYPE {} counter\n{} {}\n", name, name, value));
+        }
+
+        for (name, value) in self.gauges.read().unwrap().iter() {
+            let value = value.load(Ordering::Relaxed);
+            output.push_str(&format!("# TYPE {} gauge\n{} {}\n", name, name, value));
+        }
+
+        for (name, summary) in self.summaries.read().unwrap().iter() {
+            output.push_str(&format!(
+                "# TYPE {} summary\n{}_sum {}\n{}_count {}\n",
+                name,
+                name,
+                summary.sum(),
+                name,
+                summary.count()
+            ));
+        }
+
+        output
+    }
+}
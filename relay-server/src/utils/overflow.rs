@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use relay_config::Config;
+
+/// How long a partition key's token bucket may sit idle before [`OverflowLimiter::evict_idle`]
+/// reclaims it.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A token bucket tracking how many messages a single partition key has produced recently.
+struct TokenBucket {
+    /// Tokens currently available, up to `burst_limit`.
+    tokens: f64,
+    /// When `tokens` was last updated.
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(burst_limit: f64) -> Self {
+        Self {
+            tokens: burst_limit,
+            updated_at: Instant::now(),
+        }
+    }
+
+    /// Refills the bucket for elapsed time, then attempts to take one token.
+    ///
+    /// Returns `true` if a token was available and has been taken.
+    fn try_take(&mut self, per_second_limit: f64, burst_limit: f64) -> bool {
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_second_limit).min(burst_limit);
+        self.updated_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_idle(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.updated_at) > IDLE_TIMEOUT
+    }
+}
+
+/// Per-key overflow protection for the Kafka producer.
+///
+/// Relay pins each produced message to a partition derived from its project/event key, so that
+/// related messages land on the same partition. That is the common case we want, but a single
+/// noisy project can then saturate one Kafka partition while the rest sit idle. `OverflowLimiter`
+/// maintains a token bucket per key (`overflow_per_second_limit` refill rate,
+/// `overflow_burst_limit` capacity); once a key's bucket runs dry, [`OverflowLimiter::check`]
+/// returns `true` and the caller should stop pinning that message to its natural partition,
+/// letting it spread across all partitions instead. Keys listed in `overflow_forced_keys` always
+/// report as overflowing.
+///
+/// When `overflow_enabled` is `false` in the config, [`OverflowLimiter::check`] always returns
+/// `false` without touching the map, so disabled Relays pay no overhead.
+pub struct OverflowLimiter {
+    enabled: bool,
+    per_second_limit: f64,
+    burst_limit: f64,
+    forced_keys: Option<std::collections::BTreeSet<String>>,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl OverflowLimiter {
+    /// Creates a limiter from the overflow settings in `config`.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            enabled: config.overflow_enabled(),
+            per_second_limit: config.overflow_per_second_limit().get() as f64,
+            burst_limit: config.overflow_burst_limit().get() as f64,
+            forced_keys: config.overflow_forced_keys().cloned(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns `true` if a message for `key` should overflow to a non-keyed partition rather than
+    /// its natural one.
+    pub fn check(&self, key: &str) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        if let Some(forced_keys) = &self.forced_keys {
+            if forced_keys.contains(key) {
+                return true;
+            }
+        }
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(key.to_owned())
+            .or_insert_with(|| TokenBucket::new(self.burst_limit));
+
+        !bucket.try_take(self.per_second_limit, self.burst_limit)
+    }
+
+    /// Evicts token buckets that have not been touched in a while, so the map does not grow
+    /// unbounded as projects come and go. Intended to be called periodically, e.g. from a
+    /// recurring background task.
+    pub fn evict_idle(&self) {
+        let now = Instant::now();
+        self.buckets.lock().unwrap().retain(|_, bucket| !bucket.is_idle(now));
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fmt::{self, Write},
     net::IpAddr,
     time::Instant,
@@ -7,8 +8,8 @@ use std::{
 use actix::Addr;
 use relay_general::protocol::EventId;
 use relay_quotas::{
-    DataCategories, DataCategory, ItemScoping, QuotaScope, RateLimit, RateLimitScope, RateLimits,
-    ReasonCode, Scoping,
+    DataCategories, DataCategory, ItemScoping, MetricNamespace, MetricNamespaces, QuotaScope,
+    RateLimit, RateLimitScope, RateLimits, ReasonCode, Scoping,
 };
 
 use crate::{
@@ -20,6 +21,11 @@ use crate::{
 pub const RATE_LIMITS_HEADER: &str = "X-Sentry-Rate-Limits";
 
 /// Formats the `X-Sentry-Rate-Limits` header.
+///
+/// The format is `retry_after:categories:scope:reason_code`, with an optional fifth
+/// `;`-separated `namespaces` component listing the metric namespaces the limit applies to. The
+/// namespaces component is only written when non-empty, in which case the reason code component
+/// is always written too (even if empty), so that `namespaces` stays in a fixed fifth position.
 pub fn format_rate_limits(rate_limits: &RateLimits) -> String {
     let mut header = String::new();
 
@@ -39,8 +45,23 @@ pub fn format_rate_limits(rate_limits: &RateLimits) -> String {
 
         write!(header, ":{}", rate_limit.scope.name()).ok();
 
-        if let Some(ref reason_code) = rate_limit.reason_code {
-            write!(header, ":{}", reason_code).ok();
+        let has_namespaces = !rate_limit.namespaces.is_empty();
+
+        if has_namespaces || rate_limit.reason_code.is_some() {
+            header.push(':');
+            if let Some(ref reason_code) = rate_limit.reason_code {
+                write!(header, "{}", reason_code).ok();
+            }
+        }
+
+        if has_namespaces {
+            header.push(':');
+            for (index, namespace) in rate_limit.namespaces.iter().enumerate() {
+                if index > 0 {
+                    header.push(';');
+                }
+                write!(header, "{}", namespace).ok();
+            }
         }
     }
 
@@ -48,6 +69,9 @@ pub fn format_rate_limits(rate_limits: &RateLimits) -> String {
 }
 
 /// Parses the `X-Sentry-Rate-Limits` header.
+///
+/// The optional fifth `namespaces` component is backward compatible: if it is missing, the limit
+/// applies to an empty namespace set, meaning "all namespaces".
 pub fn parse_rate_limits(scoping: &Scoping, string: &str) -> RateLimits {
     let mut rate_limits = RateLimits::new();
 
@@ -74,13 +98,24 @@ pub fn parse_rate_limits(scoping: &Scoping, string: &str) -> RateLimits {
         let quota_scope = QuotaScope::from_name(components.next().unwrap_or(""));
         let scope = RateLimitScope::for_quota(scoping, quota_scope);
 
-        let reason_code = components.next().map(ReasonCode::new);
+        let reason_code = components
+            .next()
+            .filter(|s| !s.is_empty())
+            .map(ReasonCode::new);
+
+        let mut namespaces = MetricNamespaces::new();
+        for namespace in components.next().unwrap_or("").split(';') {
+            if !namespace.is_empty() {
+                namespaces.push(MetricNamespace::from_name(namespace));
+            }
+        }
 
         rate_limits.add(RateLimit {
             categories,
             scope,
             reason_code,
             retry_after,
+            namespaces,
         });
     }
 
@@ -111,21 +146,67 @@ fn infer_event_category(item: &Item) -> Option<DataCategory> {
     }
 }
 
+/// Returns the "indexed" counterpart of a stored data category, if it has one.
+///
+/// A stored category (e.g. `Transaction`) is rate limited independently of whether the event's
+/// full payload is retained after dynamic sampling (`TransactionIndexed`): a transaction can be
+/// over quota for storage while still having indexed-payload quota left, or vice versa. Most
+/// categories have no indexed counterpart and are only ever rate limited once, at ingest.
+fn indexed_category(category: DataCategory) -> Option<DataCategory> {
+    match category {
+        DataCategory::Transaction => Some(DataCategory::TransactionIndexed),
+        _ => None,
+    }
+}
+
+/// Returns `true` if `category` is the indexed counterpart of some stored category.
+fn is_indexed_category(category: DataCategory) -> bool {
+    matches!(category, DataCategory::TransactionIndexed)
+}
+
 /// A summary of `Envelope` contents.
 ///
 /// Summarizes the contained event, size of attachments, session updates, and whether there are
 /// plain attachments. This is used for efficient rate limiting or outcome handling.
 #[non_exhaustive]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct EnvelopeSummary {
     /// The data category of the event in the envelope. `None` if there is no event.
     pub event_category: Option<DataCategory>,
 
-    /// The quantity of all attachments combined in bytes.
-    pub attachment_quantity: usize,
+    /// The indexed-payload counterpart of `event_category`, if the category has one.
+    ///
+    /// Unlike `event_category`, this quota must not be enforced at ingest: whether the event's
+    /// payload is actually retained depends on a sampling decision that isn't known yet.
+    pub event_indexed_category: Option<DataCategory>,
+
+    /// Accumulated quantities for categories that have their own quota independent of the event,
+    /// such as attachment bytes or session counts.
+    ///
+    /// `EnvelopeLimiter::execute` checks the quota of every entry here generically. Removing
+    /// items and emitting outcomes covers only the event, attachment and session categories, so
+    /// items of another category (such as metric buckets) are kept even when their quota is hit.
+    pub quantities: BTreeMap<DataCategory, usize>,
 
-    /// The number of all session updates.
-    pub session_quantity: usize,
+    /// The number of spans contained in the transaction, if any.
+    ///
+    /// Spans are extracted from the transaction during processing and never appear as a
+    /// standalone item in the envelope, but still consume their own quota.
+    pub span_count: usize,
+
+    /// Whether the transaction in the envelope carries a profile.
+    ///
+    /// Like spans, the profile is extracted from the transaction during processing rather than
+    /// appearing as a standalone item.
+    pub has_profile: bool,
+
+    /// The metric namespace of the `Metrics`/`MetricBuckets` items accounted for in `quantities`,
+    /// if any.
+    ///
+    /// Namespace-scoped quotas (e.g. a custom-metrics limit) must only match buckets in that
+    /// namespace, so `EnvelopeLimiter::execute` passes this into the `ItemScoping` it checks the
+    /// `MetricBucket` category with.
+    pub metric_namespace: Option<MetricNamespace>,
 
     /// Indicates that the envelope contains regular attachments that do not create event payloads.
     pub has_plain_attachments: bool,
@@ -165,8 +246,22 @@ impl EnvelopeSummary {
             }
 
             match item.ty() {
-                ItemType::Attachment => summary.attachment_quantity += item.len().max(1),
-                ItemType::Session => summary.session_quantity += 1,
+                ItemType::Attachment => {
+                    *summary.quantities.entry(DataCategory::Attachment).or_insert(0) +=
+                        item.len().max(1);
+                }
+                ItemType::Session => {
+                    *summary.quantities.entry(DataCategory::Session).or_insert(0) += 1;
+                }
+                ItemType::Transaction => {
+                    summary.span_count = item.span_count();
+                    summary.has_profile = item.has_profile();
+                }
+                ItemType::Metrics | ItemType::MetricBuckets => {
+                    *summary.quantities.entry(DataCategory::MetricBucket).or_insert(0) +=
+                        item.len().max(1);
+                    summary.metric_namespace = item.metric_namespace();
+                }
                 _ => (),
             }
         }
@@ -178,21 +273,12 @@ impl EnvelopeSummary {
         if matches!(self.event_category, None | Some(DataCategory::Default)) {
             if let Some(category) = infer_event_category(item) {
                 self.event_category = Some(category);
+                self.event_indexed_category = indexed_category(category);
             }
         }
     }
 }
 
-struct ItemRetention {
-    applied_limit: Option<RateLimit>,
-    retain_in_envelope: bool,
-}
-
-struct RateLimitForItem {
-    applied_limit: RateLimit,
-    item_category: DataCategory,
-}
-
 /// Enforces rate limits with the given `check` function on items in the envelope.
 ///
 /// The `check` function is called with the following rules:
@@ -207,9 +293,7 @@ struct RateLimitForItem {
 pub struct EnvelopeLimiter<F> {
     check: F,
     event_category: Option<DataCategory>,
-    event_limit: Option<RateLimit>,
-    attachment_limit: Option<RateLimit>,
-    session_limit: Option<RateLimit>,
+    category_limits: BTreeMap<DataCategory, RateLimit>,
 }
 
 impl<E, F> EnvelopeLimiter<F>
@@ -221,9 +305,7 @@ where
         Self {
             check,
             event_category: None,
-            event_limit: None,
-            attachment_limit: None,
-            session_limit: None,
+            category_limits: BTreeMap::new(),
         }
     }
 
@@ -238,6 +320,11 @@ where
     }
 
     /// Process rate limits for the envelope, removing offending items and returning applied limits.
+    ///
+    /// This only enforces stored-category quotas (e.g. `Transaction`), which are known at ingest.
+    /// Indexed-category quotas (e.g. `TransactionIndexed`) depend on a sampling decision that
+    /// isn't made yet and must be enforced separately via [`enforce_indexed_limits`] once that
+    /// decision is known.
     pub fn enforce(
         mut self,
         envelope: &mut Envelope,
@@ -246,115 +333,188 @@ where
         let mut summary = EnvelopeSummary::compute(envelope);
         if let Some(event_category) = self.event_category {
             summary.event_category = Some(event_category);
+            summary.event_indexed_category = indexed_category(event_category);
         }
 
         let applied_limits = self.execute(&summary, scoping)?;
-        let limited_items = self.apply_retention(envelope);
+        let enforcement = self.build_enforcement(&summary);
+        self.apply_retention(envelope, &summary);
+
         Ok(RateLimitEnforcement {
             summary,
             applied_limits,
-            limited_items,
+            enforcement,
         })
     }
 
-    fn apply_retention(&mut self, envelope: &mut Envelope) -> Vec<RateLimitForItem> {
-        let mut applied_limits = vec![];
-        envelope.retain_items(|item| {
-            let retention = self.retain_item(item);
-            if let Some(applied_limit) = retention.applied_limit {
-                if let Some(item_category) = infer_event_category(item) {
-                    applied_limits.push(RateLimitForItem {
-                        applied_limit,
-                        item_category,
-                    })
+    /// Builds the per-category outcome of this enforcement from the limits applied in `execute`.
+    ///
+    /// In addition to the categories that correspond to an actual item (event, attachments,
+    /// sessions), this derives quota for categories that have no item of their own in the
+    /// envelope but are extracted from the event during processing -- spans and the profile
+    /// embedded in a transaction, and the transaction's own indexed payload. A derived category
+    /// is only populated when its parent was actually rate limited, and mirrors the parent's
+    /// reason code.
+    fn build_enforcement(&self, summary: &EnvelopeSummary) -> Enforcement {
+        let mut enforcement = Enforcement::default();
+
+        if let Some(category) = summary.event_category {
+            if let Some(limit) = self.category_limits.get(&category) {
+                let event_limit = CategoryLimit::new(category, 1, limit);
+
+                if category == DataCategory::Transaction {
+                    // The stored transaction was rejected outright, so no indexed payload will
+                    // ever be produced for it either -- mirror the outcome onto `TransactionIndexed`
+                    // the same way `spans`/`spans_indexed` and `profiles` mirror it for the data
+                    // a transaction carries. This is independent of `CachedRateLimits` dropping
+                    // indexed-category limits from the *cache*: that's about not reusing an
+                    // indexed limit against a future envelope before its own sampling decision is
+                    // known, not about accounting for the one envelope whose event was just
+                    // rejected here.
+                    enforcement.transaction_indexed = Some(CategoryLimit::derived(
+                        DataCategory::TransactionIndexed,
+                        1,
+                        &event_limit,
+                    ));
+
+                    if summary.span_count > 0 {
+                        enforcement.spans = Some(CategoryLimit::derived(
+                            DataCategory::Span,
+                            summary.span_count,
+                            &event_limit,
+                        ));
+                        enforcement.spans_indexed = Some(CategoryLimit::derived(
+                            DataCategory::SpanIndexed,
+                            summary.span_count,
+                            &event_limit,
+                        ));
+                    }
+
+                    if summary.has_profile {
+                        enforcement.profiles = Some(CategoryLimit::derived(
+                            DataCategory::Profile,
+                            1,
+                            &event_limit,
+                        ));
+                    }
                 }
+
+                enforcement.event = Some(event_limit);
             }
-            retention.retain_in_envelope
-        });
+        }
+
+        if let Some(limit) = self.category_limits.get(&DataCategory::Attachment) {
+            if summary.has_plain_attachments {
+                let quantity = summary
+                    .quantities
+                    .get(&DataCategory::Attachment)
+                    .copied()
+                    .unwrap_or(0);
+                enforcement.attachments =
+                    Some(CategoryLimit::new(DataCategory::Attachment, quantity, limit));
+            }
+        }
 
-        applied_limits
+        if let Some(limit) = self.category_limits.get(&DataCategory::Session) {
+            let quantity = summary
+                .quantities
+                .get(&DataCategory::Session)
+                .copied()
+                .unwrap_or(0);
+            enforcement.sessions = Some(CategoryLimit::new(DataCategory::Session, quantity, limit));
+        }
+
+        enforcement
     }
 
+    fn apply_retention(&mut self, envelope: &mut Envelope, summary: &EnvelopeSummary) {
+        envelope.retain_items(|item| self.retain_item(item, summary));
+    }
+
+    /// Checks quotas for the event category, then for every other category with an accumulated
+    /// quantity in `summary.quantities`.
+    ///
+    /// New item-derived categories are picked up automatically here as soon as
+    /// `EnvelopeSummary::compute` populates an entry for them. `MetricBucket` is the one
+    /// exception: it also carries a namespace, so namespace-scoped quotas only match buckets in
+    /// that namespace instead of suppressing every namespace at once.
     fn execute(&mut self, summary: &EnvelopeSummary, scoping: &Scoping) -> Result<RateLimits, E> {
         let mut rate_limits = RateLimits::new();
 
         if let Some(category) = summary.event_category {
             let event_limits = (&mut self.check)(scoping.item(category), 1)?;
-            self.event_limit = event_limits.get_active_limit().map(RateLimit::clone);
+            if let Some(limit) = event_limits.get_active_limit() {
+                self.category_limits.insert(category, limit.clone());
+            }
             rate_limits.merge(event_limits);
         }
 
-        if self.event_limit.is_none() && summary.attachment_quantity > 0 {
-            let item_scoping = scoping.item(DataCategory::Attachment);
-            let attachment_limits = (&mut self.check)(item_scoping, summary.attachment_quantity)?;
-            self.attachment_limit = attachment_limits.get_active_limit().map(RateLimit::clone);
+        let event_limited = summary
+            .event_category
+            .map_or(false, |category| self.category_limits.contains_key(&category));
+
+        for (&category, &quantity) in &summary.quantities {
+            if quantity == 0 {
+                continue;
+            }
+
+            // The event was already rejected, so Sentry will never see it to match attachments
+            // against; skip the attachment quota entirely in that case.
+            if category == DataCategory::Attachment && event_limited {
+                continue;
+            }
+
+            let mut item_scoping = scoping.item(category);
+            if category == DataCategory::MetricBucket {
+                if let Some(namespace) = summary.metric_namespace {
+                    item_scoping = item_scoping.namespace(namespace);
+                }
+            }
+
+            let limits = (&mut self.check)(item_scoping, quantity)?;
+            if let Some(limit) = limits.get_active_limit() {
+                self.category_limits.insert(category, limit.clone());
+            }
 
             // Only record rate limits for plain attachments. For all other attachments, it's
             // perfectly "legal" to send them. They will still be discarded in Sentry, but clients
             // can continue to send them.
-            if summary.has_plain_attachments {
-                rate_limits.merge(attachment_limits);
+            if category == DataCategory::Attachment && !summary.has_plain_attachments {
+                continue;
             }
-        }
 
-        if summary.session_quantity > 0 {
-            let item_scoping = scoping.item(DataCategory::Session);
-            let session_limits = (&mut self.check)(item_scoping, summary.session_quantity)?;
-            self.session_limit = session_limits.get_active_limit().map(RateLimit::clone);
-            rate_limits.merge(session_limits);
+            rate_limits.merge(limits);
         }
 
         Ok(rate_limits)
     }
 
-    fn retain_item(&self, item: &mut Item) -> ItemRetention {
+    fn retain_item(&self, item: &mut Item, summary: &EnvelopeSummary) -> bool {
         // Remove event items and all items that depend on this event
-        if let Some(event_limit) = &self.event_limit {
-            if item.requires_event() {
-                return ItemRetention {
-                    applied_limit: Some(event_limit.clone()),
-                    retain_in_envelope: false,
-                };
+        if let Some(event_category) = summary.event_category {
+            if item.requires_event() && self.category_limits.contains_key(&event_category) {
+                return false;
             }
         }
 
         // Remove attachments, except those required for processing
-        if let Some(attachment_limit) = &self.attachment_limit {
-            if item.ty() == ItemType::Attachment {
-                if item.creates_event() {
-                    let applied_limit = if item.rate_limited() {
-                        None
-                    } else {
-                        item.set_rate_limited(true);
-                        Some(attachment_limit.clone())
-                    };
-                    return ItemRetention {
-                        applied_limit,
-                        retain_in_envelope: true,
-                    };
-                } else {
-                    return ItemRetention {
-                        applied_limit: Some(attachment_limit.clone()),
-                        retain_in_envelope: false,
-                    };
-                }
-            }
+        let attachment_limited = self.category_limits.contains_key(&DataCategory::Attachment);
+        if item.ty() == ItemType::Attachment && attachment_limited {
+            return if item.creates_event() {
+                item.set_rate_limited(true);
+                true
+            } else {
+                false
+            };
         }
 
         // Remove sessions independently of events
-        if let Some(session_limit) = &self.session_limit {
-            if item.ty() == ItemType::Session {
-                return ItemRetention {
-                    applied_limit: Some(session_limit.clone()),
-                    retain_in_envelope: false,
-                };
-            }
+        let session_limited = self.category_limits.contains_key(&DataCategory::Session);
+        if item.ty() == ItemType::Session && session_limited {
+            return false;
         }
 
-        ItemRetention {
-            applied_limit: None,
-            retain_in_envelope: true,
-        }
+        true
     }
 }
 
@@ -362,41 +522,169 @@ impl<F> fmt::Debug for EnvelopeLimiter<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("EnvelopeLimiter")
             .field("event_category", &self.event_category)
-            .field("event_limit", &self.event_limit)
-            .field("attachment_limit", &self.attachment_limit)
-            .field("session_limit", &self.session_limit)
+            .field("category_limits", &self.category_limits)
             .finish()
     }
 }
 
+/// One category's contribution to a [`RateLimitEnforcement`].
+///
+/// Most entries correspond to an item that was actually present in the envelope (e.g.
+/// `Attachment`). Others are "derived": they have no item of their own in the envelope, but are
+/// extracted from one during processing (e.g. `Span`/`SpanIndexed` from a transaction's spans, or
+/// `Profile` from a profiled transaction) and must still produce a dropped-outcome when their
+/// parent category is rate limited, or accepted/dropped accounting undercounts what Relay drops.
+#[derive(Clone, Debug)]
+pub struct CategoryLimit {
+    /// The data category this entry accounts for.
+    pub category: DataCategory,
+    /// The quantity of `category` represented by this entry.
+    pub quantity: usize,
+    /// The reason code of the rate limit that was applied.
+    pub reason_code: Option<ReasonCode>,
+    /// Whether `category` was rate limited.
+    pub active: bool,
+}
+
+impl CategoryLimit {
+    /// Creates a limit for a category that has its own applied rate limit.
+    fn new(category: DataCategory, quantity: usize, limit: &RateLimit) -> Self {
+        Self {
+            category,
+            quantity,
+            reason_code: limit.reason_code.clone(),
+            active: true,
+        }
+    }
+
+    /// Creates a limit for a category derived from `parent`, with no item of its own in the
+    /// envelope.
+    ///
+    /// Mirrors `parent`'s outcome: a derived category is limited exactly when the category it is
+    /// derived from is limited, using the same reason code.
+    fn derived(category: DataCategory, quantity: usize, parent: &CategoryLimit) -> Self {
+        Self {
+            category,
+            quantity,
+            reason_code: parent.reason_code.clone(),
+            active: parent.active,
+        }
+    }
+}
+
+/// The per-category outcome of an [`EnvelopeLimiter::enforce`] call.
+///
+/// Populated by [`EnvelopeLimiter::build_enforcement`]; see [`CategoryLimit`] for the distinction
+/// between item-backed and derived categories.
+#[derive(Clone, Debug, Default)]
+pub struct Enforcement {
+    pub event: Option<CategoryLimit>,
+    pub attachments: Option<CategoryLimit>,
+    pub sessions: Option<CategoryLimit>,
+    pub spans: Option<CategoryLimit>,
+    pub spans_indexed: Option<CategoryLimit>,
+    pub profiles: Option<CategoryLimit>,
+    pub transaction_indexed: Option<CategoryLimit>,
+}
+
+impl Enforcement {
+    /// Iterates all populated category limits.
+    fn iter(&self) -> impl Iterator<Item = &CategoryLimit> {
+        [
+            &self.event,
+            &self.attachments,
+            &self.sessions,
+            &self.spans,
+            &self.spans_indexed,
+            &self.profiles,
+            &self.transaction_indexed,
+        ]
+        .into_iter()
+        .filter_map(Option::as_ref)
+    }
+}
+
 pub struct RateLimitEnforcement {
     pub applied_limits: RateLimits,
+    pub enforcement: Enforcement,
     summary: EnvelopeSummary,
-    limited_items: Vec<RateLimitForItem>,
 }
 
 impl RateLimitEnforcement {
     pub fn emit_outcomes(&self, scoping: &Scoping, outcome_producer: &Addr<OutcomeProducer>) {
         let timestamp = Instant::now();
-        for limited_item in self.limited_items.iter() {
-            let reason_code = &limited_item.applied_limit.reason_code;
-            let category = limited_item.item_category;
+        for limit in self.enforcement.iter().filter(|limit| limit.active) {
             outcome_producer.do_send(TrackOutcome {
                 timestamp,
                 scoping: *scoping,
-                outcome: Outcome::RateLimited(reason_code.clone()),
+                outcome: Outcome::RateLimited(limit.reason_code.clone()),
                 event_id: self.summary.event_id,
                 remote_addr: self.summary.remote_addr,
-                category,
-                quantity: match category {
-                    DataCategory::Attachment => self.summary.attachment_quantity,
-                    _ => 1,
-                },
+                category: limit.category,
+                quantity: limit.quantity,
             });
         }
     }
 }
 
+/// Enforces the indexed-category quota for an envelope's event, once its sampling decision is
+/// known.
+///
+/// This is the second pass referred to by [`EnvelopeLimiter::enforce`]: it must only be called
+/// for events that sampling retained. A dropped event never consumes its indexed quota, since no
+/// payload will be stored for it.
+pub fn enforce_indexed_limits<E>(
+    summary: &EnvelopeSummary,
+    scoping: &Scoping,
+    mut check: impl FnMut(ItemScoping<'_>, usize) -> Result<RateLimits, E>,
+) -> Result<RateLimits, E> {
+    let mut rate_limits = RateLimits::new();
+
+    if let Some(category) = summary.event_indexed_category {
+        let indexed_limits = check(scoping.item(category), 1)?;
+        rate_limits.merge(indexed_limits);
+    }
+
+    Ok(rate_limits)
+}
+
+/// A subset of [`RateLimits`] that is safe to persist on the project cache for reuse across
+/// envelopes.
+///
+/// Indexed-category limits (e.g. `TransactionIndexed`) are only meaningful for the envelope whose
+/// sampling decision produced them. Caching one and replaying it against a later envelope would
+/// apply it before that envelope's own sampling verdict is known, which is never correct -- so
+/// [`CachedRateLimits::new`] drops any indexed-category limit. It also drops already-expired
+/// limits, since a cache has no reason to keep entries that can never match again.
+#[derive(Debug)]
+pub struct CachedRateLimits(RateLimits);
+
+impl CachedRateLimits {
+    /// Creates the subset of `rate_limits` that is safe to persist for reuse.
+    pub fn new(rate_limits: &RateLimits) -> Self {
+        let mut cached = RateLimits::new();
+
+        for rate_limit in rate_limits {
+            if rate_limit.retry_after.remaining_seconds() == 0 {
+                continue;
+            }
+
+            if rate_limit.categories.iter().any(|c| is_indexed_category(*c)) {
+                continue;
+            }
+
+            cached.add(rate_limit.clone());
+        }
+
+        Self(cached)
+    }
+
+    /// Returns the cached rate limits.
+    pub fn as_rate_limits(&self) -> &RateLimits {
+        &self.0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -420,6 +708,7 @@ mod tests {
             scope: RateLimitScope::Organization(42),
             reason_code: Some(ReasonCode::new("my_limit")),
             retry_after: RetryAfter::from_secs(42),
+            namespaces: MetricNamespaces::new(),
         });
 
         // Add a more specific rate limit for just one category.
@@ -428,6 +717,7 @@ mod tests {
             scope: RateLimitScope::Project(ProjectId::new(21)),
             reason_code: None,
             retry_after: RetryAfter::from_secs(4711),
+            namespaces: MetricNamespaces::new(),
         });
 
         let formatted = format_rate_limits(&rate_limits);
@@ -435,6 +725,37 @@ mod tests {
         assert_eq!(formatted, expected);
     }
 
+    #[test]
+    fn test_format_rate_limits_with_namespaces() {
+        let mut rate_limits = RateLimits::new();
+
+        rate_limits.add(RateLimit {
+            categories: smallvec![DataCategory::MetricBucket],
+            scope: RateLimitScope::Organization(42),
+            reason_code: None,
+            retry_after: RetryAfter::from_secs(42),
+            namespaces: smallvec![MetricNamespace::Custom, MetricNamespace::Transactions],
+        });
+
+        let formatted = format_rate_limits(&rate_limits);
+        let expected = "42:metric_bucket:organization::custom;transactions";
+        assert_eq!(formatted, expected);
+
+        let scoping = Scoping {
+            organization_id: 42,
+            project_id: ProjectId::new(21),
+            public_key: ProjectKey::parse("a94ae32be2584e0bbd7a4cbb95971fee").unwrap(),
+            key_id: Some(17),
+        };
+        let parsed: Vec<RateLimit> = parse_rate_limits(&scoping, &formatted).into_iter().collect();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(
+            parsed[0].namespaces,
+            smallvec![MetricNamespace::Custom, MetricNamespace::Transactions]
+        );
+        assert!(parsed[0].reason_code.is_none());
+    }
+
     #[test]
     fn test_parse_invalid_rate_limits() {
         let scoping = Scoping {
@@ -472,6 +793,7 @@ mod tests {
                     scope: RateLimitScope::Organization(42),
                     reason_code: Some(ReasonCode::new("my_limit")),
                     retry_after: rate_limits[0].retry_after,
+                    namespaces: MetricNamespaces::new(),
                 },
                 RateLimit {
                     categories: smallvec![
@@ -482,6 +804,7 @@ mod tests {
                     scope: RateLimitScope::Project(ProjectId::new(21)),
                     reason_code: None,
                     retry_after: rate_limits[1].retry_after,
+                    namespaces: MetricNamespaces::new(),
                 }
             ]
         );
@@ -511,6 +834,7 @@ mod tests {
                 scope: RateLimitScope::Organization(42),
                 reason_code: None,
                 retry_after: rate_limits[0].retry_after,
+                namespaces: MetricNamespaces::new(),
             },]
         );
     }
@@ -545,6 +869,7 @@ mod tests {
             scope: RateLimitScope::Organization(42),
             reason_code: None,
             retry_after: RetryAfter::from_secs(60),
+            namespaces: MetricNamespaces::new(),
         }
     }
 
@@ -615,6 +940,34 @@ mod tests {
         mock.assert_call(DataCategory::Session, None);
     }
 
+    #[test]
+    fn test_enforce_limit_transaction_emits_category_limit() {
+        let mut envelope = envelope![Transaction];
+
+        let mut mock = MockLimiter::default().deny(DataCategory::Transaction);
+        let enforcement = EnvelopeLimiter::new(|s, q| mock.check(s, q))
+            .enforce(&mut envelope, &scoping())
+            .unwrap()
+            .enforcement;
+
+        let event = enforcement.event.expect("event category limit");
+        assert_eq!(event.category, DataCategory::Transaction);
+        assert_eq!(event.quantity, 1);
+        assert!(event.active);
+
+        // The test transaction carries no spans or profile, so nothing is derived for those --
+        // but `TransactionIndexed` is derived unconditionally, since no indexed payload will ever
+        // be produced for a transaction that was rejected at the stored-category quota.
+        assert!(enforcement.spans.is_none());
+        assert!(enforcement.spans_indexed.is_none());
+        assert!(enforcement.profiles.is_none());
+        let transaction_indexed = enforcement
+            .transaction_indexed
+            .expect("derived transaction_indexed limit");
+        assert_eq!(transaction_indexed.category, DataCategory::TransactionIndexed);
+        assert!(transaction_indexed.active);
+    }
+
     #[test]
     fn test_enforce_limit_error_with_attachments() {
         let mut envelope = envelope![Event, Attachment];
@@ -754,7 +1107,7 @@ mod tests {
         let mut limiter = EnvelopeLimiter::new(|s, q| mock.check(s, q));
         limiter.assume_event(DataCategory::Transaction);
         let enforcement = limiter.enforce(&mut envelope, &scoping()).unwrap();
-        let limits = enforcement.rate_limits;
+        let limits = enforcement.applied_limits;
 
         assert!(limits.is_limited());
         assert!(envelope.is_empty()); // obviously
@@ -772,7 +1125,7 @@ mod tests {
         let mut limiter = EnvelopeLimiter::new(|s, q| mock.check(s, q));
         limiter.assume_event(DataCategory::Error);
         let enforcement = limiter.enforce(&mut envelope, &scoping()).unwrap();
-        let limits = enforcement.rate_limits;
+        let limits = enforcement.applied_limits;
 
         assert!(limits.is_limited());
         assert!(envelope.is_empty());
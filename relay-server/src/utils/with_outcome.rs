@@ -36,6 +36,12 @@ where
     MailboxErrorBuilder: FnOnce(MailboxError) -> ResultError + 'static,
     ResultErrorBuilder: FnOnce(Error) -> ResultError + 'static,
 {
+    /// Wraps the send in a `send_with_outcome` span carrying the originating envelope's
+    /// `event_id`/`project_id`/`relay_id`, so a failed or slow upstream send can be correlated back
+    /// to the request that triggered it instead of appearing as an isolated log line. The
+    /// combinators here are plain `futures` 0.1, so the span is entered manually around each
+    /// synchronous portion of the chain rather than via `tracing::Instrument` (see `actors::keys`
+    /// for the same pattern).
     fn send_with_outcome_error(
         &self,
         message: Msg,
@@ -46,13 +52,32 @@ where
         result_error_builder: ResultErrorBuilder,
     ) -> ResponseFuture<Item, ResultError> {
         let envelope_context = *envelope_context;
+
+        let span = tracing::debug_span!(
+            "send_with_outcome",
+            event_id = ?envelope_context.event_id,
+            project_id = %envelope_context.project_id,
+            relay_id = %envelope_context.relay_id,
+            error_kind = tracing::field::Empty,
+        );
+        let _enter = span.enter();
+        let result_span = span.clone();
+
         let fut = self
             .send(message)
             .map_err(move |err| {
+                let _enter = span.enter();
+                span.record("error_kind", &"mailbox");
                 envelope_context.send_outcomes(outcome, outcome_producer);
                 mailbox_error_builder(err)
             })
-            .and_then(|result| result.map_err(|e| result_error_builder(e)));
+            .and_then(move |result| {
+                let _enter = result_span.enter();
+                result.map_err(|e| {
+                    result_span.record("error_kind", &"result");
+                    result_error_builder(e)
+                })
+            });
         Box::new(fut)
     }
 }
@@ -1,14 +1,42 @@
 use std::env;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
 
 use sentry;
-use log::LevelFilter;
+use chrono;
+use lazy_static::lazy_static;
+use log::{LevelFilter, Log, Metadata, Record};
 use failure::Error;
 use pretty_env_logger;
 use sentry::integrations::log as sentry_log;
+use serde_json::json;
 
-use smith_config::Config;
+use smith_config::{Config, LogSinkConfig, LogSinkKind};
 use smith_common::metrics;
 
+lazy_static! {
+    /// The live level filter for each sink `init_logging` wired up, in the same order as
+    /// `Config::log_sinks` returned them. `reload_log_filter` atomically swaps these in place so
+    /// `MultiLog` observes the new filter on its very next record, without rebuilding or
+    /// restarting any sink. Empty until `init_logging` has run.
+    static ref ACTIVE_FILTERS: Mutex<Vec<Arc<AtomicU8>>> = Mutex::new(Vec::new());
+}
+
+fn level_filter_to_u8(filter: LevelFilter) -> u8 {
+    filter as u8
+}
+
+fn u8_to_level_filter(value: u8) -> LevelFilter {
+    match value {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
 /// Print spawn infos to the log.
 pub fn dump_spawn_infos(config: &Config) {
     info!(
@@ -20,7 +48,75 @@ pub fn dump_spawn_infos(config: &Config) {
     info!("  log level: {}", config.log_level_filter());
 }
 
+/// Writes one NDJSON object per record to stdout -- `timestamp`, `level`, `target`, and `message`
+/// -- for shipping to a log collector that expects machine-parseable input rather than the
+/// `pretty_env_logger` sink's human-readable lines.
+struct JsonLogger;
+
+impl Log for JsonLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        // This sink's own level filter is already applied by `MultiLog` before a record reaches
+        // here, so there is nothing left to filter on.
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        println!(
+            "{}",
+            json!({
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+                "level": record.level().to_string(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            })
+        );
+    }
+
+    fn flush(&self) {}
+}
+
+/// Fans a record out to every configured sink whose own filter accepts it, in place of the single
+/// logger `sentry_log::init` otherwise expects. `enabled`/`log` re-check each sink's filter
+/// independently, so a terser sink (e.g. stdout at INFO) doesn't see records that a more verbose
+/// one (e.g. a JSON collector at DEBUG) does.
+///
+/// Each filter is an `Arc<AtomicU8>` shared with `ACTIVE_FILTERS` rather than a plain
+/// `LevelFilter`, so `reload_log_filter` can change it in place while Relay keeps running.
+struct MultiLog {
+    sinks: Vec<(Box<dyn Log>, Arc<AtomicU8>)>,
+}
+
+impl Log for MultiLog {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.sinks.iter().any(|(logger, filter)| {
+            let filter = u8_to_level_filter(filter.load(Ordering::Relaxed));
+            metadata.level() <= filter && logger.enabled(metadata)
+        })
+    }
+
+    fn log(&self, record: &Record) {
+        for (logger, filter) in &self.sinks {
+            let filter = u8_to_level_filter(filter.load(Ordering::Relaxed));
+            if record.level() <= filter && logger.enabled(record.metadata()) {
+                logger.log(record);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        for (logger, _) in &self.sinks {
+            logger.flush();
+        }
+    }
+}
+
 /// Initialize the logging system.
+///
+/// The sinks actually wired up come from `Config::log_sinks`, which isn't part of this snapshot
+/// (`smith_config` itself isn't present here) -- this assumes it returns a `Vec<LogSinkConfig>`,
+/// each pairing a `LogSinkKind` (`Stdout` or `Json`) with its own `LevelFilter`, and that it always
+/// includes a `Stdout` entry at `config.log_level_filter()` when no sinks are explicitly
+/// configured, so the default setup behaves exactly as before this change.
 pub fn init_logging(config: &Config) {
     sentry::init((
         config.sentry_dsn(),
@@ -66,14 +162,35 @@ pub fn init_logging(config: &Config) {
         );
     }
 
-    let mut log_builder = pretty_env_logger::formatted_builder().unwrap();
-    match env::var("RUST_LOG") {
-        Ok(rust_log) => log_builder.parse(&rust_log),
-        Err(_) => log_builder.filter_level(config.log_level_filter()),
-    };
+    let mut sinks: Vec<(Box<dyn Log>, Arc<AtomicU8>)> = Vec::new();
+    let mut filter_slots: Vec<Arc<AtomicU8>> = Vec::new();
+    let mut global_filter = LevelFilter::Off;
 
-    let log = Box::new(log_builder.build());
-    let global_filter = log.filter();
+    for LogSinkConfig { kind, filter } in config.log_sinks() {
+        let (logger, filter): (Box<dyn Log>, LevelFilter) = match kind {
+            LogSinkKind::Stdout => {
+                let mut log_builder = pretty_env_logger::formatted_builder().unwrap();
+                match env::var("RUST_LOG") {
+                    Ok(rust_log) => log_builder.parse(&rust_log),
+                    Err(_) => log_builder.filter_level(filter),
+                };
+                let logger = log_builder.build();
+                let filter = logger.filter();
+                (Box::new(logger), filter)
+            }
+            LogSinkKind::Json => (Box::new(JsonLogger), filter),
+        };
+
+        global_filter = global_filter.max(filter);
+        let slot = Arc::new(AtomicU8::new(level_filter_to_u8(filter)));
+        filter_slots.push(slot.clone());
+        sinks.push((logger, slot));
+    }
+
+    *ACTIVE_FILTERS.lock().unwrap() = filter_slots;
+
+    let log: Box<dyn Log> = Box::new(MultiLog { sinks });
+    log::set_max_level(global_filter);
 
     sentry_log::init(
         Some(log),
@@ -84,6 +201,47 @@ pub fn init_logging(config: &Config) {
     );
 }
 
+/// Re-reads `config`'s log section and atomically swaps in its sinks' level filters, so raising
+/// verbosity to debug a live incident doesn't require bouncing the relay and dropping in-flight
+/// connections. Only the filters change -- the sink list `init_logging` built (which kinds are
+/// active) is fixed for the process's lifetime.
+///
+/// Also raises or lowers `log::max_level()` to match, since that's the static ceiling the `log`
+/// crate's macros check before a record is even constructed -- without updating it too, a reload
+/// that raises verbosity above the level `init_logging` started at would have no effect.
+///
+/// A `Stdout` sink's `pretty_env_logger` logger keeps the `RUST_LOG` filter it was built with and
+/// is still consulted per record, so a reload can lower that sink's verbosity but cannot raise it
+/// above its startup level. Only `Json` sinks can be raised this way.
+///
+/// Nothing in this snapshot calls this yet: there's no `Controller` actor here to wire a SIGHUP
+/// handler into, and no admin endpoint for it. Whoever adds either should call this from it.
+pub fn reload_log_filter(config: &Config) -> Result<(), Error> {
+    let reloaded = Config::from_path(config.path())?;
+    let sinks = reloaded.log_sinks();
+
+    let active = ACTIVE_FILTERS.lock().unwrap();
+    if sinks.len() != active.len() {
+        failure::bail!(
+            "reloaded config has {} log sink(s), but {} were configured at startup -- \
+             changing the sink list itself requires a restart",
+            sinks.len(),
+            active.len()
+        );
+    }
+
+    let mut global_filter = LevelFilter::Off;
+    for (LogSinkConfig { filter, .. }, slot) in sinks.into_iter().zip(active.iter()) {
+        slot.store(level_filter_to_u8(filter), Ordering::Relaxed);
+        global_filter = global_filter.max(filter);
+    }
+
+    log::set_max_level(global_filter);
+    info!("reloaded log filter from {}", reloaded.path().display());
+
+    Ok(())
+}
+
 /// Initialize the metric system.
 pub fn init_metrics(config: &Config) -> Result<(), Error> {
     let addrs = config.statsd_addrs()?;